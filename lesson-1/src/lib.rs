@@ -9,3 +9,53 @@ pub use s04_memo::*;
 pub mod s05_apply_rule_again;
 pub use s05_apply_rule_again::*;
 pub mod s06_new_repr;
+pub mod s07_normalize;
+pub use s07_normalize::*;
+pub mod s08_join_graph;
+pub use s08_join_graph::*;
+pub mod s09_dpccp;
+pub use s09_dpccp::*;
+pub mod s10_dphyp;
+pub use s10_dphyp::*;
+pub mod s11_join_order_heuristics;
+pub use s11_join_order_heuristics::*;
+pub mod s12_physical;
+pub use s12_physical::*;
+pub mod s13_properties;
+pub use s13_properties::*;
+pub mod s14_budget;
+pub use s14_budget::*;
+pub mod s15_parallel;
+pub use s15_parallel::*;
+pub mod s16_bindings;
+pub use s16_bindings::*;
+pub mod s17_plan_space;
+pub use s17_plan_space::*;
+pub mod s18_dot;
+pub use s18_dot::*;
+pub mod s19_explain;
+pub use s19_explain::*;
+pub mod s20_sexp;
+pub use s20_sexp::*;
+pub mod s21_sql;
+pub use s21_sql::*;
+pub mod s22_catalog;
+pub use s22_catalog::*;
+pub mod s23_index;
+pub use s23_index::*;
+pub mod s24_keys;
+pub use s24_keys::*;
+pub mod s25_join_elimination;
+pub use s25_join_elimination::*;
+pub mod s26_executor;
+pub use s26_executor::*;
+pub mod s27_rule_testing;
+pub use s27_rule_testing::*;
+pub mod s28_plan_generator;
+pub use s28_plan_generator::*;
+pub mod s29_vectorized;
+pub use s29_vectorized::*;
+pub mod s30_calibration;
+pub use s30_calibration::*;
+#[cfg(test)]
+mod test_fixtures;
@@ -1,48 +1,159 @@
 use std::sync::Arc;
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct TableId(pub usize);
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct Scan {
     pub table: TableId,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// Reads the rows of `table` whose leading `index` columns are equal to `prefix`, in the order of
+/// the index columns. An empty prefix reads the whole table.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct IndexScan {
+    pub table: TableId,
+    pub index: String,
+    pub prefix: Vec<i64>,
+}
+
+/// Reads the rows of `table` whose leading `index` columns are equal to the `keys` columns of the
+/// current row of the outer side. Only valid as the right side of an `IndexNestedLoopJoin`.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct IndexLookup {
+    pub table: TableId,
+    pub index: String,
+    pub keys: Vec<usize>,
+}
+
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum JoinType {
+    Inner,
+    /// Rows of the left side that have a match on the right side.
+    LeftSemi,
+    /// Rows of the left side that have no match on the right side.
+    LeftAnti,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct Join {
+    pub join_type: JoinType,
     pub left: Arc<RelNode>,
     pub right: Arc<RelNode>,
     pub cond: Arc<RelNode>,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct Filter {
     pub child: Arc<RelNode>,
     pub predicate: Arc<RelNode>,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Project {
+    pub child: Arc<RelNode>,
+    pub exprs: Vec<Arc<RelNode>>,
+}
+
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct SortKey {
+    pub column: usize,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Sort {
+    pub child: Arc<RelNode>,
+    pub keys: Vec<SortKey>,
+}
+
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum AggFunc {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+/// An aggregate function over one input column, or over all rows for `COUNT(*)`.
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct AggCall {
+    pub func: AggFunc,
+    pub column: Option<usize>,
+}
+
+/// Outputs the `group_by` columns, followed by one column per aggregate.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Aggregate {
+    pub child: Arc<RelNode>,
+    pub group_by: Vec<usize>,
+    pub aggs: Vec<AggCall>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Limit {
+    pub child: Arc<RelNode>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct EqPred {
     pub left: Arc<RelNode>,
     pub right: Arc<RelNode>,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct AndPred {
+    pub left: Arc<RelNode>,
+    pub right: Arc<RelNode>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct OrPred {
+    pub left: Arc<RelNode>,
+    pub right: Arc<RelNode>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct AddPred {
+    pub left: Arc<RelNode>,
+    pub right: Arc<RelNode>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct ColumnRefPred {
     pub column: usize,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct ConstPred {
     pub value: i64,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub enum RelNode {
     Scan(Scan),
     Join(Join),
     Filter(Filter),
+    Project(Project),
+    Sort(Sort),
+    Aggregate(Aggregate),
+    Limit(Limit),
+    // Physical operators share the payload with their logical counterparts. `Project`, `Sort`,
+    // `Aggregate` and `Limit` are both logical and physical.
+    TableScan(Scan),
+    PhysicalFilter(Filter),
+    HashJoin(Join),
+    NestedLoopJoin(Join),
+    SortMergeJoin(Join),
+    IndexScan(IndexScan),
+    IndexLookup(IndexLookup),
+    /// The right side is an `IndexLookup`, evaluated once per row of the left side.
+    IndexNestedLoopJoin(Join),
     Eq(EqPred),
+    And(AndPred),
+    Or(OrPred),
+    Add(AddPred),
     ColumnRef(ColumnRefPred),
     Const(ConstPred),
 }
@@ -62,14 +173,57 @@ pub fn join(
     left: impl Into<Arc<RelNode>>,
     right: impl Into<Arc<RelNode>>,
     cond: impl Into<Arc<RelNode>>,
+) -> RelNode {
+    join_with_type(JoinType::Inner, left, right, cond)
+}
+
+pub fn join_with_type(
+    join_type: JoinType,
+    left: impl Into<Arc<RelNode>>,
+    right: impl Into<Arc<RelNode>>,
+    cond: impl Into<Arc<RelNode>>,
 ) -> RelNode {
     RelNode::Join(Join {
+        join_type,
         left: left.into(),
         right: right.into(),
         cond: cond.into(),
     })
 }
 
+pub fn project(child: impl Into<Arc<RelNode>>, exprs: Vec<Arc<RelNode>>) -> RelNode {
+    RelNode::Project(Project {
+        child: child.into(),
+        exprs,
+    })
+}
+
+pub fn sort(child: impl Into<Arc<RelNode>>, keys: Vec<SortKey>) -> RelNode {
+    RelNode::Sort(Sort {
+        child: child.into(),
+        keys,
+    })
+}
+
+pub fn aggregate(
+    child: impl Into<Arc<RelNode>>,
+    group_by: Vec<usize>,
+    aggs: Vec<AggCall>,
+) -> RelNode {
+    RelNode::Aggregate(Aggregate {
+        child: child.into(),
+        group_by,
+        aggs,
+    })
+}
+
+pub fn limit(child: impl Into<Arc<RelNode>>, limit: usize) -> RelNode {
+    RelNode::Limit(Limit {
+        child: child.into(),
+        limit,
+    })
+}
+
 pub fn eq_pred(left: impl Into<Arc<RelNode>>, right: impl Into<Arc<RelNode>>) -> RelNode {
     RelNode::Eq(EqPred {
         left: left.into(),
@@ -77,6 +231,27 @@ pub fn eq_pred(left: impl Into<Arc<RelNode>>, right: impl Into<Arc<RelNode>>) ->
     })
 }
 
+pub fn and_pred(left: impl Into<Arc<RelNode>>, right: impl Into<Arc<RelNode>>) -> RelNode {
+    RelNode::And(AndPred {
+        left: left.into(),
+        right: right.into(),
+    })
+}
+
+pub fn or_pred(left: impl Into<Arc<RelNode>>, right: impl Into<Arc<RelNode>>) -> RelNode {
+    RelNode::Or(OrPred {
+        left: left.into(),
+        right: right.into(),
+    })
+}
+
+pub fn add_pred(left: impl Into<Arc<RelNode>>, right: impl Into<Arc<RelNode>>) -> RelNode {
+    RelNode::Add(AddPred {
+        left: left.into(),
+        right: right.into(),
+    })
+}
+
 pub fn column_ref_pred(idx: usize) -> RelNode {
     RelNode::ColumnRef(ColumnRefPred { column: idx })
 }
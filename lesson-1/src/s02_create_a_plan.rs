@@ -15,6 +15,9 @@ pub fn plan() -> RelNode {
 
 pub fn join_commute(node: Arc<RelNode>) -> Option<Arc<RelNode>> {
     if let RelNode::Join(ref a) = &*node {
+        if a.join_type != JoinType::Inner {
+            return None;
+        }
         // TODO: rewrite the condition
         return Some(join(a.right.clone(), a.left.clone(), a.cond.clone()).into());
     }
@@ -24,6 +27,9 @@ pub fn join_commute(node: Arc<RelNode>) -> Option<Arc<RelNode>> {
 pub fn join_assoc(node: Arc<RelNode>) -> Option<Arc<RelNode>> {
     if let RelNode::Join(ref a) = &*node {
         if let RelNode::Join(b) = &*a.left {
+            if a.join_type != JoinType::Inner || b.join_type != JoinType::Inner {
+                return None;
+            }
             return Some(
                 join(
                     b.left.clone(),
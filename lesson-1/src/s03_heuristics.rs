@@ -17,7 +17,7 @@ pub fn apply_rule_bottom_up(
             children.push(child);
         }
         let rel = Arc::new(node.clone_with_children(children));
-        rule(rel.clone()).unwrap_or_else(|| rel)
+        rule(rel.clone()).unwrap_or(rel)
     }
     apply_rule_bottom_up_inner(node, &rule)
 }
@@ -35,6 +35,28 @@ impl Scan {
     }
 }
 
+impl IndexScan {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        let _ = children;
+        self.clone()
+    }
+}
+
+impl IndexLookup {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        let _ = children;
+        self.clone()
+    }
+}
+
 impl Filter {
     pub fn children(&self) -> Vec<Arc<RelNode>> {
         vec![self.child.clone(), self.predicate.clone()]
@@ -55,6 +77,7 @@ impl Join {
 
     pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
         Self {
+            join_type: self.join_type,
             left: children[0].clone(),
             right: children[1].clone(),
             cond: children[2].clone(),
@@ -62,6 +85,61 @@ impl Join {
     }
 }
 
+impl Project {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        let mut children = vec![self.child.clone()];
+        children.extend(self.exprs.iter().cloned());
+        children
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            child: children[0].clone(),
+            exprs: children[1..].to_vec(),
+        }
+    }
+}
+
+impl Sort {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.child.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            child: children[0].clone(),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl Aggregate {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.child.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            child: children[0].clone(),
+            group_by: self.group_by.clone(),
+            aggs: self.aggs.clone(),
+        }
+    }
+}
+
+impl Limit {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.child.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            child: children[0].clone(),
+            limit: self.limit,
+        }
+    }
+}
+
 impl EqPred {
     pub fn children(&self) -> Vec<Arc<RelNode>> {
         vec![self.left.clone(), self.right.clone()]
@@ -75,6 +153,45 @@ impl EqPred {
     }
 }
 
+impl AndPred {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            left: children[0].clone(),
+            right: children[1].clone(),
+        }
+    }
+}
+
+impl OrPred {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            left: children[0].clone(),
+            right: children[1].clone(),
+        }
+    }
+}
+
+impl AddPred {
+    pub fn children(&self) -> Vec<Arc<RelNode>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    pub fn clone_with_children(&self, children: Vec<Arc<RelNode>>) -> Self {
+        Self {
+            left: children[0].clone(),
+            right: children[1].clone(),
+        }
+    }
+}
+
 impl ColumnRefPred {
     pub fn children(&self) -> Vec<Arc<RelNode>> {
         vec![]
@@ -102,10 +219,23 @@ impl ConstPred {
 impl RelNode {
     pub fn children(&self) -> Vec<Arc<RelNode>> {
         match self {
-            RelNode::Scan(scan) => scan.children(),
-            RelNode::Join(join) => join.children(),
-            RelNode::Filter(filter) => filter.children(),
+            RelNode::Scan(scan) | RelNode::TableScan(scan) => scan.children(),
+            RelNode::Join(join)
+            | RelNode::HashJoin(join)
+            | RelNode::NestedLoopJoin(join)
+            | RelNode::SortMergeJoin(join)
+            | RelNode::IndexNestedLoopJoin(join) => join.children(),
+            RelNode::IndexScan(scan) => scan.children(),
+            RelNode::IndexLookup(lookup) => lookup.children(),
+            RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => filter.children(),
+            RelNode::Project(project) => project.children(),
+            RelNode::Sort(sort) => sort.children(),
+            RelNode::Aggregate(aggregate) => aggregate.children(),
+            RelNode::Limit(limit) => limit.children(),
             RelNode::Eq(eq) => eq.children(),
+            RelNode::And(and) => and.children(),
+            RelNode::Or(or) => or.children(),
+            RelNode::Add(add) => add.children(),
             RelNode::ColumnRef(column_ref) => column_ref.children(),
             RelNode::Const(const_pred) => const_pred.children(),
         }
@@ -116,7 +246,34 @@ impl RelNode {
             RelNode::Scan(scan) => RelNode::Scan(scan.clone_with_children(children)),
             RelNode::Join(join) => RelNode::Join(join.clone_with_children(children)),
             RelNode::Filter(filter) => RelNode::Filter(filter.clone_with_children(children)),
+            RelNode::Project(project) => RelNode::Project(project.clone_with_children(children)),
+            RelNode::Sort(sort) => RelNode::Sort(sort.clone_with_children(children)),
+            RelNode::Aggregate(aggregate) => {
+                RelNode::Aggregate(aggregate.clone_with_children(children))
+            }
+            RelNode::Limit(limit) => RelNode::Limit(limit.clone_with_children(children)),
+            RelNode::TableScan(scan) => RelNode::TableScan(scan.clone_with_children(children)),
+            RelNode::PhysicalFilter(filter) => {
+                RelNode::PhysicalFilter(filter.clone_with_children(children))
+            }
+            RelNode::HashJoin(join) => RelNode::HashJoin(join.clone_with_children(children)),
+            RelNode::NestedLoopJoin(join) => {
+                RelNode::NestedLoopJoin(join.clone_with_children(children))
+            }
+            RelNode::SortMergeJoin(join) => {
+                RelNode::SortMergeJoin(join.clone_with_children(children))
+            }
+            RelNode::IndexScan(scan) => RelNode::IndexScan(scan.clone_with_children(children)),
+            RelNode::IndexLookup(lookup) => {
+                RelNode::IndexLookup(lookup.clone_with_children(children))
+            }
+            RelNode::IndexNestedLoopJoin(join) => {
+                RelNode::IndexNestedLoopJoin(join.clone_with_children(children))
+            }
             RelNode::Eq(eq) => RelNode::Eq(eq.clone_with_children(children)),
+            RelNode::And(and) => RelNode::And(and.clone_with_children(children)),
+            RelNode::Or(or) => RelNode::Or(or.clone_with_children(children)),
+            RelNode::Add(add) => RelNode::Add(add.clone_with_children(children)),
             RelNode::ColumnRef(column_ref) => {
                 RelNode::ColumnRef(column_ref.clone_with_children(children))
             }
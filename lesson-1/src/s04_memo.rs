@@ -1,50 +1,227 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use super::*;
 
 pub type MemoScan = Scan;
 pub type MemoColumnRefPred = ColumnRefPred;
 pub type MemoConstPred = ConstPred;
+pub type MemoIndexScan = IndexScan;
+pub type MemoIndexLookup = IndexLookup;
 
-#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq)]
-pub struct GroupId(usize);
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct GroupId(pub usize);
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct MemoJoin {
+    pub join_type: JoinType,
     pub left: GroupId,
     pub right: GroupId,
     pub cond: GroupId,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct MemoFilter {
     pub child: GroupId,
     pub predicate: GroupId,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoProject {
+    pub child: GroupId,
+    pub exprs: Vec<GroupId>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoSort {
+    pub child: GroupId,
+    pub keys: Vec<SortKey>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoAggregate {
+    pub child: GroupId,
+    pub group_by: Vec<usize>,
+    pub aggs: Vec<AggCall>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoLimit {
+    pub child: GroupId,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct MemoEqPred {
     pub left: GroupId,
     pub right: GroupId,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoAndPred {
+    pub left: GroupId,
+    pub right: GroupId,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoOrPred {
+    pub left: GroupId,
+    pub right: GroupId,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct MemoAddPred {
+    pub left: GroupId,
+    pub right: GroupId,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub enum MemoRelNode {
     Scan(MemoScan),
     Join(MemoJoin),
     Filter(MemoFilter),
+    Project(MemoProject),
+    Sort(MemoSort),
+    Aggregate(MemoAggregate),
+    Limit(MemoLimit),
+    TableScan(MemoScan),
+    PhysicalFilter(MemoFilter),
+    HashJoin(MemoJoin),
+    NestedLoopJoin(MemoJoin),
+    SortMergeJoin(MemoJoin),
+    IndexScan(MemoIndexScan),
+    IndexLookup(MemoIndexLookup),
+    IndexNestedLoopJoin(MemoJoin),
     Eq(MemoEqPred),
+    And(MemoAndPred),
+    Or(MemoOrPred),
+    Add(MemoAddPred),
     ColumnRef(MemoColumnRefPred),
     Const(MemoConstPred),
 }
 
+impl MemoJoin {
+    fn clone_with_children(&self, children: Vec<GroupId>) -> Self {
+        Self {
+            join_type: self.join_type,
+            left: children[0],
+            right: children[1],
+            cond: children[2],
+        }
+    }
+}
+
+impl MemoRelNode {
+    pub fn children(&self) -> Vec<GroupId> {
+        match self {
+            MemoRelNode::Scan(_)
+            | MemoRelNode::TableScan(_)
+            | MemoRelNode::IndexScan(_)
+            | MemoRelNode::IndexLookup(_)
+            | MemoRelNode::ColumnRef(_)
+            | MemoRelNode::Const(_) => vec![],
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join) => vec![join.left, join.right, join.cond],
+            MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter) => {
+                vec![filter.child, filter.predicate]
+            }
+            MemoRelNode::Project(project) => {
+                let mut children = vec![project.child];
+                children.extend(project.exprs.iter().copied());
+                children
+            }
+            MemoRelNode::Sort(sort) => vec![sort.child],
+            MemoRelNode::Aggregate(aggregate) => vec![aggregate.child],
+            MemoRelNode::Limit(limit) => vec![limit.child],
+            MemoRelNode::Eq(eq) => vec![eq.left, eq.right],
+            MemoRelNode::And(and) => vec![and.left, and.right],
+            MemoRelNode::Or(or) => vec![or.left, or.right],
+            MemoRelNode::Add(add) => vec![add.left, add.right],
+        }
+    }
+
+    pub fn clone_with_children(&self, children: Vec<GroupId>) -> Self {
+        match self {
+            MemoRelNode::Scan(_)
+            | MemoRelNode::TableScan(_)
+            | MemoRelNode::IndexScan(_)
+            | MemoRelNode::IndexLookup(_)
+            | MemoRelNode::ColumnRef(_)
+            | MemoRelNode::Const(_) => self.clone(),
+            MemoRelNode::Join(join) => MemoRelNode::Join(join.clone_with_children(children)),
+            MemoRelNode::HashJoin(join) => {
+                MemoRelNode::HashJoin(join.clone_with_children(children))
+            }
+            MemoRelNode::NestedLoopJoin(join) => {
+                MemoRelNode::NestedLoopJoin(join.clone_with_children(children))
+            }
+            MemoRelNode::SortMergeJoin(join) => {
+                MemoRelNode::SortMergeJoin(join.clone_with_children(children))
+            }
+            MemoRelNode::IndexNestedLoopJoin(join) => {
+                MemoRelNode::IndexNestedLoopJoin(join.clone_with_children(children))
+            }
+            MemoRelNode::Filter(_) => MemoRelNode::Filter(MemoFilter {
+                child: children[0],
+                predicate: children[1],
+            }),
+            MemoRelNode::PhysicalFilter(_) => MemoRelNode::PhysicalFilter(MemoFilter {
+                child: children[0],
+                predicate: children[1],
+            }),
+            MemoRelNode::Project(_) => MemoRelNode::Project(MemoProject {
+                child: children[0],
+                exprs: children[1..].to_vec(),
+            }),
+            MemoRelNode::Sort(sort) => MemoRelNode::Sort(MemoSort {
+                child: children[0],
+                keys: sort.keys.clone(),
+            }),
+            MemoRelNode::Aggregate(aggregate) => MemoRelNode::Aggregate(MemoAggregate {
+                child: children[0],
+                group_by: aggregate.group_by.clone(),
+                aggs: aggregate.aggs.clone(),
+            }),
+            MemoRelNode::Limit(limit) => MemoRelNode::Limit(MemoLimit {
+                child: children[0],
+                limit: limit.limit,
+            }),
+            MemoRelNode::Eq(_) => MemoRelNode::Eq(MemoEqPred {
+                left: children[0],
+                right: children[1],
+            }),
+            MemoRelNode::And(_) => MemoRelNode::And(MemoAndPred {
+                left: children[0],
+                right: children[1],
+            }),
+            MemoRelNode::Or(_) => MemoRelNode::Or(MemoOrPred {
+                left: children[0],
+                right: children[1],
+            }),
+            MemoRelNode::Add(_) => MemoRelNode::Add(MemoAddPred {
+                left: children[0],
+                right: children[1],
+            }),
+        }
+    }
+}
+
 pub struct Memo {
     groups: Vec<Vec<MemoRelNode>>,
     expr_to_group: HashMap<MemoRelNode, GroupId>,
+    /// When two groups are found to be equivalent, the second one is merged into the first one and
+    /// we record it here. Expressions may still refer to the merged group id.
+    merged_groups: HashMap<GroupId, GroupId>,
 }
 
 impl Memo {
     pub fn add_expr(&mut self, expr: MemoRelNode) -> GroupId {
+        let expr = self.reduce_expr(expr);
         if let Some(group_id) = self.get_group(expr.clone()) {
             return group_id;
         }
@@ -55,18 +232,25 @@ impl Memo {
     }
 
     pub fn get_group(&self, expr: MemoRelNode) -> Option<GroupId> {
-        self.expr_to_group.get(&expr).copied()
+        let expr = self.reduce_expr(expr);
+        self.expr_to_group
+            .get(&expr)
+            .map(|group| self.reduce_group(*group))
     }
 
     pub fn new() -> Self {
         Self {
             groups: vec![],
             expr_to_group: HashMap::new(),
+            merged_groups: HashMap::new(),
         }
     }
 
     pub fn dump(&self) {
         for (i, group) in self.groups.iter().enumerate() {
+            if group.is_empty() {
+                continue;
+            }
             println!("Group {}", i);
             for expr in group {
                 println!("  {:?}", expr);
@@ -75,58 +259,325 @@ impl Memo {
     }
 
     pub fn get_all_exprs_in_group(&self, group: GroupId) -> Vec<MemoRelNode> {
-        self.groups[group.0].iter().cloned().collect()
+        self.groups[self.reduce_group(group).0].to_vec()
+    }
+
+    /// The expressions of the group with their children reduced, without duplicates.
+    pub fn get_distinct_exprs_in_group(&self, group: GroupId) -> Vec<MemoRelNode> {
+        let mut seen = HashSet::new();
+        let mut exprs = vec![];
+        for expr in &self.groups[self.reduce_group(group).0] {
+            let expr = self.reduce_expr(expr.clone());
+            if seen.insert(expr.clone()) {
+                exprs.push(expr);
+            }
+        }
+        exprs
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.groups.len() - self.merged_groups.len()
+    }
+
+    pub fn num_exprs(&self) -> usize {
+        self.groups.iter().map(|group| group.len()).sum()
+    }
+
+    /// The expressions of each group, and which groups were merged into which.
+    pub fn into_parts(self) -> (Vec<Vec<MemoRelNode>>, HashMap<GroupId, GroupId>) {
+        (self.groups, self.merged_groups)
+    }
+
+    pub fn from_parts(
+        groups: Vec<Vec<MemoRelNode>>,
+        merged_groups: HashMap<GroupId, GroupId>,
+    ) -> Self {
+        let mut expr_to_group = HashMap::new();
+        for (idx, group) in groups.iter().enumerate() {
+            for expr in group {
+                expr_to_group.insert(expr.clone(), GroupId(idx));
+            }
+        }
+        Self {
+            groups,
+            expr_to_group,
+            merged_groups,
+        }
+    }
+
+    /// All groups that have not been merged into another group.
+    pub fn group_ids(&self) -> Vec<GroupId> {
+        (0..self.groups.len())
+            .map(GroupId)
+            .filter(|group| !self.merged_groups.contains_key(group))
+            .collect()
+    }
+
+    /// Follow the merge chain and return the group that currently holds the expressions.
+    pub fn reduce_group(&self, mut group: GroupId) -> GroupId {
+        while let Some(&merged_into) = self.merged_groups.get(&group) {
+            group = merged_into;
+        }
+        group
     }
 
+    /// The expression with its children reduced, and its operands in canonical order.
+    pub fn reduce_expr(&self, expr: MemoRelNode) -> MemoRelNode {
+        let children = expr
+            .children()
+            .into_iter()
+            .map(|child| self.reduce_group(child))
+            .collect();
+        canonicalize_memo_expr(expr.clone_with_children(children))
+    }
+
+    /// Merge `group2` into `group1` and return the group id that survives. Expressions that refer
+    /// to `group2` are canonicalized again, and if that makes two groups hold the same expression,
+    /// they are merged too (congruence closure).
     pub fn merge_group(&mut self, group1: GroupId, group2: GroupId) -> GroupId {
-        unimplemented!()
+        let mut pending = vec![(group1, group2)];
+        while let Some((group1, group2)) = pending.pop() {
+            let group1 = self.reduce_group(group1);
+            let group2 = self.reduce_group(group2);
+            if group1 == group2 {
+                continue;
+            }
+            let exprs = std::mem::take(&mut self.groups[group2.0]);
+            self.groups[group1.0].extend(exprs);
+            self.merged_groups.insert(group2, group1);
+            let mut touched = vec![group1];
+            // in order, so that the same groups survive every time
+            let mut parents = self
+                .expr_to_group
+                .keys()
+                .filter(|expr| expr.children().contains(&group2))
+                .cloned()
+                .collect::<Vec<_>>();
+            parents.sort();
+            for parent in parents {
+                let group = self.expr_to_group.remove(&parent).unwrap();
+                let parent = self.reduce_expr(parent);
+                match self.expr_to_group.get(&parent) {
+                    Some(&existing) => pending.push((existing, group)),
+                    None => {
+                        self.expr_to_group.insert(parent, group);
+                    }
+                }
+                touched.push(group);
+            }
+            // keep each group free of duplicates after its children were merged
+            for group in touched {
+                let group = self.reduce_group(group);
+                let mut exprs = vec![];
+                for expr in std::mem::take(&mut self.groups[group.0]) {
+                    let expr = self.reduce_expr(expr);
+                    if !exprs.contains(&expr) {
+                        exprs.push(expr);
+                    }
+                }
+                self.groups[group.0] = exprs;
+            }
+        }
+        self.reduce_group(group1)
+    }
+
+    /// Add an equivalent expression to an existing group. If the expression is already in another
+    /// group, the two groups are merged. Returns the group id that holds the expression.
+    pub fn add_expr_to_group(&mut self, group: GroupId, expr: MemoRelNode) -> GroupId {
+        let expr = self.reduce_expr(expr);
+        if let Some(existing) = self.get_group(expr.clone()) {
+            return self.merge_group(group, existing);
+        }
+        let group = self.reduce_group(group);
+        self.groups[group.0].push(expr.clone());
+        self.expr_to_group.insert(expr, group);
+        group
+    }
+}
+
+impl Default for Memo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Order the operands of commutative operators by group id, so that `#1 = #3` and `#3 = #1` are
+/// deduplicated into the same group.
+pub fn canonicalize_memo_expr(expr: MemoRelNode) -> MemoRelNode {
+    fn ordered(left: GroupId, right: GroupId) -> (GroupId, GroupId) {
+        (left.min(right), left.max(right))
+    }
+    match expr {
+        MemoRelNode::Eq(MemoEqPred { left, right }) => {
+            let (left, right) = ordered(left, right);
+            MemoRelNode::Eq(MemoEqPred { left, right })
+        }
+        MemoRelNode::And(MemoAndPred { left, right }) => {
+            let (left, right) = ordered(left, right);
+            MemoRelNode::And(MemoAndPred { left, right })
+        }
+        MemoRelNode::Or(MemoOrPred { left, right }) => {
+            let (left, right) = ordered(left, right);
+            MemoRelNode::Or(MemoOrPred { left, right })
+        }
+        MemoRelNode::Add(MemoAddPred { left, right }) => {
+            let (left, right) = ordered(left, right);
+            MemoRelNode::Add(MemoAddPred { left, right })
+        }
+        expr => expr,
+    }
+}
+
+fn memorize_join(memo: &mut Memo, join: &Join) -> MemoJoin {
+    MemoJoin {
+        join_type: join.join_type,
+        left: memorize_rel(memo, join.left.clone()),
+        right: memorize_rel(memo, join.right.clone()),
+        cond: memorize_rel(memo, join.cond.clone()),
     }
+}
 
-    pub fn add_expr_to_group(&mut self, group: GroupId, expr: MemoRelNode) {
-        unimplemented!()
+fn memorize_filter(memo: &mut Memo, filter: &Filter) -> MemoFilter {
+    MemoFilter {
+        child: memorize_rel(memo, filter.child.clone()),
+        predicate: memorize_rel(memo, filter.predicate.clone()),
     }
 }
 
 pub fn memorize_rel(memo: &mut Memo, rel: Arc<RelNode>) -> GroupId {
     let rel = match &*rel {
         RelNode::Scan(scan) => MemoRelNode::Scan(scan.clone()),
-        RelNode::Join(join) => MemoRelNode::Join(MemoJoin {
-            left: memorize_rel(memo, join.left.clone()),
-            right: memorize_rel(memo, join.right.clone()),
-            cond: memorize_rel(memo, join.cond.clone()),
+        RelNode::Join(join) => MemoRelNode::Join(memorize_join(memo, join)),
+        RelNode::Filter(filter) => MemoRelNode::Filter(memorize_filter(memo, filter)),
+        RelNode::Project(project) => MemoRelNode::Project(MemoProject {
+            child: memorize_rel(memo, project.child.clone()),
+            exprs: project
+                .exprs
+                .iter()
+                .map(|expr| memorize_rel(memo, expr.clone()))
+                .collect(),
+        }),
+        RelNode::Sort(sort) => MemoRelNode::Sort(MemoSort {
+            child: memorize_rel(memo, sort.child.clone()),
+            keys: sort.keys.clone(),
         }),
-        RelNode::Filter(filter) => MemoRelNode::Filter(MemoFilter {
-            child: memorize_rel(memo, filter.child.clone()),
-            predicate: memorize_rel(memo, filter.predicate.clone()),
+        RelNode::Aggregate(aggregate) => MemoRelNode::Aggregate(MemoAggregate {
+            child: memorize_rel(memo, aggregate.child.clone()),
+            group_by: aggregate.group_by.clone(),
+            aggs: aggregate.aggs.clone(),
         }),
+        RelNode::Limit(limit) => MemoRelNode::Limit(MemoLimit {
+            child: memorize_rel(memo, limit.child.clone()),
+            limit: limit.limit,
+        }),
+        RelNode::TableScan(scan) => MemoRelNode::TableScan(scan.clone()),
+        RelNode::PhysicalFilter(filter) => {
+            MemoRelNode::PhysicalFilter(memorize_filter(memo, filter))
+        }
+        RelNode::HashJoin(join) => MemoRelNode::HashJoin(memorize_join(memo, join)),
+        RelNode::NestedLoopJoin(join) => MemoRelNode::NestedLoopJoin(memorize_join(memo, join)),
+        RelNode::SortMergeJoin(join) => MemoRelNode::SortMergeJoin(memorize_join(memo, join)),
+        RelNode::IndexScan(scan) => MemoRelNode::IndexScan(scan.clone()),
+        RelNode::IndexLookup(lookup) => MemoRelNode::IndexLookup(lookup.clone()),
+        RelNode::IndexNestedLoopJoin(join) => {
+            MemoRelNode::IndexNestedLoopJoin(memorize_join(memo, join))
+        }
         RelNode::Eq(eq) => MemoRelNode::Eq(MemoEqPred {
             left: memorize_rel(memo, eq.left.clone()),
             right: memorize_rel(memo, eq.right.clone()),
         }),
+        RelNode::And(and) => MemoRelNode::And(MemoAndPred {
+            left: memorize_rel(memo, and.left.clone()),
+            right: memorize_rel(memo, and.right.clone()),
+        }),
+        RelNode::Or(or) => MemoRelNode::Or(MemoOrPred {
+            left: memorize_rel(memo, or.left.clone()),
+            right: memorize_rel(memo, or.right.clone()),
+        }),
+        RelNode::Add(add) => MemoRelNode::Add(MemoAddPred {
+            left: memorize_rel(memo, add.left.clone()),
+            right: memorize_rel(memo, add.right.clone()),
+        }),
         RelNode::ColumnRef(column_ref) => MemoRelNode::ColumnRef(column_ref.clone()),
         RelNode::Const(const_pred) => MemoRelNode::Const(const_pred.clone()),
         // ... doesn't seem maintainable
     };
-    memo.add_expr(rel)
+    memo.add_expr(canonicalize_memo_expr(rel))
+}
+
+fn bind_join(memo: &Memo, join: &MemoJoin) -> Join {
+    Join {
+        join_type: join.join_type,
+        left: generate_one_binding(memo, join.left),
+        right: generate_one_binding(memo, join.right),
+        cond: generate_one_binding(memo, join.cond),
+    }
+}
+
+fn bind_filter(memo: &Memo, filter: &MemoFilter) -> Filter {
+    Filter {
+        child: generate_one_binding(memo, filter.child),
+        predicate: generate_one_binding(memo, filter.predicate),
+    }
 }
 
 pub fn generate_one_binding(memo: &Memo, group: GroupId) -> Arc<RelNode> {
-    let expr = &memo.groups[group.0][0];
+    let expr = &memo.groups[memo.reduce_group(group).0][0];
     match expr {
         MemoRelNode::Scan(scan) => Arc::new(RelNode::Scan(scan.clone())),
-        MemoRelNode::Join(join) => Arc::new(RelNode::Join(Join {
-            left: generate_one_binding(memo, join.left),
-            right: generate_one_binding(memo, join.right),
-            cond: generate_one_binding(memo, join.cond),
+        MemoRelNode::Join(join) => Arc::new(RelNode::Join(bind_join(memo, join))),
+        MemoRelNode::Filter(filter) => Arc::new(RelNode::Filter(bind_filter(memo, filter))),
+        MemoRelNode::Project(project) => Arc::new(RelNode::Project(Project {
+            child: generate_one_binding(memo, project.child),
+            exprs: project
+                .exprs
+                .iter()
+                .map(|expr| generate_one_binding(memo, *expr))
+                .collect(),
+        })),
+        MemoRelNode::Sort(sort) => Arc::new(RelNode::Sort(Sort {
+            child: generate_one_binding(memo, sort.child),
+            keys: sort.keys.clone(),
+        })),
+        MemoRelNode::Aggregate(aggregate) => Arc::new(RelNode::Aggregate(Aggregate {
+            child: generate_one_binding(memo, aggregate.child),
+            group_by: aggregate.group_by.clone(),
+            aggs: aggregate.aggs.clone(),
         })),
-        MemoRelNode::Filter(filter) => Arc::new(RelNode::Filter(Filter {
-            child: generate_one_binding(memo, filter.child),
-            predicate: generate_one_binding(memo, filter.predicate),
+        MemoRelNode::Limit(limit) => Arc::new(RelNode::Limit(Limit {
+            child: generate_one_binding(memo, limit.child),
+            limit: limit.limit,
         })),
+        MemoRelNode::TableScan(scan) => Arc::new(RelNode::TableScan(scan.clone())),
+        MemoRelNode::PhysicalFilter(filter) => {
+            Arc::new(RelNode::PhysicalFilter(bind_filter(memo, filter)))
+        }
+        MemoRelNode::HashJoin(join) => Arc::new(RelNode::HashJoin(bind_join(memo, join))),
+        MemoRelNode::NestedLoopJoin(join) => {
+            Arc::new(RelNode::NestedLoopJoin(bind_join(memo, join)))
+        }
+        MemoRelNode::SortMergeJoin(join) => Arc::new(RelNode::SortMergeJoin(bind_join(memo, join))),
+        MemoRelNode::IndexScan(scan) => Arc::new(RelNode::IndexScan(scan.clone())),
+        MemoRelNode::IndexLookup(lookup) => Arc::new(RelNode::IndexLookup(lookup.clone())),
+        MemoRelNode::IndexNestedLoopJoin(join) => {
+            Arc::new(RelNode::IndexNestedLoopJoin(bind_join(memo, join)))
+        }
         MemoRelNode::Eq(eq) => Arc::new(RelNode::Eq(EqPred {
             left: generate_one_binding(memo, eq.left),
             right: generate_one_binding(memo, eq.right),
         })),
+        MemoRelNode::And(and) => Arc::new(RelNode::And(AndPred {
+            left: generate_one_binding(memo, and.left),
+            right: generate_one_binding(memo, and.right),
+        })),
+        MemoRelNode::Or(or) => Arc::new(RelNode::Or(OrPred {
+            left: generate_one_binding(memo, or.left),
+            right: generate_one_binding(memo, or.right),
+        })),
+        MemoRelNode::Add(add) => Arc::new(RelNode::Add(AddPred {
+            left: generate_one_binding(memo, add.left),
+            right: generate_one_binding(memo, add.right),
+        })),
         MemoRelNode::ColumnRef(column_ref) => Arc::new(RelNode::ColumnRef(column_ref.clone())),
         MemoRelNode::Const(const_pred) => Arc::new(RelNode::Const(const_pred.clone())),
         // ... doesn't seem maintainable
@@ -139,10 +590,7 @@ mod tests {
 
     #[test]
     fn test_memorize_rel() {
-        let mut memo = Memo {
-            groups: vec![],
-            expr_to_group: HashMap::new(),
-        };
+        let mut memo = Memo::new();
 
         let rel = join(
             // Do a self-join
@@ -157,4 +605,48 @@ mod tests {
         assert_eq!(memo.groups.len(), 8);
         assert_eq!(generate_one_binding(&memo, group_id).as_ref(), &rel);
     }
+
+    #[test]
+    fn test_commuted_self_join() {
+        let mut memo = Memo::new();
+
+        let left = filter(scan(TableId(0)), eq_pred(column_ref_pred(1), const_pred(3)));
+        let right = filter(scan(TableId(0)), eq_pred(const_pred(3), column_ref_pred(1)));
+        let rel = join(
+            left.clone(),
+            right.clone(),
+            eq_pred(column_ref_pred(1), column_ref_pred(3)),
+        );
+        let commuted = join(right, left, eq_pred(column_ref_pred(3), column_ref_pred(1)));
+
+        let group_id = memorize_rel(&mut memo, Arc::new(rel));
+        let commuted_group_id = memorize_rel(&mut memo, Arc::new(commuted));
+        memo.dump();
+
+        // without canonicalization, the commuted predicates and joins would take 12 groups
+        assert_eq!(memo.groups.len(), 8);
+        assert_eq!(group_id, commuted_group_id);
+    }
+
+    #[test]
+    fn test_merge_group() {
+        let mut memo = Memo::new();
+        let pred = || eq_pred(column_ref_pred(1), const_pred(3));
+        let filter0 = memorize_rel(&mut memo, Arc::new(filter(scan(TableId(0)), pred())));
+        let filter1 = memorize_rel(&mut memo, Arc::new(filter(scan(TableId(1)), pred())));
+        let scan0 = memorize_rel(&mut memo, Arc::new(scan(TableId(0))));
+        let scan1 = memorize_rel(&mut memo, Arc::new(scan(TableId(1))));
+        assert_ne!(filter0, filter1);
+
+        // once the scans are equivalent, so are the filters over them
+        let merged = memo.merge_group(scan0, scan1);
+        assert_eq!(memo.reduce_group(scan1), merged);
+        assert_eq!(memo.reduce_group(filter0), memo.reduce_group(filter1));
+        let filters = memo.get_all_exprs_in_group(filter0);
+        assert_eq!(filters.len(), 1);
+        assert_eq!(
+            memo.get_group(filters[0].clone()),
+            Some(memo.reduce_group(filter0))
+        );
+    }
 }
@@ -8,6 +8,7 @@ pub type BindConstPred = ConstPred;
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct BindJoin {
+    pub join_type: JoinType,
     pub left: Arc<BindRelNode>,
     pub right: Arc<BindRelNode>,
     pub cond: Arc<BindRelNode>,
@@ -19,44 +20,118 @@ pub struct BindFilter {
     pub predicate: Arc<BindRelNode>,
 }
 
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct BindProject {
+    pub child: Arc<BindRelNode>,
+    pub exprs: Vec<Arc<BindRelNode>>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct BindSort {
+    pub child: Arc<BindRelNode>,
+    pub keys: Vec<SortKey>,
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct BindEqPred {
     pub left: Arc<BindRelNode>,
     pub right: Arc<BindRelNode>,
 }
 
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct BindAndPred {
+    pub left: Arc<BindRelNode>,
+    pub right: Arc<BindRelNode>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct BindOrPred {
+    pub left: Arc<BindRelNode>,
+    pub right: Arc<BindRelNode>,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct BindAddPred {
+    pub left: Arc<BindRelNode>,
+    pub right: Arc<BindRelNode>,
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub enum BindRelNode {
     Scan(BindScan),
     Join(BindJoin),
     Filter(BindFilter),
+    Project(BindProject),
+    Sort(BindSort),
     Eq(BindEqPred),
+    And(BindAndPred),
+    Or(BindOrPred),
+    Add(BindAddPred),
     ColumnRef(BindColumnRefPred),
     Const(BindConstPred),
     Group(GroupId),
 }
 
-fn join_commute_memo(node: Arc<BindRelNode>) -> Option<Arc<BindRelNode>> {
+/// Swap the inputs of an inner join. The columns of the right input now come first, so the
+/// condition is remapped, and a projection restores the original column order.
+fn join_commute_memo(
+    node: Arc<BindRelNode>,
+    left_columns: usize,
+    right_columns: usize,
+) -> Option<Arc<BindRelNode>> {
     if let BindRelNode::Join(ref a) = &*node {
-        // TODO: rewrite the condition
-        return Some(Arc::new(BindRelNode::Join(BindJoin {
-            right: a.left.clone(),
-            left: a.right.clone(),
-            cond: a.cond.clone(),
+        if a.join_type != JoinType::Inner {
+            return None;
+        }
+        let swap = |column| {
+            if column < left_columns {
+                column + right_columns
+            } else {
+                column - left_columns
+            }
+        };
+        let exprs = (0..left_columns + right_columns)
+            .map(|column| {
+                Arc::new(BindRelNode::ColumnRef(ColumnRefPred {
+                    column: swap(column),
+                }))
+            })
+            .collect();
+        return Some(Arc::new(BindRelNode::Project(BindProject {
+            child: Arc::new(BindRelNode::Join(BindJoin {
+                join_type: a.join_type,
+                left: a.right.clone(),
+                right: a.left.clone(),
+                cond: map_bind_column_refs(&a.cond, &swap),
+            })),
+            exprs,
         })));
     }
     None
 }
 
-fn join_assoc_memo(node: Arc<BindRelNode>) -> Option<Arc<BindRelNode>> {
+/// `(A join B) join C` to `A join (B join C)`, if the top condition doesn't refer to `A`. The
+/// output keeps its column order, so only the top condition is shifted.
+fn join_assoc_memo(node: Arc<BindRelNode>, left_columns: usize) -> Option<Arc<BindRelNode>> {
     if let BindRelNode::Join(ref a) = &*node {
         if let BindRelNode::Join(b) = &*a.left {
+            if a.join_type != JoinType::Inner || b.join_type != JoinType::Inner {
+                return None;
+            }
+            if bind_column_refs(&a.cond)
+                .iter()
+                .any(|&column| column < left_columns)
+            {
+                return None;
+            }
             return Some(Arc::new(BindRelNode::Join(BindJoin {
+                join_type: JoinType::Inner,
                 left: b.left.clone(),
                 right: Arc::new(BindRelNode::Join(BindJoin {
+                    join_type: JoinType::Inner,
                     left: b.right.clone(),
                     right: a.right.clone(),
-                    cond: a.cond.clone(),
+                    cond: map_bind_column_refs(&a.cond, &|column| column - left_columns),
                 })),
                 cond: b.cond.clone(),
             })));
@@ -65,65 +140,293 @@ fn join_assoc_memo(node: Arc<BindRelNode>) -> Option<Arc<BindRelNode>> {
     None
 }
 
-pub fn apply_join_commute_rules_on_node(memo: &mut Memo, group: GroupId, node: MemoRelNode) {
-    if let MemoRelNode::Join(node) = node {
-        let binding = BindJoin {
-            left: Arc::new(BindRelNode::Group(node.left)),
-            right: Arc::new(BindRelNode::Group(node.right)),
-            cond: Arc::new(BindRelNode::Group(node.cond)),
-        };
-        let applied = join_commute_memo(Arc::new(BindRelNode::Join(binding))).unwrap();
-        add_binding_to_memo(memo, group, applied);
+/// Number of columns produced by a group, like `column_count`.
+pub fn memo_column_count(memo: &impl MemoStore, stats: &dyn TableStats, group: GroupId) -> usize {
+    let columns = |group| memo_column_count(memo, stats, group);
+    match memo.get_all_exprs_in_group(group).into_iter().next() {
+        Some(MemoRelNode::Scan(scan) | MemoRelNode::TableScan(scan)) => {
+            stats.column_count(&scan.table)
+        }
+        Some(MemoRelNode::IndexScan(scan)) => stats.column_count(&scan.table),
+        Some(MemoRelNode::IndexLookup(lookup)) => stats.column_count(&lookup.table),
+        Some(
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join),
+        ) => match join.join_type {
+            JoinType::Inner => columns(join.left) + columns(join.right),
+            JoinType::LeftSemi | JoinType::LeftAnti => columns(join.left),
+        },
+        Some(MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter)) => {
+            columns(filter.child)
+        }
+        Some(MemoRelNode::Project(project)) => project.exprs.len(),
+        Some(MemoRelNode::Sort(sort)) => columns(sort.child),
+        Some(MemoRelNode::Aggregate(aggregate)) => aggregate.group_by.len() + aggregate.aggs.len(),
+        Some(MemoRelNode::Limit(limit)) => columns(limit.child),
+        _ => 0,
     }
 }
 
-pub fn apply_join_assoc_rules_on_node(memo: &mut Memo, group: GroupId, node: MemoRelNode) {
-    if let MemoRelNode::Join(node1) = node {
-        for expr in memo.get_all_exprs_in_group(node1.left) {
-            if let MemoRelNode::Join(node2) = expr {
-                let binding = BindJoin {
-                    left: Arc::new(BindRelNode::Join(BindJoin {
-                        left: Arc::new(BindRelNode::Group(node2.left)),
-                        right: Arc::new(BindRelNode::Group(node2.right)),
-                        cond: Arc::new(BindRelNode::Group(node2.cond)),
-                    })),
-                    right: Arc::new(BindRelNode::Group(node1.right)),
-                    cond: Arc::new(BindRelNode::Group(node1.cond)),
-                };
-                let applied = join_assoc_memo(Arc::new(BindRelNode::Join(binding))).unwrap();
-                add_binding_to_memo(memo, group, applied);
-            }
+/// Expand a scalar group into a binding, so that rules can rewrite its column references.
+fn bind_scalar(memo: &impl MemoStore, group: GroupId) -> Arc<BindRelNode> {
+    let expand = |left, right| (bind_scalar(memo, left), bind_scalar(memo, right));
+    let node = match memo.get_all_exprs_in_group(group).into_iter().next() {
+        Some(MemoRelNode::ColumnRef(column_ref)) => BindRelNode::ColumnRef(column_ref),
+        Some(MemoRelNode::Const(constant)) => BindRelNode::Const(constant),
+        Some(MemoRelNode::Eq(eq)) => {
+            let (left, right) = expand(eq.left, eq.right);
+            BindRelNode::Eq(BindEqPred { left, right })
+        }
+        Some(MemoRelNode::And(and)) => {
+            let (left, right) = expand(and.left, and.right);
+            BindRelNode::And(BindAndPred { left, right })
+        }
+        Some(MemoRelNode::Or(or)) => {
+            let (left, right) = expand(or.left, or.right);
+            BindRelNode::Or(BindOrPred { left, right })
+        }
+        Some(MemoRelNode::Add(add)) => {
+            let (left, right) = expand(add.left, add.right);
+            BindRelNode::Add(BindAddPred { left, right })
+        }
+        _ => BindRelNode::Group(group),
+    };
+    Arc::new(node)
+}
+
+fn bind_column_refs(node: &BindRelNode) -> Vec<usize> {
+    let (left, right) = match node {
+        BindRelNode::ColumnRef(column_ref) => return vec![column_ref.column],
+        BindRelNode::Eq(eq) => (&eq.left, &eq.right),
+        BindRelNode::And(and) => (&and.left, &and.right),
+        BindRelNode::Or(or) => (&or.left, &or.right),
+        BindRelNode::Add(add) => (&add.left, &add.right),
+        _ => return vec![],
+    };
+    let mut refs = bind_column_refs(left);
+    refs.extend(bind_column_refs(right));
+    refs
+}
+
+fn map_bind_column_refs(node: &Arc<BindRelNode>, f: &impl Fn(usize) -> usize) -> Arc<BindRelNode> {
+    let map = |node| map_bind_column_refs(node, f);
+    Arc::new(match &**node {
+        BindRelNode::ColumnRef(column_ref) => BindRelNode::ColumnRef(ColumnRefPred {
+            column: f(column_ref.column),
+        }),
+        BindRelNode::Eq(eq) => BindRelNode::Eq(BindEqPred {
+            left: map(&eq.left),
+            right: map(&eq.right),
+        }),
+        BindRelNode::And(and) => BindRelNode::And(BindAndPred {
+            left: map(&and.left),
+            right: map(&and.right),
+        }),
+        BindRelNode::Or(or) => BindRelNode::Or(BindOrPred {
+            left: map(&or.left),
+            right: map(&or.right),
+        }),
+        BindRelNode::Add(add) => BindRelNode::Add(BindAddPred {
+            left: map(&add.left),
+            right: map(&add.right),
+        }),
+        _ => return node.clone(),
+    })
+}
+
+/// The binding `join_commute_memo` makes of a memo expression, if it's an inner join.
+pub fn join_commute_binding(
+    memo: &impl MemoStore,
+    stats: &dyn TableStats,
+    node: &MemoRelNode,
+) -> Option<Arc<BindRelNode>> {
+    let MemoRelNode::Join(node) = node else {
+        return None;
+    };
+    let left_columns = memo_column_count(memo, stats, node.left);
+    let right_columns = memo_column_count(memo, stats, node.right);
+    let binding = BindJoin {
+        join_type: node.join_type,
+        left: Arc::new(BindRelNode::Group(node.left)),
+        right: Arc::new(BindRelNode::Group(node.right)),
+        cond: bind_scalar(memo, node.cond),
+    };
+    join_commute_memo(
+        Arc::new(BindRelNode::Join(binding)),
+        left_columns,
+        right_columns,
+    )
+}
+
+/// The bindings `join_assoc_memo` makes of a memo expression, one for each join in its left
+/// input.
+pub fn join_assoc_bindings(
+    memo: &impl MemoStore,
+    stats: &dyn TableStats,
+    node: &MemoRelNode,
+) -> Vec<Arc<BindRelNode>> {
+    let MemoRelNode::Join(node1) = node else {
+        return vec![];
+    };
+    let mut bindings = vec![];
+    for expr in memo.get_all_exprs_in_group(node1.left) {
+        if let MemoRelNode::Join(node2) = expr {
+            let left_columns = memo_column_count(memo, stats, node2.left);
+            let binding = BindJoin {
+                join_type: node1.join_type,
+                left: Arc::new(BindRelNode::Join(BindJoin {
+                    join_type: node2.join_type,
+                    left: Arc::new(BindRelNode::Group(node2.left)),
+                    right: Arc::new(BindRelNode::Group(node2.right)),
+                    cond: Arc::new(BindRelNode::Group(node2.cond)),
+                })),
+                right: Arc::new(BindRelNode::Group(node1.right)),
+                cond: bind_scalar(memo, node1.cond),
+            };
+            bindings.extend(join_assoc_memo(
+                Arc::new(BindRelNode::Join(binding)),
+                left_columns,
+            ));
         }
     }
+    bindings
 }
 
-pub fn add_binding_to_memo(memo: &mut Memo, group: GroupId, node: Arc<BindRelNode>) -> GroupId {
-    fn add_binding_to_memo_inner(memo: &mut Memo, node: Arc<BindRelNode>) -> GroupId {
-        let node = match &*node {
-            BindRelNode::Scan(scan) => MemoRelNode::Scan(scan.clone()),
-            BindRelNode::Join(join) => {
-                let left = add_binding_to_memo_inner(memo, join.left.clone());
-                let right = add_binding_to_memo_inner(memo, join.right.clone());
-                let cond = add_binding_to_memo_inner(memo, join.cond.clone());
-                MemoRelNode::Join(MemoJoin { left, right, cond })
-            }
-            BindRelNode::Filter(filter) => {
-                let child = add_binding_to_memo_inner(memo, filter.child.clone());
-                let predicate = add_binding_to_memo_inner(memo, filter.predicate.clone());
-                MemoRelNode::Filter(MemoFilter { child, predicate })
-            }
-            BindRelNode::Eq(eq) => {
-                let left = add_binding_to_memo_inner(memo, eq.left.clone());
-                let right = add_binding_to_memo_inner(memo, eq.right.clone());
-                MemoRelNode::Eq(MemoEqPred { left, right })
-            }
-            BindRelNode::ColumnRef(column_ref) => MemoRelNode::ColumnRef(column_ref.clone()),
-            BindRelNode::Const(constant) => MemoRelNode::Const(constant.clone()),
-            BindRelNode::Group(group) => return *group,
-        };
-        memo.add_expr(node.clone())
+/// The bindings `join_commute` and `join_assoc` make of a memo expression. Only reads the memo,
+/// so that many expressions can be explored at the same time.
+pub fn explore_expr(
+    memo: &impl MemoStore,
+    stats: &dyn TableStats,
+    node: &MemoRelNode,
+) -> Vec<Arc<BindRelNode>> {
+    let mut bindings = Vec::from_iter(join_commute_binding(memo, stats, node));
+    bindings.extend(join_assoc_bindings(memo, stats, node));
+    bindings
+}
+
+/// Add a binding made by exploring an expression of `group`.
+pub fn add_explored_binding(memo: &mut impl MemoStore, group: GroupId, binding: Arc<BindRelNode>) {
+    // a swapped join commutes back into the one it came from: only add it if it's new, so that
+    // two groups never contain projections of each other
+    if let BindRelNode::Project(project) = &*binding {
+        if find_binding(memo, &project.child).is_some() {
+            return;
+        }
+    }
+    add_binding_to_memo(memo, group, binding);
+}
+
+pub fn apply_join_commute_rules_on_node(
+    memo: &mut impl MemoStore,
+    stats: &dyn TableStats,
+    group: GroupId,
+    node: MemoRelNode,
+) {
+    if let Some(binding) = join_commute_binding(memo, stats, &node) {
+        add_explored_binding(memo, group, binding);
+    }
+}
+
+pub fn apply_join_assoc_rules_on_node(
+    memo: &mut impl MemoStore,
+    stats: &dyn TableStats,
+    group: GroupId,
+    node: MemoRelNode,
+) {
+    for binding in join_assoc_bindings(memo, stats, &node) {
+        add_explored_binding(memo, group, binding);
+    }
+}
+
+/// The memo expression at the root of a binding, with `child` giving the groups of its children.
+/// Returns `None` for a group, or if `child` does.
+fn binding_expr(
+    node: &BindRelNode,
+    child: &mut impl FnMut(&Arc<BindRelNode>) -> Option<GroupId>,
+) -> Option<MemoRelNode> {
+    let expr = match node {
+        BindRelNode::Scan(scan) => MemoRelNode::Scan(scan.clone()),
+        BindRelNode::Join(join) => MemoRelNode::Join(MemoJoin {
+            join_type: join.join_type,
+            left: child(&join.left)?,
+            right: child(&join.right)?,
+            cond: child(&join.cond)?,
+        }),
+        BindRelNode::Filter(filter) => MemoRelNode::Filter(MemoFilter {
+            child: child(&filter.child)?,
+            predicate: child(&filter.predicate)?,
+        }),
+        BindRelNode::Project(project) => MemoRelNode::Project(MemoProject {
+            child: child(&project.child)?,
+            exprs: project
+                .exprs
+                .iter()
+                .map(&mut *child)
+                .collect::<Option<_>>()?,
+        }),
+        BindRelNode::Sort(sort) => MemoRelNode::Sort(MemoSort {
+            child: child(&sort.child)?,
+            keys: sort.keys.clone(),
+        }),
+        BindRelNode::Eq(eq) => MemoRelNode::Eq(MemoEqPred {
+            left: child(&eq.left)?,
+            right: child(&eq.right)?,
+        }),
+        BindRelNode::And(and) => MemoRelNode::And(MemoAndPred {
+            left: child(&and.left)?,
+            right: child(&and.right)?,
+        }),
+        BindRelNode::Or(or) => MemoRelNode::Or(MemoOrPred {
+            left: child(&or.left)?,
+            right: child(&or.right)?,
+        }),
+        BindRelNode::Add(add) => MemoRelNode::Add(MemoAddPred {
+            left: child(&add.left)?,
+            right: child(&add.right)?,
+        }),
+        BindRelNode::ColumnRef(column_ref) => MemoRelNode::ColumnRef(column_ref.clone()),
+        BindRelNode::Const(constant) => MemoRelNode::Const(constant.clone()),
+        BindRelNode::Group(_) => return None,
+    };
+    Some(canonicalize_memo_expr(expr))
+}
+
+/// The group of a binding, if all of its expressions are in the memo already.
+pub fn find_binding(memo: &impl MemoStore, node: &Arc<BindRelNode>) -> Option<GroupId> {
+    if let BindRelNode::Group(group) = &**node {
+        return Some(*group);
+    }
+    let expr = binding_expr(node, &mut |child| find_binding(memo, child))?;
+    memo.get_group(expr)
+}
+
+pub fn add_binding_to_memo(
+    memo: &mut impl MemoStore,
+    group: GroupId,
+    node: Arc<BindRelNode>,
+) -> GroupId {
+    /// Add the binding to `group` if given, or to a new group otherwise.
+    fn add_binding_to_memo_inner(
+        memo: &mut impl MemoStore,
+        node: Arc<BindRelNode>,
+        group: Option<GroupId>,
+    ) -> GroupId {
+        if let BindRelNode::Group(group) = &*node {
+            return *group;
+        }
+        let node = binding_expr(&node, &mut |child| {
+            Some(add_binding_to_memo_inner(memo, child.clone(), None))
+        })
+        .unwrap();
+        match group {
+            Some(group) => memo.add_expr_to_group(group, node),
+            None => memo.add_expr(node),
+        }
     }
-    let new_group = add_binding_to_memo_inner(memo, node);
+    let new_group = add_binding_to_memo_inner(memo, node, Some(group));
     if group != new_group {
         memo.merge_group(group, new_group)
     } else {
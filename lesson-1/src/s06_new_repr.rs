@@ -73,7 +73,7 @@ pub fn join(
     cond: impl Into<Arc<RelNode>>,
 ) -> RelNode {
     RelNode {
-        typ: RelNodeType::Filter,
+        typ: RelNodeType::Join,
         children: vec![left.into(), right.into(), cond.into()],
         data: Arc::new(RelAttrType::None),
     }
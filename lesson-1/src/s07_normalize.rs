@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use super::*;
+
+// Rules that split or push down conjuncts only work well if every predicate is in the same shape.
+// We normalize predicate trees into CNF, pull common factors out of ORs and sort commutative
+// operands, so that equivalent predicates end up structurally equal (and hash the same in the
+// memo).
+
+/// Converting to CNF can blow up exponentially, e.g., `(a AND b) OR (c AND d) OR ...`. We give up
+/// and keep the original shape if the result would have more clauses than this.
+pub const DEFAULT_MAX_CNF_CLAUSES: usize = 64;
+
+pub fn conjuncts(pred: &Arc<RelNode>) -> Vec<Arc<RelNode>> {
+    match &**pred {
+        RelNode::And(and) => {
+            let mut res = conjuncts(&and.left);
+            res.extend(conjuncts(&and.right));
+            res
+        }
+        _ => vec![pred.clone()],
+    }
+}
+
+pub fn disjuncts(pred: &Arc<RelNode>) -> Vec<Arc<RelNode>> {
+    match &**pred {
+        RelNode::Or(or) => {
+            let mut res = disjuncts(&or.left);
+            res.extend(disjuncts(&or.right));
+            res
+        }
+        _ => vec![pred.clone()],
+    }
+}
+
+/// Build a left-deep AND chain. Returns `None` for an empty list, which would be `TRUE`.
+pub fn fold_and(preds: Vec<Arc<RelNode>>) -> Option<Arc<RelNode>> {
+    preds
+        .into_iter()
+        .reduce(|left, right| Arc::new(and_pred(left, right)))
+}
+
+/// Build a left-deep OR chain. Returns `None` for an empty list, which would be `FALSE`.
+pub fn fold_or(preds: Vec<Arc<RelNode>>) -> Option<Arc<RelNode>> {
+    preds
+        .into_iter()
+        .reduce(|left, right| Arc::new(or_pred(left, right)))
+}
+
+fn sorted_dedup(mut preds: Vec<Arc<RelNode>>) -> Vec<Arc<RelNode>> {
+    preds.sort();
+    preds.dedup();
+    preds
+}
+
+/// Sort the operands of commutative operators, flatten nested AND / OR chains and remove
+/// duplicated operands.
+pub fn canonicalize_pred(pred: Arc<RelNode>) -> Arc<RelNode> {
+    match &*pred {
+        RelNode::And(_) => {
+            let preds = conjuncts(&pred).into_iter().map(canonicalize_pred);
+            // a child may turn into an AND after canonicalization, flatten again
+            let preds = preds.flat_map(|pred| conjuncts(&pred)).collect();
+            fold_and(sorted_dedup(preds)).unwrap()
+        }
+        RelNode::Or(_) => {
+            let preds = disjuncts(&pred).into_iter().map(canonicalize_pred);
+            let preds = preds.flat_map(|pred| disjuncts(&pred)).collect();
+            fold_or(sorted_dedup(preds)).unwrap()
+        }
+        RelNode::Eq(eq) => {
+            let left = canonicalize_pred(eq.left.clone());
+            let right = canonicalize_pred(eq.right.clone());
+            if left <= right {
+                Arc::new(eq_pred(left, right))
+            } else {
+                Arc::new(eq_pred(right, left))
+            }
+        }
+        RelNode::Add(add) => {
+            let left = canonicalize_pred(add.left.clone());
+            let right = canonicalize_pred(add.right.clone());
+            if left <= right {
+                Arc::new(add_pred(left, right))
+            } else {
+                Arc::new(add_pred(right, left))
+            }
+        }
+        _ => {
+            let children = pred.children().into_iter().map(canonicalize_pred).collect();
+            Arc::new(pred.clone_with_children(children))
+        }
+    }
+}
+
+/// `(a AND b) OR (a AND c)` -> `a AND (b OR c)`, and `a OR (a AND b)` -> `a`.
+pub fn extract_common_factors(pred: Arc<RelNode>) -> Arc<RelNode> {
+    match &*pred {
+        RelNode::And(_) => fold_and(
+            conjuncts(&pred)
+                .into_iter()
+                .map(extract_common_factors)
+                .collect(),
+        )
+        .unwrap(),
+        RelNode::Or(_) => {
+            let branches = disjuncts(&pred)
+                .into_iter()
+                .map(|pred| sorted_dedup(conjuncts(&extract_common_factors(pred))))
+                .collect::<Vec<_>>();
+            let common = branches[0]
+                .iter()
+                .filter(|factor| branches[1..].iter().all(|branch| branch.contains(factor)))
+                .cloned()
+                .collect::<Vec<_>>();
+            if common.is_empty() {
+                return fold_or(branches.into_iter().filter_map(fold_and).collect()).unwrap();
+            }
+            let mut remaining = Vec::with_capacity(branches.len());
+            for branch in branches {
+                let rest = branch.into_iter().filter(|x| !common.contains(x)).collect();
+                match fold_and(rest) {
+                    Some(rest) => remaining.push(rest),
+                    // one of the branches is exactly the common part, the OR is absorbed
+                    None => return fold_and(common).unwrap(),
+                }
+            }
+            let mut res = common;
+            res.push(fold_or(remaining).unwrap());
+            fold_and(res).unwrap()
+        }
+        _ => pred,
+    }
+}
+
+/// Returns the CNF clauses of the predicate, each clause being a list of disjuncts, or `None` if
+/// there would be more than `max_clauses` clauses.
+fn cnf_clauses(pred: &Arc<RelNode>, max_clauses: usize) -> Option<Vec<Vec<Arc<RelNode>>>> {
+    match &**pred {
+        RelNode::And(and) => {
+            let mut left = cnf_clauses(&and.left, max_clauses)?;
+            let right = cnf_clauses(&and.right, max_clauses)?;
+            if left.len() + right.len() > max_clauses {
+                return None;
+            }
+            left.extend(right);
+            Some(left)
+        }
+        RelNode::Or(or) => {
+            let left = cnf_clauses(&or.left, max_clauses)?;
+            let right = cnf_clauses(&or.right, max_clauses)?;
+            if left.len() * right.len() > max_clauses {
+                return None;
+            }
+            let mut res = Vec::with_capacity(left.len() * right.len());
+            for l in &left {
+                for r in &right {
+                    res.push(l.iter().chain(r.iter()).cloned().collect());
+                }
+            }
+            Some(res)
+        }
+        _ => Some(vec![vec![pred.clone()]]),
+    }
+}
+
+/// Convert the predicate to CNF, or `None` if the expansion would be too large.
+pub fn try_to_cnf(pred: Arc<RelNode>, max_clauses: usize) -> Option<Arc<RelNode>> {
+    let clauses = cnf_clauses(&pred, max_clauses)?
+        .into_iter()
+        .map(|clause| fold_or(sorted_dedup(clause)).unwrap())
+        .collect();
+    Some(fold_and(sorted_dedup(clauses)).unwrap())
+}
+
+/// Factor out common terms, convert to CNF (falling back to the factored form if CNF is too
+/// large) and canonicalize the result.
+pub fn normalize_pred(pred: Arc<RelNode>, max_clauses: usize) -> Arc<RelNode> {
+    let pred = extract_common_factors(pred);
+    let pred = try_to_cnf(pred.clone(), max_clauses).unwrap_or(pred);
+    canonicalize_pred(pred)
+}
+
+/// Normalize all filter and join predicates in the plan.
+pub fn normalize_plan(node: Arc<RelNode>, max_clauses: usize) -> Arc<RelNode> {
+    apply_rule_bottom_up(node, |node| match &*node {
+        RelNode::Filter(a) => Some(Arc::new(filter(
+            a.child.clone(),
+            normalize_pred(a.predicate.clone(), max_clauses),
+        ))),
+        RelNode::Join(a) => Some(Arc::new(RelNode::Join(Join {
+            cond: normalize_pred(a.cond.clone(), max_clauses),
+            ..a.clone()
+        }))),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn col_eq(col: usize, value: i64) -> RelNode {
+        eq_pred(column_ref_pred(col), const_pred(value))
+    }
+
+    #[test]
+    fn test_cnf() {
+        // (#1 = 1 AND #2 = 2) OR #3 = 3 -> (#1 = 1 OR #3 = 3) AND (#2 = 2 OR #3 = 3)
+        let pred = or_pred(and_pred(col_eq(1, 1), col_eq(2, 2)), col_eq(3, 3));
+        let expected = and_pred(
+            or_pred(col_eq(1, 1), col_eq(3, 3)),
+            or_pred(col_eq(2, 2), col_eq(3, 3)),
+        );
+        assert_eq!(
+            normalize_pred(Arc::new(pred), DEFAULT_MAX_CNF_CLAUSES).as_ref(),
+            &expected
+        );
+
+        // 4 ORs of 2 conjuncts would expand to 16 clauses
+        let pred = fold_or(
+            (0..4)
+                .map(|i| Arc::new(and_pred(col_eq(i * 2, 0), col_eq(i * 2 + 1, 0))))
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(conjuncts(&normalize_pred(pred.clone(), 16)).len(), 16);
+        assert_eq!(normalize_pred(pred.clone(), 8), canonicalize_pred(pred));
+    }
+
+    #[test]
+    fn test_common_factors() {
+        // (#1 = 1 AND #2 = 2) OR (#3 = 3 AND #1 = 1) -> #1 = 1 AND (#2 = 2 OR #3 = 3)
+        let pred = or_pred(
+            and_pred(col_eq(1, 1), col_eq(2, 2)),
+            and_pred(col_eq(3, 3), col_eq(1, 1)),
+        );
+        let expected = and_pred(col_eq(1, 1), or_pred(col_eq(2, 2), col_eq(3, 3)));
+        assert_eq!(
+            normalize_pred(Arc::new(pred), DEFAULT_MAX_CNF_CLAUSES).as_ref(),
+            &expected
+        );
+
+        // #1 = 1 OR (#1 = 1 AND #2 = 2) -> #1 = 1
+        let pred = or_pred(col_eq(1, 1), and_pred(col_eq(1, 1), col_eq(2, 2)));
+        assert_eq!(
+            extract_common_factors(Arc::new(pred)).as_ref(),
+            &col_eq(1, 1)
+        );
+    }
+
+    #[test]
+    fn test_equivalent_preds_same_group() {
+        let pred1 = and_pred(
+            eq_pred(column_ref_pred(3), column_ref_pred(1)),
+            col_eq(2, 3),
+        );
+        let pred2 = and_pred(
+            col_eq(2, 3),
+            eq_pred(column_ref_pred(1), column_ref_pred(3)),
+        );
+        let plan1 = filter(scan(TableId(0)), pred1.clone());
+        let plan2 = filter(scan(TableId(0)), pred2.clone());
+
+        let mut memo = Memo::new();
+        let group1 = memorize_rel(
+            &mut memo,
+            normalize_plan(Arc::new(plan1), DEFAULT_MAX_CNF_CLAUSES),
+        );
+        let group2 = memorize_rel(
+            &mut memo,
+            normalize_plan(Arc::new(plan2), DEFAULT_MAX_CNF_CLAUSES),
+        );
+        assert_eq!(group1, group2);
+
+        // the join type is kept
+        let semi = |pred| {
+            Arc::new(join_with_type(
+                JoinType::LeftSemi,
+                scan(TableId(0)),
+                scan(TableId(1)),
+                pred,
+            ))
+        };
+        let normalized = normalize_plan(semi(pred1), DEFAULT_MAX_CNF_CLAUSES);
+        assert_eq!(
+            normalized,
+            normalize_plan(semi(pred2), DEFAULT_MAX_CNF_CLAUSES)
+        );
+        assert!(
+            matches!(&*normalized, RelNode::Join(join) if join.join_type == JoinType::LeftSemi)
+        );
+    }
+}
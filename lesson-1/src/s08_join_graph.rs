@@ -0,0 +1,691 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::*;
+
+// Join ordering works on a join graph instead of the join tree: the relations being joined and the
+// predicates connecting them. All predicates refer to columns by a global position (every relation
+// gets its own range of columns), so that we can rebuild a tree in any order.
+//
+// A predicate is a (hyper)edge between two sets of relations. Inner join predicates only need their
+// own relations on both sides. Semi and anti joins can't be freely reordered: the whole right side
+// must be joined first, so the right end of their edge is every relation of the right side.
+
+/// The table metadata the optimizer needs. A `Catalog` has all of it; tests often get by with a
+/// map of `TableInfo`s. Shared by the threads of `explore_parallel`.
+pub trait TableStats: Sync {
+    fn column_count(&self, table: &TableId) -> usize;
+    fn row_count(&self, table: &TableId) -> f64;
+
+    /// Column sets in which no two rows of the table have the same values, where NULLs count as
+    /// equal like in a DISTINCT. Unique columns that may be NULL are not keys.
+    fn keys(&self, _table: &TableId) -> Vec<Vec<usize>> {
+        vec![]
+    }
+
+    /// Whether no two rows of the table have the same values in `columns`.
+    fn is_unique(&self, table: &TableId, columns: &[usize]) -> bool {
+        self.keys(table)
+            .iter()
+            .any(|key| key.iter().all(|column| columns.contains(column)))
+    }
+
+    fn indexes(&self, _table: &TableId) -> Vec<Index> {
+        vec![]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TableInfo {
+    pub columns: usize,
+    pub rows: f64,
+}
+
+impl TableStats for HashMap<TableId, TableInfo> {
+    fn column_count(&self, table: &TableId) -> usize {
+        self[table].columns
+    }
+
+    fn row_count(&self, table: &TableId) -> f64 {
+        self[table].rows
+    }
+}
+
+/// We don't have column statistics, so every predicate gets the same selectivity.
+pub const DEFAULT_SELECTIVITY: f64 = 0.1;
+
+/// Number of columns produced by a plan node, 0 for scalar expressions.
+pub fn column_count(node: &RelNode, stats: &dyn TableStats) -> usize {
+    match node {
+        RelNode::Scan(scan) | RelNode::TableScan(scan) => stats.column_count(&scan.table),
+        RelNode::IndexScan(scan) => stats.column_count(&scan.table),
+        RelNode::IndexLookup(lookup) => stats.column_count(&lookup.table),
+        RelNode::Join(join)
+        | RelNode::HashJoin(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::IndexNestedLoopJoin(join) => match join.join_type {
+            JoinType::Inner => column_count(&join.left, stats) + column_count(&join.right, stats),
+            JoinType::LeftSemi | JoinType::LeftAnti => column_count(&join.left, stats),
+        },
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => {
+            column_count(&filter.child, stats)
+        }
+        RelNode::Project(project) => project.exprs.len(),
+        RelNode::Sort(sort) => column_count(&sort.child, stats),
+        RelNode::Aggregate(aggregate) => aggregate.group_by.len() + aggregate.aggs.len(),
+        RelNode::Limit(limit) => column_count(&limit.child, stats),
+        _ => 0,
+    }
+}
+
+/// Without statistics on the group-by columns, every group has `1 / DEFAULT_SELECTIVITY` rows.
+pub fn aggregate_cardinality(input: f64, group_by: usize) -> f64 {
+    if group_by == 0 {
+        1.0
+    } else {
+        (input * DEFAULT_SELECTIVITY).max(1.0)
+    }
+}
+
+/// Whether the predicate compares a unique key of the table with constants, so that at most one
+/// row passes.
+pub fn fixes_unique_key(stats: &dyn TableStats, table: &TableId, pred: &Arc<RelNode>) -> bool {
+    let mut columns = vec![];
+    for pred in conjuncts(pred) {
+        if let RelNode::Eq(eq) = &*pred {
+            match (&*eq.left, &*eq.right) {
+                (RelNode::ColumnRef(column_ref), RelNode::Const(_))
+                | (RelNode::Const(_), RelNode::ColumnRef(column_ref)) => {
+                    columns.push(column_ref.column)
+                }
+                _ => {}
+            }
+        }
+    }
+    !columns.is_empty() && stats.is_unique(table, &columns)
+}
+
+pub fn estimate_cardinality(node: &RelNode, stats: &dyn TableStats) -> f64 {
+    match node {
+        RelNode::Scan(scan) | RelNode::TableScan(scan) => stats.row_count(&scan.table),
+        RelNode::IndexScan(scan) => {
+            index_cardinality(stats, &scan.table, &scan.index, scan.prefix.len())
+        }
+        // the rows found for one row of the outer side
+        RelNode::IndexLookup(lookup) => {
+            index_cardinality(stats, &lookup.table, &lookup.index, lookup.keys.len())
+        }
+        RelNode::Join(join)
+        | RelNode::HashJoin(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::IndexNestedLoopJoin(join) => {
+            let left = estimate_cardinality(&join.left, stats);
+            let right = match &*join.right {
+                RelNode::IndexLookup(lookup) => stats.row_count(&lookup.table),
+                right => estimate_cardinality(right, stats),
+            };
+            let selectivity = DEFAULT_SELECTIVITY.powi(conjuncts(&join.cond).len() as i32);
+            match join.join_type {
+                JoinType::Inner => left * right * selectivity,
+                JoinType::LeftSemi => left * selectivity,
+                JoinType::LeftAnti => left * (1.0 - selectivity),
+            }
+        }
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => {
+            let child = estimate_cardinality(&filter.child, stats);
+            let estimate =
+                child * DEFAULT_SELECTIVITY.powi(conjuncts(&filter.predicate).len() as i32);
+            match &*filter.child {
+                RelNode::Scan(scan) | RelNode::TableScan(scan)
+                    if fixes_unique_key(stats, &scan.table, &filter.predicate) =>
+                {
+                    estimate.min(1.0)
+                }
+                _ => estimate,
+            }
+        }
+        RelNode::Project(project) => estimate_cardinality(&project.child, stats),
+        RelNode::Sort(sort) => estimate_cardinality(&sort.child, stats),
+        RelNode::Aggregate(aggregate) => {
+            let child = estimate_cardinality(&aggregate.child, stats);
+            aggregate_cardinality(child, aggregate.group_by.len())
+        }
+        RelNode::Limit(limit) => estimate_cardinality(&limit.child, stats).min(limit.limit as f64),
+        _ => 1.0,
+    }
+}
+
+/// Collect all column indexes referenced by a scalar expression.
+pub fn column_refs(pred: &RelNode) -> Vec<usize> {
+    match pred {
+        RelNode::ColumnRef(column_ref) => vec![column_ref.column],
+        _ => pred
+            .children()
+            .iter()
+            .flat_map(|child| column_refs(child))
+            .collect(),
+    }
+}
+
+/// Rewrite all column references in a scalar expression.
+pub fn map_column_refs(pred: Arc<RelNode>, f: &impl Fn(usize) -> usize) -> Arc<RelNode> {
+    apply_rule_bottom_up(pred, |node| match &*node {
+        RelNode::ColumnRef(column_ref) => Some(Arc::new(column_ref_pred(f(column_ref.column)))),
+        _ => None,
+    })
+}
+
+/// A set of relations in the join graph, relation `i` is bit `i`.
+pub type RelSet = u64;
+
+/// Relations with index no larger than `idx`.
+pub(crate) fn lower_or_equal(idx: usize) -> RelSet {
+    if idx >= 63 {
+        RelSet::MAX
+    } else {
+        (1 << (idx + 1)) - 1
+    }
+}
+
+pub fn rel_set_iter(set: RelSet) -> impl Iterator<Item = usize> {
+    (0..64).filter(move |i| set & (1 << i) != 0)
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinRelation {
+    pub node: Arc<RelNode>,
+    /// The global position of the first column of this relation.
+    pub column_offset: usize,
+    pub columns: usize,
+    pub cardinality: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinEdge {
+    /// The predicate, with column references in global positions.
+    pub pred: Arc<RelNode>,
+    pub join_type: JoinType,
+    /// The relations that must be on the left side of the join evaluating this predicate. For
+    /// predicates on a single relation, `right` is empty and the predicate is a filter.
+    pub left: RelSet,
+    pub right: RelSet,
+}
+
+impl JoinEdge {
+    pub fn relations(&self) -> RelSet {
+        self.left | self.right
+    }
+
+    /// Whether this is a plain inner join predicate between two relations.
+    pub fn is_simple(&self) -> bool {
+        self.join_type == JoinType::Inner
+            && self.left.count_ones() == 1
+            && self.right.count_ones() == 1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinGraph {
+    pub relations: Vec<JoinRelation>,
+    pub edges: Vec<JoinEdge>,
+    /// The global positions of the columns produced by the original tree.
+    pub output_columns: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinTree {
+    Relation(usize),
+    Join(Box<JoinTree>, Box<JoinTree>),
+}
+
+impl JoinTree {
+    pub fn join(left: JoinTree, right: JoinTree) -> Self {
+        JoinTree::Join(Box::new(left), Box::new(right))
+    }
+
+    pub fn relations(&self) -> RelSet {
+        match self {
+            JoinTree::Relation(idx) => 1 << idx,
+            JoinTree::Join(left, right) => left.relations() | right.relations(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinPlan {
+    pub tree: JoinTree,
+    pub cardinality: f64,
+    /// C_out, the sum of the cardinalities of all intermediate and final results.
+    pub cost: f64,
+}
+
+/// An edge as found in the tree, before we know how many relations there are.
+struct PendingEdge {
+    pred: Arc<RelNode>,
+    join_type: JoinType,
+    left: Vec<usize>,
+    right: Vec<usize>,
+}
+
+impl JoinGraph {
+    /// Extract the join graph from a tree of joins. Returns `None` if the root is not a join or if
+    /// there are more relations than a `RelSet` can hold.
+    pub fn extract(node: &Arc<RelNode>, stats: &dyn TableStats) -> Option<Self> {
+        if !matches!(&**node, RelNode::Join(_)) {
+            return None;
+        }
+        let mut graph = JoinGraph {
+            relations: vec![],
+            edges: vec![],
+            output_columns: vec![],
+        };
+        let mut edges = vec![];
+        let (output_columns, _) = graph.extract_inner(node, stats, &mut edges);
+        if graph.relations.len() > 64 {
+            return None;
+        }
+        graph.output_columns = output_columns;
+        for edge in edges {
+            let edge = graph.make_edge(edge);
+            graph.edges.push(edge);
+        }
+        Some(graph)
+    }
+
+    /// Returns the global positions of the columns produced by the node, and the relations in it.
+    fn extract_inner(
+        &mut self,
+        node: &Arc<RelNode>,
+        stats: &dyn TableStats,
+        edges: &mut Vec<PendingEdge>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        if let RelNode::Join(join) = &**node {
+            let (left_columns, mut left) = self.extract_inner(&join.left, stats, edges);
+            let (right_columns, right) = self.extract_inner(&join.right, stats, edges);
+            let scope = left_columns
+                .iter()
+                .chain(right_columns.iter())
+                .copied()
+                .collect::<Vec<_>>();
+            let cond = map_column_refs(join.cond.clone(), &|column| scope[column]);
+            // the conjuncts of an inner join are independent edges, but the condition of a semi or
+            // anti join must be evaluated at once
+            let preds = match join.join_type {
+                JoinType::Inner => conjuncts(&cond),
+                JoinType::LeftSemi | JoinType::LeftAnti => vec![cond],
+            };
+            for pred in preds {
+                edges.push(PendingEdge {
+                    pred,
+                    join_type: join.join_type,
+                    left: left.clone(),
+                    right: right.clone(),
+                });
+            }
+            let columns = match join.join_type {
+                JoinType::Inner => scope,
+                JoinType::LeftSemi | JoinType::LeftAnti => left_columns,
+            };
+            left.extend(right);
+            (columns, left)
+        } else {
+            let column_offset = self
+                .relations
+                .last()
+                .map_or(0, |rel| rel.column_offset + rel.columns);
+            let columns = column_count(node, stats);
+            self.relations.push(JoinRelation {
+                node: node.clone(),
+                column_offset,
+                columns,
+                cardinality: estimate_cardinality(node, stats),
+            });
+            (
+                (column_offset..column_offset + columns).collect(),
+                vec![self.relations.len() - 1],
+            )
+        }
+    }
+
+    fn make_edge(&self, edge: PendingEdge) -> JoinEdge {
+        let to_set = |rels: &[usize]| rels.iter().fold(0, |set, rel| set | 1 << rel);
+        let (left_rels, right_rels) = (to_set(&edge.left), to_set(&edge.right));
+        let refs = self.relations_of(&edge.pred);
+        let (left, right) = match edge.join_type {
+            JoinType::Inner if refs == 0 => (left_rels, right_rels),
+            JoinType::Inner if refs & left_rels != 0 && refs & right_rels != 0 => {
+                (refs & left_rels, refs & right_rels)
+            }
+            JoinType::Inner if refs.count_ones() == 1 => (refs, 0),
+            // a predicate between relations on the same side of the join
+            JoinType::Inner => {
+                let lowest = refs & refs.wrapping_neg();
+                (lowest, refs & !lowest)
+            }
+            JoinType::LeftSemi | JoinType::LeftAnti => {
+                let left = if refs & left_rels == 0 {
+                    left_rels
+                } else {
+                    refs & left_rels
+                };
+                (left, right_rels)
+            }
+        };
+        JoinEdge {
+            pred: edge.pred,
+            join_type: edge.join_type,
+            left,
+            right,
+        }
+    }
+
+    fn relation_of_column(&self, column: usize) -> usize {
+        self.relations
+            .iter()
+            .position(|rel| column >= rel.column_offset && column < rel.column_offset + rel.columns)
+            .expect("column out of range")
+    }
+
+    fn relations_of(&self, pred: &RelNode) -> RelSet {
+        column_refs(pred)
+            .into_iter()
+            .fold(0, |set, column| set | 1 << self.relation_of_column(column))
+    }
+
+    pub fn all_relations(&self) -> RelSet {
+        if self.relations.len() == 64 {
+            RelSet::MAX
+        } else {
+            (1 << self.relations.len()) - 1
+        }
+    }
+
+    /// Whether all edges are simple inner join predicates, so that simple-graph algorithms such as
+    /// DPccp can be used.
+    pub fn is_simple(&self) -> bool {
+        self.edges
+            .iter()
+            .all(|edge| edge.is_simple() || edge.right == 0)
+    }
+
+    /// Relations connected to `set` by a simple edge, excluding `set` itself.
+    pub fn neighbors(&self, set: RelSet) -> RelSet {
+        let mut neighbors = 0;
+        for edge in &self.edges {
+            if edge.is_simple() && edge.relations() & set != 0 {
+                neighbors |= edge.relations();
+            }
+        }
+        neighbors & !set
+    }
+
+    /// The edges that are evaluated when joining the two sets of relations.
+    pub fn edges_between(
+        &self,
+        left: RelSet,
+        right: RelSet,
+    ) -> impl Iterator<Item = &JoinEdge> + '_ {
+        self.edges.iter().filter(move |edge| {
+            let relations = edge.relations();
+            relations & !(left | right) == 0 && relations & !left != 0 && relations & !right != 0
+        })
+    }
+
+    /// The join type to use for joining `left` and `right` in this order, or `None` if this join
+    /// is not valid: a cross product, or a reordering that semi and anti joins don't allow.
+    pub fn join_type_between(&self, left: RelSet, right: RelSet) -> Option<JoinType> {
+        let mut join_type = None;
+        for edge in self.edges_between(left, right) {
+            let inner = edge.join_type == JoinType::Inner;
+            if !(inner || edge.left & !left == 0 && edge.right & !right == 0) {
+                return None;
+            }
+            join_type = match join_type {
+                None => Some(edge.join_type),
+                Some(existing) if existing == edge.join_type && inner => Some(existing),
+                // one join can't evaluate a non-inner predicate together with other predicates
+                Some(_) => return None,
+            };
+        }
+        join_type
+    }
+
+    /// Whether there is an edge between the two sets of relations that can be evaluated when
+    /// joining them.
+    pub fn connected(&self, left: RelSet, right: RelSet) -> bool {
+        self.join_type_between(left, right).is_some()
+    }
+
+    /// Cardinality of joining all relations in the set with all predicates that apply.
+    pub fn cardinality(&self, set: RelSet) -> f64 {
+        // relations on the right side of a semi or anti join don't contribute rows
+        let hidden = self
+            .edges
+            .iter()
+            .filter(|edge| edge.join_type != JoinType::Inner && edge.relations() & !set == 0)
+            .fold(0, |hidden, edge| hidden | edge.right);
+        let mut cardinality = rel_set_iter(set & !hidden)
+            .map(|idx| self.relations[idx].cardinality)
+            .product::<f64>();
+        for edge in &self.edges {
+            if edge.relations() & !set != 0 {
+                continue;
+            }
+            cardinality *= match edge.join_type {
+                JoinType::Inner if edge.relations() & hidden != 0 => 1.0,
+                JoinType::Inner | JoinType::LeftSemi => DEFAULT_SELECTIVITY,
+                JoinType::LeftAnti => 1.0 - DEFAULT_SELECTIVITY,
+            };
+        }
+        cardinality
+    }
+
+    pub fn relation_plan(&self, idx: usize) -> JoinPlan {
+        JoinPlan {
+            tree: JoinTree::Relation(idx),
+            cardinality: self.cardinality(1 << idx),
+            cost: 0.0,
+        }
+    }
+
+    pub fn join_plan(&self, left: &JoinPlan, right: &JoinPlan) -> JoinPlan {
+        let cardinality = self.cardinality(left.tree.relations() | right.tree.relations());
+        JoinPlan {
+            tree: JoinTree::join(left.tree.clone(), right.tree.clone()),
+            cardinality,
+            cost: left.cost + right.cost + cardinality,
+        }
+    }
+
+    /// Compute the cost of an arbitrary join tree over this graph.
+    pub fn plan_for_tree(&self, tree: &JoinTree) -> JoinPlan {
+        match tree {
+            JoinTree::Relation(idx) => self.relation_plan(*idx),
+            JoinTree::Join(left, right) => {
+                self.join_plan(&self.plan_for_tree(left), &self.plan_for_tree(right))
+            }
+        }
+    }
+
+    /// Build the plan for a join tree, placing each predicate at the lowest join where all of its
+    /// relations are available. A projection is added on top if the column order changes.
+    pub fn to_rel_node(&self, tree: &JoinTree) -> Arc<RelNode> {
+        let (node, columns) = self.to_rel_node_inner(tree);
+        if columns == self.output_columns {
+            return node;
+        }
+        let exprs = self
+            .output_columns
+            .iter()
+            .map(|column| {
+                let pos = columns.iter().position(|c| c == column).unwrap();
+                Arc::new(column_ref_pred(pos))
+            })
+            .collect();
+        Arc::new(project(node, exprs))
+    }
+
+    /// Returns the plan and the global positions of the columns it produces.
+    fn to_rel_node_inner(&self, tree: &JoinTree) -> (Arc<RelNode>, Vec<usize>) {
+        match tree {
+            JoinTree::Relation(idx) => {
+                let relation = &self.relations[*idx];
+                let preds = self
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.left == 1 << idx && edge.right == 0)
+                    .map(|edge| {
+                        map_column_refs(edge.pred.clone(), &|column| {
+                            column - relation.column_offset
+                        })
+                    })
+                    .collect();
+                let node = match fold_and(preds) {
+                    Some(pred) => Arc::new(filter(relation.node.clone(), pred)),
+                    None => relation.node.clone(),
+                };
+                let offset = relation.column_offset;
+                (node, (offset..offset + relation.columns).collect())
+            }
+            JoinTree::Join(left, right) => {
+                let (left_set, right_set) = (left.relations(), right.relations());
+                let join_type = self
+                    .join_type_between(left_set, right_set)
+                    .expect("not a valid join");
+                let (left, left_columns) = self.to_rel_node_inner(left);
+                let (right, right_columns) = self.to_rel_node_inner(right);
+                let scope = left_columns
+                    .iter()
+                    .chain(right_columns.iter())
+                    .copied()
+                    .collect::<Vec<_>>();
+                let preds = self
+                    .edges_between(left_set, right_set)
+                    .map(|edge| {
+                        map_column_refs(edge.pred.clone(), &|column| {
+                            scope.iter().position(|c| *c == column).unwrap()
+                        })
+                    })
+                    .collect();
+                let cond = fold_and(preds).unwrap();
+                let node = Arc::new(join_with_type(join_type, left, right, cond));
+                match join_type {
+                    JoinType::Inner => (node, scope),
+                    JoinType::LeftSemi | JoinType::LeftAnti => (node, left_columns),
+                }
+            }
+        }
+    }
+}
+
+/// Add the plan for `tree` as an alternative to `group`, which should be the group of the join
+/// tree the graph was extracted from.
+pub fn add_join_tree_to_memo(
+    memo: &mut Memo,
+    group: GroupId,
+    graph: &JoinGraph,
+    tree: &JoinTree,
+) -> GroupId {
+    let new_group = memorize_rel(memo, graph.to_rel_node(tree));
+    memo.merge_group(group, new_group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_graph() {
+        let stats = [2, 2, 3]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, columns)| {
+                (
+                    TableId(idx),
+                    TableInfo {
+                        columns,
+                        rows: 10.0,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let rel = Arc::new(join_with_type(
+            JoinType::LeftSemi,
+            join(
+                scan(TableId(0)),
+                scan(TableId(1)),
+                and_pred(
+                    eq_pred(column_ref_pred(1), column_ref_pred(2)),
+                    eq_pred(column_ref_pred(0), const_pred(7)),
+                ),
+            ),
+            scan(TableId(2)),
+            eq_pred(column_ref_pred(3), column_ref_pred(5)),
+        ));
+        let graph = JoinGraph::extract(&rel, &stats).unwrap();
+        let offsets = graph
+            .relations
+            .iter()
+            .map(|relation| (relation.column_offset, relation.columns))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [(0, 2), (2, 2), (4, 3)]);
+        assert_eq!(graph.output_columns, [0, 1, 2, 3]);
+        // a join edge, a filter on t0, and the semi join edge from t1 to t2
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.join_type, edge.left, edge.right))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            [
+                (JoinType::Inner, 0b001, 0b010),
+                (JoinType::Inner, 0b001, 0),
+                (JoinType::LeftSemi, 0b010, 0b100),
+            ]
+        );
+        assert!(!graph.is_simple());
+        assert_eq!(graph.join_type_between(0b100, 0b011), None);
+
+        // the original order gives back the original plan, with the filter pushed down
+        let original = JoinTree::join(
+            JoinTree::join(JoinTree::Relation(0), JoinTree::Relation(1)),
+            JoinTree::Relation(2),
+        );
+        let expected = join_with_type(
+            JoinType::LeftSemi,
+            join(
+                filter(scan(TableId(0)), eq_pred(column_ref_pred(0), const_pred(7))),
+                scan(TableId(1)),
+                eq_pred(column_ref_pred(1), column_ref_pred(2)),
+            ),
+            scan(TableId(2)),
+            eq_pred(column_ref_pred(3), column_ref_pred(5)),
+        );
+        assert_eq!(*graph.to_rel_node(&original), expected);
+
+        // swapping t0 and t1 remaps the predicates, and a projection restores the column order
+        let swapped = JoinTree::join(
+            JoinTree::join(JoinTree::Relation(1), JoinTree::Relation(0)),
+            JoinTree::Relation(2),
+        );
+        let expected = project(
+            join_with_type(
+                JoinType::LeftSemi,
+                join(
+                    scan(TableId(1)),
+                    filter(scan(TableId(0)), eq_pred(column_ref_pred(0), const_pred(7))),
+                    eq_pred(column_ref_pred(3), column_ref_pred(0)),
+                ),
+                scan(TableId(2)),
+                eq_pred(column_ref_pred(1), column_ref_pred(5)),
+            ),
+            [2, 3, 0, 1]
+                .map(|column| Arc::new(column_ref_pred(column)))
+                .to_vec(),
+        );
+        assert_eq!(*graph.to_rel_node(&swapped), expected);
+        assert_eq!(column_count(&expected, &stats), column_count(&rel, &stats));
+    }
+}
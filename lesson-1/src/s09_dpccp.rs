@@ -0,0 +1,185 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::*;
+
+// DPccp (Moerkotte and Neumann, 2006) enumerates exactly the pairs of connected subgraphs and
+// their connected complements (csg-cmp pairs) of the join graph. Every pair is a join without
+// cross product, and each pair is enumerated once.
+
+/// All non-empty subsets of `set`, in increasing order, so that every subset comes before its
+/// supersets.
+pub fn subsets(set: RelSet) -> impl Iterator<Item = RelSet> {
+    let mut sub: RelSet = 0;
+    std::iter::from_fn(move || {
+        sub = sub.wrapping_sub(set) & set;
+        if sub == 0 {
+            return None;
+        }
+        Some(sub)
+    })
+}
+
+struct CsgCmpEnumerator<'a> {
+    graph: &'a JoinGraph,
+    pairs: Vec<(RelSet, RelSet)>,
+}
+
+impl CsgCmpEnumerator<'_> {
+    fn enumerate(&mut self) {
+        for idx in (0..self.graph.relations.len()).rev() {
+            let set = 1 << idx;
+            self.emit_csg(set);
+            self.enumerate_csg_rec(set, lower_or_equal(idx));
+        }
+    }
+
+    fn enumerate_csg_rec(&mut self, set: RelSet, excluded: RelSet) {
+        let neighbors = self.graph.neighbors(set) & !excluded;
+        for sub in subsets(neighbors) {
+            self.emit_csg(set | sub);
+        }
+        for sub in subsets(neighbors) {
+            self.enumerate_csg_rec(set | sub, excluded | neighbors);
+        }
+    }
+
+    fn emit_csg(&mut self, set: RelSet) {
+        let min = set.trailing_zeros() as usize;
+        let excluded = set | lower_or_equal(min);
+        let neighbors = self.graph.neighbors(set) & !excluded;
+        for idx in rel_set_iter(neighbors)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+        {
+            let cmp = 1 << idx;
+            self.pairs.push((set, cmp));
+            self.enumerate_cmp_rec(set, cmp, excluded | (neighbors & lower_or_equal(idx)));
+        }
+    }
+
+    fn enumerate_cmp_rec(&mut self, csg: RelSet, cmp: RelSet, excluded: RelSet) {
+        let neighbors = self.graph.neighbors(cmp) & !excluded;
+        for sub in subsets(neighbors) {
+            if self.graph.connected(csg, cmp | sub) {
+                self.pairs.push((csg, cmp | sub));
+            }
+        }
+        for sub in subsets(neighbors) {
+            self.enumerate_cmp_rec(csg, cmp | sub, excluded | neighbors);
+        }
+    }
+}
+
+/// Enumerate all csg-cmp pairs of the join graph. Each unordered pair is returned once.
+pub fn enumerate_csg_cmp_pairs(graph: &JoinGraph) -> Vec<(RelSet, RelSet)> {
+    let mut enumerator = CsgCmpEnumerator {
+        graph,
+        pairs: vec![],
+    };
+    enumerator.enumerate();
+    enumerator.pairs
+}
+
+/// Find the cheapest cross-product-free join tree from pairs of subsets. Smaller sets are
+/// always planned before the larger ones that contain them.
+pub fn solve_join_pairs(graph: &JoinGraph, mut pairs: Vec<(RelSet, RelSet)>) -> Option<JoinPlan> {
+    pairs.sort_by_key(|(left, right)| (left | right).count_ones());
+    let mut best: HashMap<RelSet, JoinPlan> = HashMap::new();
+    for idx in 0..graph.relations.len() {
+        best.insert(1 << idx, graph.relation_plan(idx));
+    }
+    for (left, right) in pairs {
+        let (Some(left), Some(right)) = (best.get(&left), best.get(&right)) else {
+            continue;
+        };
+        // C_out is symmetric, but we still try both sides in case the cost function is not
+        let plans = [(left, right), (right, left)]
+            .into_iter()
+            .filter(|(left, right)| graph.connected(left.tree.relations(), right.tree.relations()))
+            .map(|(left, right)| graph.join_plan(left, right))
+            .collect::<Vec<_>>();
+        for plan in plans {
+            let set = plan.tree.relations();
+            if best.get(&set).is_none_or(|best| plan.cost < best.cost) {
+                best.insert(set, plan);
+            }
+        }
+    }
+    best.remove(&graph.all_relations())
+}
+
+/// Returns the best join tree, or `None` if the graph is not connected or not a simple graph.
+pub fn dpccp(graph: &JoinGraph) -> Option<JoinPlan> {
+    if !graph.is_simple() {
+        return None;
+    }
+    solve_join_pairs(graph, enumerate_csg_cmp_pairs(graph))
+}
+
+/// Reorder the join tree at the root of the plan with DPccp. Returns `None` if the root is not a
+/// join or the join graph needs a cross product.
+pub fn reorder_joins_dpccp(node: Arc<RelNode>, stats: &dyn TableStats) -> Option<Arc<RelNode>> {
+    let graph = JoinGraph::extract(&node, stats)?;
+    let plan = dpccp(&graph)?;
+    Some(graph.to_rel_node(&plan.tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{join_tables, stats};
+
+    #[test]
+    fn test_dpccp() {
+        let stats = stats(2, [1000.0, 10.0, 1000.0, 10.0]);
+
+        // chain: (n^3 - n) / 6 csg-cmp pairs
+        let chain = join_tables(4, &[(0, 1), (1, 2), (2, 3)]);
+        let graph = JoinGraph::extract(&chain, &stats).unwrap();
+        assert_eq!(enumerate_csg_cmp_pairs(&graph).len(), 10);
+
+        // clique: (3^n - 2^(n + 1) + 1) / 2 csg-cmp pairs
+        let edges = [(0, 1), (0, 2), (1, 2), (0, 3), (1, 3), (2, 3)];
+        let clique = join_tables(4, &edges);
+        let graph = JoinGraph::extract(&clique, &stats).unwrap();
+        assert_eq!(enumerate_csg_cmp_pairs(&graph).len(), 25);
+
+        // the best plan is cheaper than the original order, and keeps the same result
+        let graph = JoinGraph::extract(&chain, &stats).unwrap();
+        let best = dpccp(&graph).unwrap();
+        let original = graph.plan_for_tree(&JoinTree::join(
+            JoinTree::join(
+                JoinTree::join(JoinTree::Relation(0), JoinTree::Relation(1)),
+                JoinTree::Relation(2),
+            ),
+            JoinTree::Relation(3),
+        ));
+        assert!(best.cost < original.cost);
+        let reordered = reorder_joins_dpccp(chain.clone(), &stats).unwrap();
+        assert_eq!(
+            estimate_cardinality(&reordered, &stats),
+            estimate_cardinality(&chain, &stats)
+        );
+
+        // a star with a relation joined on a condition that only refers to the star: the
+        // condition is a filter, and the graph is simple but disconnected, so there is no plan
+        // without a cross product
+        let disconnected = Arc::new(join(
+            join_tables(3, &[(0, 1), (0, 2)]),
+            scan(TableId(3)),
+            eq_pred(column_ref_pred(0), const_pred(1)),
+        ));
+        let split = JoinGraph::extract(&disconnected, &stats).unwrap();
+        assert!(split.is_simple());
+        assert_eq!(split.neighbors(0b0111), 0);
+        assert!(dpccp(&split).is_none());
+        assert!(reorder_joins_dpccp(disconnected, &stats).is_none());
+
+        // the reordered plan can be added to the memo as an alternative of the original join
+        let mut memo = Memo::new();
+        let group = memorize_rel(&mut memo, chain);
+        let group = add_join_tree_to_memo(&mut memo, group, &graph, &best.tree);
+        assert_eq!(memo.get_all_exprs_in_group(group).len(), 2);
+    }
+}
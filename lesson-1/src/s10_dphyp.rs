@@ -0,0 +1,234 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::*;
+
+// DPhyp (Moerkotte and Neumann, 2008) generalizes DPccp to hypergraphs. Predicates on three or
+// more relations and the ordering constraints of semi / anti joins are hyperedges between two sets
+// of relations, which the simple graph of DPccp can't express.
+
+struct DpHyp<'a> {
+    graph: &'a JoinGraph,
+    best: HashMap<RelSet, JoinPlan>,
+    pairs: usize,
+}
+
+impl DpHyp<'_> {
+    /// The representatives (lowest relation) of the hypernodes reachable from `set` through one
+    /// edge, not touching `excluded`.
+    fn neighborhood(&self, set: RelSet, excluded: RelSet) -> RelSet {
+        let excluded = excluded | set;
+        let mut candidates = vec![];
+        for edge in &self.graph.edges {
+            if edge.left == 0 || edge.right == 0 {
+                continue;
+            }
+            for (from, to) in [(edge.left, edge.right), (edge.right, edge.left)] {
+                if from & !set == 0 && to & excluded == 0 {
+                    candidates.push(to);
+                }
+            }
+        }
+        // only keep the minimal hypernodes
+        let mut neighbors = 0;
+        for candidate in &candidates {
+            let minimal = candidates
+                .iter()
+                .all(|other| other == candidate || other & !candidate != 0);
+            if minimal {
+                neighbors |= candidate & candidate.wrapping_neg();
+            }
+        }
+        neighbors
+    }
+
+    fn solve(&mut self) {
+        for idx in 0..self.graph.relations.len() {
+            self.best.insert(1 << idx, self.graph.relation_plan(idx));
+        }
+        for idx in (0..self.graph.relations.len()).rev() {
+            let set = 1 << idx;
+            self.emit_csg(set);
+            self.enumerate_csg_rec(set, lower_or_equal(idx));
+        }
+    }
+
+    fn enumerate_csg_rec(&mut self, set: RelSet, excluded: RelSet) {
+        let neighbors = self.neighborhood(set, excluded);
+        for sub in subsets(neighbors) {
+            // with hyperedges, a set of neighbors is not necessarily connected
+            if self.best.contains_key(&(set | sub)) {
+                self.emit_csg(set | sub);
+            }
+        }
+        for sub in subsets(neighbors) {
+            self.enumerate_csg_rec(set | sub, excluded | neighbors);
+        }
+    }
+
+    fn emit_csg(&mut self, set: RelSet) {
+        let min = set.trailing_zeros() as usize;
+        let excluded = set | lower_or_equal(min);
+        let neighbors = self.neighborhood(set, excluded);
+        for idx in rel_set_iter(neighbors)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+        {
+            let cmp = 1 << idx;
+            if self.connected(set, cmp) {
+                self.emit_csg_cmp(set, cmp);
+            }
+            self.enumerate_cmp_rec(set, cmp, excluded | (neighbors & lower_or_equal(idx)));
+        }
+    }
+
+    fn enumerate_cmp_rec(&mut self, csg: RelSet, cmp: RelSet, excluded: RelSet) {
+        let neighbors = self.neighborhood(cmp, excluded);
+        for sub in subsets(neighbors) {
+            if self.best.contains_key(&(cmp | sub)) && self.connected(csg, cmp | sub) {
+                self.emit_csg_cmp(csg, cmp | sub);
+            }
+        }
+        for sub in subsets(neighbors) {
+            self.enumerate_cmp_rec(csg, cmp | sub, excluded | neighbors);
+        }
+    }
+
+    fn connected(&self, left: RelSet, right: RelSet) -> bool {
+        self.graph.connected(left, right) || self.graph.connected(right, left)
+    }
+
+    fn emit_csg_cmp(&mut self, csg: RelSet, cmp: RelSet) {
+        self.pairs += 1;
+        let mut plans = vec![];
+        for (left, right) in [(csg, cmp), (cmp, csg)] {
+            if !self.graph.connected(left, right) {
+                continue;
+            }
+            let (Some(left), Some(right)) = (self.best.get(&left), self.best.get(&right)) else {
+                continue;
+            };
+            plans.push(self.graph.join_plan(left, right));
+        }
+        for plan in plans {
+            let set = plan.tree.relations();
+            if self.best.get(&set).is_none_or(|best| plan.cost < best.cost) {
+                self.best.insert(set, plan);
+            }
+        }
+    }
+}
+
+/// Returns the best join tree and the number of csg-cmp pairs considered, or `None` if the graph
+/// is not connected.
+pub fn dphyp_with_stats(graph: &JoinGraph) -> (Option<JoinPlan>, usize) {
+    let mut dphyp = DpHyp {
+        graph,
+        best: HashMap::new(),
+        pairs: 0,
+    };
+    dphyp.solve();
+    (dphyp.best.remove(&graph.all_relations()), dphyp.pairs)
+}
+
+pub fn dphyp(graph: &JoinGraph) -> Option<JoinPlan> {
+    dphyp_with_stats(graph).0
+}
+
+/// Reorder the join tree at the root of the plan with DPhyp. Returns `None` if the root is not a
+/// join or the join graph needs a cross product.
+pub fn reorder_joins_dphyp(node: Arc<RelNode>, stats: &dyn TableStats) -> Option<Arc<RelNode>> {
+    let graph = JoinGraph::extract(&node, stats)?;
+    let plan = dphyp(&graph)?;
+    Some(graph.to_rel_node(&plan.tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{join_tables, stats};
+
+    fn rows(n: usize) -> impl Iterator<Item = f64> {
+        (0..n).map(|idx| (10 + idx * 37 % 100) as f64)
+    }
+
+    fn col(table: usize) -> Arc<RelNode> {
+        Arc::new(column_ref_pred(table * 2))
+    }
+
+    #[test]
+    fn test_query_shapes() {
+        let n = 5;
+        let chain = (1..n).map(|i| (i - 1, i)).collect::<Vec<_>>();
+        let star = (1..n).map(|i| (0, i)).collect::<Vec<_>>();
+        let mut cycle = chain.clone();
+        cycle.push((0, n - 1));
+        let clique = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+
+        let stats = stats(2, rows(n));
+        for (edges, pairs) in [(chain, 20), (star, 32), (cycle, 40), (clique, 90)] {
+            let graph = JoinGraph::extract(&join_tables(n, &edges), &stats).unwrap();
+            let (plan, count) = dphyp_with_stats(&graph);
+            // on simple graphs, DPhyp enumerates the same pairs as DPccp
+            assert_eq!(count, pairs);
+            assert_eq!(enumerate_csg_cmp_pairs(&graph).len(), pairs);
+            assert_eq!(plan.unwrap().cost, dpccp(&graph).unwrap().cost);
+        }
+    }
+
+    #[test]
+    fn test_hyperedges() {
+        let stats = stats(2, rows(4));
+
+        // t0.a + t1.a = t2.a can only be evaluated once t0, t1 and t2 are joined
+        let node = join(
+            join(
+                join_tables(2, &[(0, 1)]),
+                scan(TableId(2)),
+                eq_pred(add_pred(col(0), col(1)), col(2)),
+            ),
+            scan(TableId(3)),
+            eq_pred(col(2), col(3)),
+        );
+        let graph = JoinGraph::extract(&Arc::new(node), &stats).unwrap();
+        assert!(!graph.is_simple());
+        assert!(dpccp(&graph).is_none());
+        let plan = dphyp(&graph).unwrap();
+        graph.to_rel_node(&plan.tree);
+
+        // (t0 JOIN t1) SEMI JOIN (t2 JOIN t3) on t1.a = t2.a: t2 and t3 must be joined first, and
+        // they must stay on the right side
+        let node = join_with_type(
+            JoinType::LeftSemi,
+            join_tables(2, &[(0, 1)]),
+            join(scan(TableId(2)), scan(TableId(3)), eq_pred(col(0), col(1))),
+            eq_pred(col(1), col(2)),
+        );
+        let node = Arc::new(node);
+        let graph = JoinGraph::extract(&node, &stats).unwrap();
+        let (plan, pairs) = dphyp_with_stats(&graph);
+        let plan = plan.unwrap();
+        // {t0, t1}, {t2, t3}, {t1, t2 + t3}, {t0, t1 + t2 + t3} and {t0 + t1, t2 + t3}
+        assert_eq!(pairs, 5);
+        fn check_semi_join(node: &RelNode) {
+            if let RelNode::Join(join) = node {
+                if join.join_type == JoinType::LeftSemi {
+                    let right = join.right.as_ref();
+                    assert!(
+                        matches!(right, RelNode::Join(join) if join.join_type == JoinType::Inner)
+                    );
+                }
+                check_semi_join(&join.left);
+                check_semi_join(&join.right);
+            }
+        }
+        let reordered = graph.to_rel_node(&plan.tree);
+        check_semi_join(&reordered);
+        assert_eq!(
+            column_count(&reordered, &stats),
+            column_count(&node, &stats)
+        );
+    }
+}
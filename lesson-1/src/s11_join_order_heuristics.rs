@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use super::*;
+
+// Exhaustive enumeration explodes beyond ~15 relations. For larger queries we start with a greedy
+// plan (GOO, Fegaras 1998) and improve it with simulated annealing over tree transformations.
+
+/// A small seeded PRNG (splitmix64), so that randomized optimization is reproducible.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number in `0..n`.
+    pub fn gen_range(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A random number in `[0, 1)`.
+    pub fn gen_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinOrderConfig {
+    /// Use exhaustive enumeration (DPhyp) up to this many relations.
+    pub max_exhaustive_relations: usize,
+    pub seed: u64,
+    /// Number of random moves tried by simulated annealing.
+    pub iterations: usize,
+    /// The initial temperature, relative to the cost of the greedy plan.
+    pub initial_temperature: f64,
+}
+
+impl Default for JoinOrderConfig {
+    fn default() -> Self {
+        Self {
+            max_exhaustive_relations: 12,
+            seed: 0,
+            iterations: 2000,
+            initial_temperature: 0.1,
+        }
+    }
+}
+
+impl JoinGraph {
+    /// Whether every join in the tree has a predicate, and respects semi / anti join constraints.
+    pub fn is_valid_tree(&self, tree: &JoinTree) -> bool {
+        match tree {
+            JoinTree::Relation(_) => true,
+            JoinTree::Join(left, right) => {
+                self.join_type_between(left.relations(), right.relations())
+                    .is_some()
+                    && self.is_valid_tree(left)
+                    && self.is_valid_tree(right)
+            }
+        }
+    }
+}
+
+/// Greedy operator ordering: repeatedly join the two plans with the smallest result.
+pub fn goo(graph: &JoinGraph) -> Option<JoinPlan> {
+    let mut plans = (0..graph.relations.len())
+        .map(|idx| graph.relation_plan(idx))
+        .collect::<Vec<_>>();
+    while plans.len() > 1 {
+        let mut best: Option<(usize, usize, JoinPlan)> = None;
+        for i in 0..plans.len() {
+            for j in 0..plans.len() {
+                let (left, right) = (&plans[i], &plans[j]);
+                if i == j || !graph.connected(left.tree.relations(), right.tree.relations()) {
+                    continue;
+                }
+                let plan = graph.join_plan(left, right);
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, _, best)| plan.cardinality < best.cardinality)
+                {
+                    best = Some((i, j, plan));
+                }
+            }
+        }
+        let (i, j, plan) = best?;
+        plans.remove(i.max(j));
+        plans.remove(i.min(j));
+        plans.push(plan);
+    }
+    plans.pop()
+}
+
+/// Number of joins in the tree.
+fn join_count(tree: &JoinTree) -> usize {
+    match tree {
+        JoinTree::Relation(_) => 0,
+        JoinTree::Join(left, right) => 1 + join_count(left) + join_count(right),
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Move {
+    /// `A JOIN B` -> `B JOIN A`
+    Commute,
+    /// `(A JOIN B) JOIN C` -> `A JOIN (B JOIN C)`
+    RotateRight,
+    /// `A JOIN (B JOIN C)` -> `(A JOIN B) JOIN C`
+    RotateLeft,
+    /// `(A JOIN B) JOIN C` -> `(A JOIN C) JOIN B`
+    Exchange,
+}
+
+/// Apply the move at the `target`-th join in pre-order. Returns `None` if the move doesn't apply.
+fn apply_move(tree: &JoinTree, target: &mut usize, mv: Move) -> Option<JoinTree> {
+    let JoinTree::Join(left, right) = tree else {
+        return None;
+    };
+    if *target > 0 {
+        *target -= 1;
+        if let Some(left) = apply_move(left, target, mv) {
+            return Some(JoinTree::join(left, (**right).clone()));
+        }
+        return apply_move(right, target, mv).map(|right| JoinTree::join((**left).clone(), right));
+    }
+    *target = usize::MAX;
+    match (mv, &**left, &**right) {
+        (Move::Commute, _, _) => Some(JoinTree::join((**right).clone(), (**left).clone())),
+        (Move::RotateRight, JoinTree::Join(a, b), c) => Some(JoinTree::join(
+            (**a).clone(),
+            JoinTree::join((**b).clone(), c.clone()),
+        )),
+        (Move::RotateLeft, a, JoinTree::Join(b, c)) => Some(JoinTree::join(
+            JoinTree::join(a.clone(), (**b).clone()),
+            (**c).clone(),
+        )),
+        (Move::Exchange, JoinTree::Join(a, b), c) => Some(JoinTree::join(
+            JoinTree::join((**a).clone(), c.clone()),
+            (**b).clone(),
+        )),
+        _ => None,
+    }
+}
+
+/// Improve a plan with simulated annealing. With the same seed, the result is always the same.
+/// A plan without joins is returned unchanged.
+pub fn simulated_annealing(
+    graph: &JoinGraph,
+    plan: JoinPlan,
+    config: &JoinOrderConfig,
+) -> JoinPlan {
+    if join_count(&plan.tree) == 0 {
+        return plan;
+    }
+    let mut rng = Rng::new(config.seed);
+    let mut temperature = plan.cost * config.initial_temperature;
+    let mut current = plan.clone();
+    let mut best = plan;
+    let moves = [
+        Move::Commute,
+        Move::RotateRight,
+        Move::RotateLeft,
+        Move::Exchange,
+    ];
+    for _ in 0..config.iterations {
+        let mut target = rng.gen_range(join_count(&current.tree));
+        let mv = moves[rng.gen_range(moves.len())];
+        let Some(tree) = apply_move(&current.tree, &mut target, mv) else {
+            continue;
+        };
+        if !graph.is_valid_tree(&tree) {
+            continue;
+        }
+        let candidate = graph.plan_for_tree(&tree);
+        let delta = candidate.cost - current.cost;
+        if delta <= 0.0 || (temperature > 0.0 && rng.gen_f64() < (-delta / temperature).exp()) {
+            current = candidate;
+            if current.cost < best.cost {
+                best = current.clone();
+            }
+        }
+        temperature *= 0.995;
+    }
+    best
+}
+
+/// Pick a join order, using DPhyp for small queries and greedy plus simulated annealing for
+/// queries with more than `config.max_exhaustive_relations` relations.
+pub fn order_joins(graph: &JoinGraph, config: &JoinOrderConfig) -> Option<JoinPlan> {
+    if graph.relations.len() <= config.max_exhaustive_relations {
+        return dphyp(graph);
+    }
+    let plan = goo(graph)?;
+    Some(simulated_annealing(graph, plan, config))
+}
+
+pub fn reorder_joins(
+    node: Arc<RelNode>,
+    stats: &dyn TableStats,
+    config: &JoinOrderConfig,
+) -> Option<Arc<RelNode>> {
+    let graph = JoinGraph::extract(&node, stats)?;
+    let plan = order_joins(&graph, config)?;
+    Some(graph.to_rel_node(&plan.tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    /// A chain of `n` tables with varying sizes, each joined with the previous one.
+    fn chain(n: usize) -> (Arc<RelNode>, HashMap<TableId, TableInfo>) {
+        let mut rng = Rng::new(42);
+        let stats = stats(1, (0..n).map(|_| (10 + rng.gen_range(10000)) as f64));
+        let mut node = Arc::new(scan(TableId(0)));
+        for idx in 1..n {
+            let cond = eq_pred(column_ref_pred(idx - 1), column_ref_pred(idx));
+            node = Arc::new(join(node, scan(TableId(idx)), cond));
+        }
+        (node, stats)
+    }
+
+    #[test]
+    fn test_large_join() {
+        let (node, stats) = chain(20);
+        let graph = JoinGraph::extract(&node, &stats).unwrap();
+        let config = JoinOrderConfig::default();
+
+        let greedy = goo(&graph).unwrap();
+        assert!(graph.is_valid_tree(&greedy.tree));
+        let plan = order_joins(&graph, &config).unwrap();
+        assert!(graph.is_valid_tree(&plan.tree));
+        assert!(plan.cost <= greedy.cost);
+        // deterministic given the seed
+        assert_eq!(order_joins(&graph, &config).unwrap().tree, plan.tree);
+
+        // small queries are still planned exhaustively
+        let (node, stats) = chain(6);
+        let graph = JoinGraph::extract(&node, &stats).unwrap();
+        let plan = order_joins(&graph, &config).unwrap();
+        assert_eq!(plan.cost, dpccp(&graph).unwrap().cost);
+        assert!(reorder_joins(node, &stats, &config).is_some());
+
+        // there is no move to make on a single relation
+        let single = graph.relation_plan(0);
+        let plan = simulated_annealing(&graph, single.clone(), &config);
+        assert_eq!(plan.tree, single.tree);
+    }
+}
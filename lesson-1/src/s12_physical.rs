@@ -0,0 +1,710 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use super::*;
+
+// All the nodes we had so far are logical: they say what to compute, but not how. Implementation
+// rules add physical alternatives (hash join, nested-loop join, ...) to the same memo groups as
+// the logical expressions, and the optimizer picks the cheapest physical expression of each group.
+
+impl MemoRelNode {
+    pub fn is_logical(&self) -> bool {
+        matches!(
+            self,
+            MemoRelNode::Scan(_)
+                | MemoRelNode::Join(_)
+                | MemoRelNode::Filter(_)
+                | MemoRelNode::Project(_)
+                | MemoRelNode::Sort(_)
+                | MemoRelNode::Aggregate(_)
+                | MemoRelNode::Limit(_)
+        )
+    }
+
+    pub fn is_physical(&self) -> bool {
+        matches!(
+            self,
+            MemoRelNode::TableScan(_)
+                | MemoRelNode::PhysicalFilter(_)
+                | MemoRelNode::HashJoin(_)
+                | MemoRelNode::NestedLoopJoin(_)
+                | MemoRelNode::SortMergeJoin(_)
+                | MemoRelNode::IndexScan(_)
+                | MemoRelNode::IndexLookup(_)
+                | MemoRelNode::IndexNestedLoopJoin(_)
+                | MemoRelNode::Project(_)
+                | MemoRelNode::Sort(_)
+                | MemoRelNode::Aggregate(_)
+                | MemoRelNode::Limit(_)
+        )
+    }
+
+    /// The children that produce rows, as opposed to scalar expressions.
+    pub fn rel_children(&self) -> Vec<GroupId> {
+        match self {
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join) => vec![join.left, join.right],
+            MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter) => {
+                vec![filter.child]
+            }
+            MemoRelNode::Project(project) => vec![project.child],
+            MemoRelNode::Sort(sort) => vec![sort.child],
+            MemoRelNode::Aggregate(aggregate) => vec![aggregate.child],
+            MemoRelNode::Limit(limit) => vec![limit.child],
+            _ => vec![],
+        }
+    }
+}
+
+/// Properties shared by all expressions of a group.
+#[derive(Debug, Clone)]
+pub struct LogicalProps {
+    pub columns: usize,
+    pub cardinality: f64,
+    pub keys: KeyProps,
+}
+
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    /// Processing one tuple in any operator.
+    pub cpu_tuple: f64,
+    /// Inserting one tuple into a hash table.
+    pub hash_build: f64,
+    /// Looking up one tuple in a hash table.
+    pub hash_probe: f64,
+    /// Sorting, per `n log n` comparisons.
+    pub sort: f64,
+    /// Descending an index to the first matching entry.
+    pub index_probe: f64,
+    /// Reading one tuple through an index, which is random access unlike a table scan.
+    pub index_tuple: f64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            cpu_tuple: 1.0,
+            hash_build: 2.0,
+            hash_probe: 1.5,
+            sort: 1.0,
+            index_probe: 4.0,
+            index_tuple: 2.0,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn sort_cost(&self, rows: f64) -> f64 {
+        rows * rows.max(2.0).log2() * self.sort
+    }
+
+    /// The cost of the operator itself, given the cardinality of its output and its inputs.
+    pub fn operator_cost(&self, expr: &MemoRelNode, output: f64, inputs: &[f64]) -> f64 {
+        match expr {
+            MemoRelNode::TableScan(_) => output * self.cpu_tuple,
+            MemoRelNode::IndexScan(_) => self.index_probe + output * self.index_tuple,
+            MemoRelNode::PhysicalFilter(_) | MemoRelNode::Project(_) => inputs[0] * self.cpu_tuple,
+            MemoRelNode::HashJoin(_) => {
+                inputs[1] * self.hash_build + inputs[0] * self.hash_probe + output * self.cpu_tuple
+            }
+            MemoRelNode::NestedLoopJoin(_) => (inputs[0] * inputs[1] + output) * self.cpu_tuple,
+            // the inputs are sorted by enforcers
+            MemoRelNode::SortMergeJoin(_) => (inputs[0] + inputs[1] + output) * self.cpu_tuple,
+            // the right input is an index lookup, its cardinality is per row of the left input
+            MemoRelNode::IndexNestedLoopJoin(_) => {
+                inputs[0] * (self.index_probe + inputs[1] * self.index_tuple)
+                    + output * self.cpu_tuple
+            }
+            MemoRelNode::Sort(_) => self.sort_cost(inputs[0]),
+            // a hash aggregation
+            MemoRelNode::Aggregate(_) => inputs[0] * self.hash_build + output * self.cpu_tuple,
+            MemoRelNode::Limit(_) => output * self.cpu_tuple,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Returns the pairs of (left column, right column) compared by `=` in the join condition, with
+/// right columns relative to the right input.
+pub fn equi_join_keys(cond: &Arc<RelNode>, left_columns: usize) -> Vec<(usize, usize)> {
+    let mut keys = vec![];
+    for pred in conjuncts(cond) {
+        let RelNode::Eq(eq) = &*pred else {
+            continue;
+        };
+        let (RelNode::ColumnRef(a), RelNode::ColumnRef(b)) = (&*eq.left, &*eq.right) else {
+            continue;
+        };
+        let (a, b) = (a.column, b.column);
+        if a < left_columns && b >= left_columns {
+            keys.push((a, b - left_columns));
+        } else if b < left_columns && a >= left_columns {
+            keys.push((b, a - left_columns));
+        }
+    }
+    keys
+}
+
+#[derive(Debug, Clone)]
+pub struct Winner {
+    pub expr: MemoRelNode,
+    pub cost: f64,
+    /// The properties required from each relational child.
+    pub child_props: Vec<PhysicalProps>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchCounters {
+    /// Physical expressions considered by the search.
+    pub costed_exprs: usize,
+    /// Physical expressions abandoned because they exceeded the cost bound.
+    pub pruned_exprs: usize,
+    /// Transformation rules applied during exploration.
+    pub rule_applications: usize,
+}
+
+pub struct Optimizer<'a> {
+    pub memo: Memo,
+    pub stats: &'a dyn TableStats,
+    pub cost_model: CostModel,
+    props: HashMap<GroupId, LogicalProps>,
+    winners: HashMap<(GroupId, PhysicalProps), Option<Winner>>,
+    /// No plan of the group with the properties costs this much or less.
+    lower_bounds: HashMap<(GroupId, PhysicalProps), f64>,
+    /// Prune alternatives that can't be cheaper than the best plan found so far.
+    pub pruning: bool,
+    pub counters: SearchCounters,
+    pub config: OptimizerConfig,
+    pub(crate) deadline: Option<Instant>,
+    /// Whether the search stopped early because the budget ran out.
+    pub truncated: bool,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(stats: &'a dyn TableStats) -> Self {
+        Self {
+            memo: Memo::new(),
+            stats,
+            cost_model: CostModel::default(),
+            props: HashMap::new(),
+            winners: HashMap::new(),
+            lower_bounds: HashMap::new(),
+            pruning: true,
+            counters: SearchCounters::default(),
+            config: OptimizerConfig::default(),
+            deadline: None,
+            truncated: false,
+        }
+    }
+
+    pub fn logical_props(&mut self, group: GroupId) -> LogicalProps {
+        let group = self.memo.reduce_group(group);
+        if let Some(props) = self.props.get(&group) {
+            return props.clone();
+        }
+        // all expressions have the same properties, but estimates may differ in the last bits, so
+        // take the same one regardless of the order of the expressions
+        let props = self
+            .memo
+            .get_all_exprs_in_group(group)
+            .iter()
+            .filter(|expr| expr.is_logical() || expr.is_physical())
+            .map(|expr| self.expr_props(expr))
+            .min_by(|a, b| a.cardinality.total_cmp(&b.cardinality))
+            .expect("not a relational group");
+        self.props.insert(group, props.clone());
+        props
+    }
+
+    fn expr_props(&mut self, expr: &MemoRelNode) -> LogicalProps {
+        let (columns, cardinality) = self.expr_size(expr);
+        let keys = self.derive_keys(expr);
+        let cardinality = self.bound_cardinality(expr, &keys, cardinality);
+        LogicalProps {
+            columns,
+            cardinality,
+            keys,
+        }
+    }
+
+    /// The properties of the inputs of a join. The right side of an index nested-loop join is a
+    /// lookup, but the join finds matches in the whole table.
+    pub fn join_input_props(&mut self, join: &MemoJoin) -> (LogicalProps, LogicalProps) {
+        let left = self.logical_props(join.left);
+        let mut right = self.logical_props(join.right);
+        if let Some(MemoRelNode::IndexLookup(lookup)) =
+            self.memo.get_all_exprs_in_group(join.right).first()
+        {
+            right.cardinality = self.stats.row_count(&lookup.table);
+        }
+        (left, right)
+    }
+
+    /// The number of columns and the estimated number of rows.
+    fn expr_size(&mut self, expr: &MemoRelNode) -> (usize, f64) {
+        let conjuncts_of =
+            |memo: &Memo, pred: GroupId| conjuncts(&generate_one_binding(memo, pred)).len() as i32;
+        match expr {
+            MemoRelNode::Scan(scan) | MemoRelNode::TableScan(scan) => (
+                self.stats.column_count(&scan.table),
+                self.stats.row_count(&scan.table),
+            ),
+            MemoRelNode::IndexScan(scan) => (
+                self.stats.column_count(&scan.table),
+                index_cardinality(self.stats, &scan.table, &scan.index, scan.prefix.len()),
+            ),
+            MemoRelNode::IndexLookup(lookup) => (
+                self.stats.column_count(&lookup.table),
+                index_cardinality(self.stats, &lookup.table, &lookup.index, lookup.keys.len()),
+            ),
+            MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter) => {
+                let child = self.logical_props(filter.child);
+                let selectivity =
+                    DEFAULT_SELECTIVITY.powi(conjuncts_of(&self.memo, filter.predicate));
+                (child.columns, child.cardinality * selectivity)
+            }
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join) => {
+                let (left, right) = self.join_input_props(join);
+                let selectivity = DEFAULT_SELECTIVITY.powi(conjuncts_of(&self.memo, join.cond));
+                match join.join_type {
+                    JoinType::Inner => (
+                        left.columns + right.columns,
+                        left.cardinality * right.cardinality * selectivity,
+                    ),
+                    JoinType::LeftSemi => (left.columns, left.cardinality * selectivity),
+                    JoinType::LeftAnti => (left.columns, left.cardinality * (1.0 - selectivity)),
+                }
+            }
+            MemoRelNode::Project(project) => (
+                project.exprs.len(),
+                self.logical_props(project.child).cardinality,
+            ),
+            MemoRelNode::Sort(sort) => {
+                let child = self.logical_props(sort.child);
+                (child.columns, child.cardinality)
+            }
+            MemoRelNode::Aggregate(aggregate) => {
+                let child = self.logical_props(aggregate.child);
+                (
+                    aggregate.group_by.len() + aggregate.aggs.len(),
+                    aggregate_cardinality(child.cardinality, aggregate.group_by.len()),
+                )
+            }
+            MemoRelNode::Limit(limit) => {
+                let child = self.logical_props(limit.child);
+                (child.columns, child.cardinality.min(limit.limit as f64))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// The physical alternatives of a logical expression.
+    pub fn implementations(&mut self, expr: &MemoRelNode) -> Vec<MemoRelNode> {
+        match expr {
+            MemoRelNode::Scan(scan) => {
+                let mut res = vec![MemoRelNode::TableScan(scan.clone())];
+                res.extend(self.full_index_scans(scan));
+                res
+            }
+            MemoRelNode::Filter(filter) => {
+                let mut res = vec![MemoRelNode::PhysicalFilter(filter.clone())];
+                res.extend(self.index_range_scans(filter));
+                res
+            }
+            MemoRelNode::Join(join) => {
+                let mut res = vec![MemoRelNode::NestedLoopJoin(join.clone())];
+                res.extend(self.index_nested_loop_joins(join));
+                let left_columns = self.logical_props(join.left).columns;
+                let cond = generate_one_binding(&self.memo, join.cond);
+                if !equi_join_keys(&cond, left_columns).is_empty() {
+                    res.push(MemoRelNode::HashJoin(join.clone()));
+                    if join.join_type == JoinType::Inner {
+                        res.push(MemoRelNode::SortMergeJoin(join.clone()));
+                    }
+                }
+                res
+            }
+            MemoRelNode::Aggregate(aggregate) => self.distinct_eliminations(aggregate),
+            _ => vec![],
+        }
+    }
+
+    /// Apply `join_commute` and `join_assoc` until no new expressions are found, or the budget
+    /// runs out.
+    pub fn explore(&mut self) {
+        self.explore_in_rounds(|optimizer, exprs| {
+            exprs
+                .iter()
+                .map(|expr| optimizer.explore_expr(expr))
+                .collect()
+        });
+    }
+
+    /// The bindings the exploration rules make of the expression, or `None` if the time ran out.
+    pub(crate) fn explore_expr(&self, expr: &MemoRelNode) -> Option<Vec<Arc<BindRelNode>>> {
+        if self.out_of_time() {
+            return None;
+        }
+        Some(explore_expr(&self.memo, self.stats, expr))
+    }
+
+    /// Explore in rounds. `explore_round` makes the bindings of all expressions from the memo as
+    /// it was at the start of the round, and they are added in order, so the memo doesn't depend
+    /// on how `explore_round` computes them.
+    pub(crate) fn explore_in_rounds(
+        &mut self,
+        explore_round: impl Fn(&Self, &[MemoRelNode]) -> Vec<Option<Vec<Arc<BindRelNode>>>>,
+    ) {
+        loop {
+            let num_exprs = self.memo.num_exprs();
+            let mut exprs = vec![];
+            for group in self.memo.group_ids() {
+                for expr in self.memo.get_all_exprs_in_group(group) {
+                    exprs.push((group, expr));
+                }
+            }
+            // each expression takes two rule applications
+            let remaining = self.config.max_rule_applications;
+            let remaining = remaining.saturating_sub(self.counters.rule_applications);
+            let rules_exhausted = exprs.len() > remaining.div_ceil(2);
+            exprs.truncate(remaining.div_ceil(2));
+            let nodes = exprs
+                .iter()
+                .map(|(_, expr)| expr.clone())
+                .collect::<Vec<_>>();
+            let bindings = explore_round(self, &nodes);
+            for ((group, _), bindings) in exprs.into_iter().zip(bindings) {
+                let Some(bindings) = bindings.filter(|_| !self.memo_exhausted()) else {
+                    self.truncated = true;
+                    return;
+                };
+                self.counters.rule_applications += 2;
+                for binding in bindings {
+                    add_explored_binding(&mut self.memo, group, binding);
+                }
+            }
+            if rules_exhausted {
+                self.truncated = true;
+                return;
+            }
+            if self.memo.num_exprs() == num_exprs {
+                break;
+            }
+        }
+    }
+
+    /// Add the physical alternatives of all logical expressions to the memo.
+    pub fn implement(&mut self) {
+        for group in self.memo.group_ids() {
+            for expr in self.memo.get_all_exprs_in_group(group) {
+                if !expr.is_logical() {
+                    continue;
+                }
+                for physical in self.implementations(&expr) {
+                    self.memo.add_expr_to_group(group, physical);
+                }
+            }
+        }
+    }
+
+    /// Find the cheapest physical expression of the group that delivers the required properties.
+    /// Returns `None` if there is none.
+    pub fn optimize_group(&mut self, group: GroupId, required: &PhysicalProps) -> Option<Winner> {
+        self.optimize_group_with_bound(group, required, f64::INFINITY)
+    }
+
+    /// Like `optimize_group`, but gives up on alternatives that cost more than `bound`. Returns
+    /// `None` if there is no plan within `bound`.
+    pub fn optimize_group_with_bound(
+        &mut self,
+        group: GroupId,
+        required: &PhysicalProps,
+        bound: f64,
+    ) -> Option<Winner> {
+        let group = self.memo.reduce_group(group);
+        let key = (group, required.clone());
+        if let Some(winner) = self.winners.get(&key) {
+            return winner.clone().filter(|winner| winner.cost <= bound);
+        }
+        if self
+            .lower_bounds
+            .get(&key)
+            .is_some_and(|lower| bound <= *lower)
+        {
+            return None;
+        }
+        // break cycles introduced by merging groups
+        self.winners.insert(key.clone(), None);
+        let bound = if self.pruning { bound } else { f64::INFINITY };
+        let output = self.logical_props(group).cardinality;
+        let mut best: Option<Winner> = None;
+        'next_expr: for expr in self.memo.get_all_exprs_in_group(group) {
+            if !expr.is_physical() {
+                continue;
+            }
+            let Some(child_props) = self.required_child_props(&expr, required) else {
+                continue;
+            };
+            self.counters.costed_exprs += 1;
+            let limit = match &best {
+                Some(best) if self.pruning => best.cost,
+                _ => bound,
+            };
+            let children = expr.rel_children();
+            let inputs = children
+                .iter()
+                .map(|child| self.logical_props(*child).cardinality)
+                .collect::<Vec<_>>();
+            let operator_cost = self.cost_model.operator_cost(&expr, output, &inputs);
+            if operator_cost > limit {
+                self.counters.pruned_exprs += 1;
+                continue;
+            }
+            let mut cost = 0.0;
+            for (child, props) in children.into_iter().zip(&child_props) {
+                let child_bound = limit - operator_cost - cost;
+                let Some(winner) = self.optimize_group_with_bound(child, props, child_bound) else {
+                    if child_bound < f64::INFINITY {
+                        self.counters.pruned_exprs += 1;
+                    }
+                    continue 'next_expr;
+                };
+                cost += winner.cost;
+            }
+            cost += operator_cost;
+            let candidate = Winner {
+                expr,
+                cost,
+                child_props,
+            };
+            if self.is_better(&candidate, best.as_ref()) {
+                best = Some(candidate);
+            }
+            // settle for the best plan so far
+            if best.is_some() && self.out_of_time() {
+                self.truncated = true;
+                break;
+            }
+        }
+        let limit = match &best {
+            Some(best) if self.pruning => best.cost,
+            _ => bound,
+        };
+        if let Some(enforcer) = self.enforce(group, required, limit) {
+            if self.is_better(&enforcer, best.as_ref()) {
+                best = Some(enforcer);
+            }
+        }
+        if best.is_none() && bound < f64::INFINITY {
+            // there may be a plan, but not one within the bound
+            self.winners.remove(&key);
+            self.lower_bounds.insert(key, bound);
+            return None;
+        }
+        self.winners.insert(key, best.clone());
+        best
+    }
+
+    fn is_better(&self, candidate: &Winner, best: Option<&Winner>) -> bool {
+        let Some(best) = best else {
+            return true;
+        };
+        if candidate.cost != best.cost {
+            return candidate.cost < best.cost;
+        }
+        // break ties on the expressions, so that the result doesn't depend on the order of the
+        // expressions in the memo
+        let expr = |winner: &Winner| self.memo.reduce_expr(winner.expr.clone());
+        (expr(candidate), &candidate.child_props) < (expr(best), &best.child_props)
+    }
+
+    /// Build the cheapest physical plan of the group that delivers the required properties.
+    pub fn best_plan(&mut self, group: GroupId, required: &PhysicalProps) -> Option<Arc<RelNode>> {
+        let winner = self.optimize_group(group, required)?;
+        self.winner_plan(&winner)
+    }
+
+    fn winner_plan(&mut self, winner: &Winner) -> Option<Arc<RelNode>> {
+        let props = &winner.child_props;
+        let plan = match &winner.expr {
+            MemoRelNode::TableScan(scan) => RelNode::TableScan(scan.clone()),
+            MemoRelNode::IndexScan(scan) => RelNode::IndexScan(scan.clone()),
+            MemoRelNode::IndexLookup(lookup) => RelNode::IndexLookup(lookup.clone()),
+            MemoRelNode::PhysicalFilter(filter) => RelNode::PhysicalFilter(Filter {
+                child: self.best_plan(filter.child, &props[0])?,
+                predicate: generate_one_binding(&self.memo, filter.predicate),
+            }),
+            MemoRelNode::HashJoin(join) => RelNode::HashJoin(self.best_join(join, props)?),
+            MemoRelNode::NestedLoopJoin(join) => {
+                RelNode::NestedLoopJoin(self.best_join(join, props)?)
+            }
+            MemoRelNode::SortMergeJoin(join) => {
+                RelNode::SortMergeJoin(self.best_join(join, props)?)
+            }
+            MemoRelNode::IndexNestedLoopJoin(join) => {
+                RelNode::IndexNestedLoopJoin(self.best_join(join, props)?)
+            }
+            MemoRelNode::Project(project) => RelNode::Project(Project {
+                child: self.best_plan(project.child, &props[0])?,
+                exprs: project
+                    .exprs
+                    .iter()
+                    .map(|expr| generate_one_binding(&self.memo, *expr))
+                    .collect(),
+            }),
+            MemoRelNode::Sort(sort) => RelNode::Sort(Sort {
+                child: self.best_plan(sort.child, &props[0])?,
+                keys: sort.keys.clone(),
+            }),
+            MemoRelNode::Aggregate(aggregate) => RelNode::Aggregate(Aggregate {
+                child: self.best_plan(aggregate.child, &props[0])?,
+                group_by: aggregate.group_by.clone(),
+                aggs: aggregate.aggs.clone(),
+            }),
+            MemoRelNode::Limit(limit) => RelNode::Limit(Limit {
+                child: self.best_plan(limit.child, &props[0])?,
+                limit: limit.limit,
+            }),
+            _ => unreachable!(),
+        };
+        Some(Arc::new(plan))
+    }
+
+    fn best_join(&mut self, join: &MemoJoin, props: &[PhysicalProps]) -> Option<Join> {
+        Some(Join {
+            join_type: join.join_type,
+            left: self.best_plan(join.left, &props[0])?,
+            right: self.best_plan(join.right, &props[1])?,
+            cond: generate_one_binding(&self.memo, join.cond),
+        })
+    }
+
+    /// Add the plan to the memo, explore and implement it, and return the cheapest physical plan.
+    pub fn optimize(&mut self, rel: Arc<RelNode>) -> Option<Arc<RelNode>> {
+        let group = memorize_rel(&mut self.memo, rel);
+        self.explore();
+        self.implement();
+        self.best_plan(group, &PhysicalProps::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_implementation() {
+        let stats = stats(2, [10000.0, 100.0]);
+
+        let mut optimizer = Optimizer::new(&stats);
+        let best = optimizer.optimize(Arc::new(plan())).unwrap();
+        let expected = RelNode::PhysicalFilter(Filter {
+            child: Arc::new(RelNode::HashJoin(Join {
+                join_type: JoinType::Inner,
+                left: Arc::new(RelNode::TableScan(Scan { table: TableId(0) })),
+                right: Arc::new(RelNode::TableScan(Scan { table: TableId(1) })),
+                cond: Arc::new(eq_pred(column_ref_pred(1), column_ref_pred(3))),
+            })),
+            predicate: Arc::new(eq_pred(column_ref_pred(2), const_pred(3))),
+        });
+        assert_eq!(best.as_ref(), &expected);
+
+        // logical and physical expressions live in the same group: the join, its commuted form
+        // and the three join implementations
+        let RelNode::Filter(filter) = plan() else {
+            unreachable!()
+        };
+        let join_group = memorize_rel(&mut optimizer.memo, filter.child);
+        let exprs = optimizer.memo.get_all_exprs_in_group(join_group);
+        assert_eq!(exprs.len(), 5);
+        assert_eq!(exprs.iter().filter(|expr| expr.is_logical()).count(), 2);
+
+        // without an equi-join condition, only nested-loop join applies
+        let rel = join(
+            scan(TableId(0)),
+            scan(TableId(1)),
+            eq_pred(
+                add_pred(column_ref_pred(0), column_ref_pred(2)),
+                const_pred(3),
+            ),
+        );
+        let mut optimizer = Optimizer::new(&stats);
+        let best = optimizer.optimize(Arc::new(rel)).unwrap();
+        assert!(matches!(&*best, RelNode::NestedLoopJoin(_)));
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let stats = stats(2, [100.0, 20.0, 300.0, 1000.0, 10.0, 5000.0]);
+        let mut rel = scan(TableId(0));
+        for idx in 1..6 {
+            let cond = eq_pred(column_ref_pred(idx * 2 - 2), column_ref_pred(idx * 2));
+            rel = join(rel, scan(TableId(idx)), cond);
+        }
+        let rel = Arc::new(rel);
+
+        let optimize = |pruning| {
+            let mut optimizer = Optimizer::new(&stats);
+            optimizer.pruning = pruning;
+            let group = memorize_rel(&mut optimizer.memo, rel.clone());
+            optimizer.explore();
+            optimizer.implement();
+            let winner = optimizer
+                .optimize_group(group, &PhysicalProps::default())
+                .unwrap();
+            let best = optimizer.best_plan(group, &PhysicalProps::default());
+            (best, winner.cost, optimizer.counters)
+        };
+        let (exhaustive, exhaustive_cost, exhaustive_counters) = optimize(false);
+        let (pruned, pruned_cost, pruned_counters) = optimize(true);
+        assert_eq!(exhaustive, pruned);
+        assert_eq!(exhaustive_cost, pruned_cost);
+        assert_eq!(exhaustive_counters.pruned_exprs, 0);
+        assert!(pruned_counters.pruned_exprs > 0);
+        assert!(pruned_counters.costed_exprs < exhaustive_counters.costed_exprs);
+    }
+
+    #[test]
+    fn test_exploration() {
+        let stats = stats(2, [10.0; 3]);
+        let mut db = Database::new();
+        for idx in 0..3 {
+            let rows = (0..6)
+                .map(|row| vec![Some(row % 3), Some((row + idx as i64) % 4)])
+                .collect();
+            db.insert(&TableId(idx), rows);
+        }
+        let rel = Arc::new(filter(
+            join(
+                join(
+                    scan(TableId(0)),
+                    scan(TableId(1)),
+                    eq_pred(column_ref_pred(1), column_ref_pred(2)),
+                ),
+                scan(TableId(2)),
+                eq_pred(column_ref_pred(3), column_ref_pred(4)),
+            ),
+            eq_pred(column_ref_pred(5), const_pred(1)),
+        ));
+        assert!(!execute(&rel, &db).is_empty());
+
+        // every plan of the explored memo returns the same rows, in the same column order
+        let mut optimizer = Optimizer::new(&stats);
+        let group = memorize_rel(&mut optimizer.memo, rel.clone());
+        optimizer.explore();
+        let plans = bindings(&optimizer.memo, group).count();
+        // both orders of each join, and both associations
+        assert_eq!(plans, 8);
+        assert!(check_bindings(&rel, &optimizer.memo, group, plans, &db).is_empty());
+
+        let best = Optimizer::new(&stats).optimize(rel.clone()).unwrap();
+        assert!(check_equivalence(&rel, [best], &db).is_empty());
+    }
+}
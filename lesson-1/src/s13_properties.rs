@@ -0,0 +1,199 @@
+use super::*;
+
+// Some operators produce their rows in a particular order, and some need their inputs in order.
+// Such physical properties are not part of the logical result, so the memo still stores a group
+// once, and the optimizer searches for the best plan of a group for each set of required
+// properties. When no expression delivers a property by itself, an enforcer (`Sort`) is put on top
+// of the best plan that doesn't.
+
+/// Physical properties of the output of an operator. Only the sort order for now, data
+/// distribution would be another field.
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct PhysicalProps {
+    pub order: Vec<SortKey>,
+}
+
+impl PhysicalProps {
+    pub fn sorted(order: Vec<SortKey>) -> Self {
+        Self { order }
+    }
+
+    /// Whether the properties don't require anything.
+    pub fn is_any(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Whether a plan with these properties can be used where `required` is needed.
+    pub fn satisfies(&self, required: &PhysicalProps) -> bool {
+        self.order.starts_with(&required.order)
+    }
+}
+
+fn ascending(columns: impl Iterator<Item = usize>) -> Vec<SortKey> {
+    columns
+        .map(|column| SortKey {
+            column,
+            descending: false,
+        })
+        .collect()
+}
+
+impl Optimizer<'_> {
+    /// The properties `expr` requires from its relational children to deliver `required`, or
+    /// `None` if it can't deliver them.
+    pub fn required_child_props(
+        &mut self,
+        expr: &MemoRelNode,
+        required: &PhysicalProps,
+    ) -> Option<Vec<PhysicalProps>> {
+        let any = PhysicalProps::default;
+        match expr {
+            MemoRelNode::TableScan(_) | MemoRelNode::IndexLookup(_) => {
+                required.is_any().then(Vec::new)
+            }
+            // the rows are in index order, and with a prefix fixed, also in the order of the
+            // remaining index columns
+            MemoRelNode::IndexScan(scan) => {
+                let columns = index_columns(self.stats, &scan.table, &scan.index);
+                let all = PhysicalProps::sorted(ascending(columns.iter().copied()));
+                let rest = ascending(columns[scan.prefix.len()..].iter().copied());
+                (all.satisfies(required) || PhysicalProps::sorted(rest).satisfies(required))
+                    .then(Vec::new)
+            }
+            MemoRelNode::PhysicalFilter(_) => Some(vec![required.clone()]),
+            MemoRelNode::Project(project) => {
+                // the order is kept for columns that are passed through
+                let mut order = vec![];
+                for key in &required.order {
+                    let expr = generate_one_binding(&self.memo, *project.exprs.get(key.column)?);
+                    let RelNode::ColumnRef(column_ref) = &*expr else {
+                        return None;
+                    };
+                    order.push(SortKey {
+                        column: column_ref.column,
+                        descending: key.descending,
+                    });
+                }
+                Some(vec![PhysicalProps::sorted(order)])
+            }
+            MemoRelNode::Sort(sort) => PhysicalProps::sorted(sort.keys.clone())
+                .satisfies(required)
+                .then(|| vec![any()]),
+            // a hash aggregation doesn't keep any order, and a limit has to keep the rows its
+            // child delivers without a requirement
+            MemoRelNode::Aggregate(_) | MemoRelNode::Limit(_) => {
+                required.is_any().then(|| vec![any()])
+            }
+            // all keep the order of the left (outer / probe) side
+            MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join) => {
+                let left_columns = self.logical_props(join.left).columns;
+                required
+                    .order
+                    .iter()
+                    .all(|key| key.column < left_columns)
+                    .then(|| vec![required.clone(), any()])
+            }
+            MemoRelNode::SortMergeJoin(join) => {
+                let left_columns = self.logical_props(join.left).columns;
+                let cond = generate_one_binding(&self.memo, join.cond);
+                let keys = equi_join_keys(&cond, left_columns);
+                let left = PhysicalProps::sorted(ascending(keys.iter().map(|(left, _)| *left)));
+                let right = PhysicalProps::sorted(ascending(keys.iter().map(|(_, right)| *right)));
+                left.satisfies(required).then(|| vec![left, right])
+            }
+            _ => None,
+        }
+    }
+
+    /// Deliver the required properties by sorting the best plan of the group that has no
+    /// requirements. Returns `None` if that costs more than `bound`.
+    pub fn enforce(
+        &mut self,
+        group: GroupId,
+        required: &PhysicalProps,
+        bound: f64,
+    ) -> Option<Winner> {
+        if required.is_any() {
+            return None;
+        }
+        let any = PhysicalProps::default();
+        let expr = MemoRelNode::Sort(MemoSort {
+            child: group,
+            keys: required.order.clone(),
+        });
+        let rows = self.logical_props(group).cardinality;
+        let sort_cost = self.cost_model.operator_cost(&expr, rows, &[rows]);
+        let child = self.optimize_group_with_bound(group, &any, bound - sort_cost)?;
+        let cost = child.cost + sort_cost;
+        Some(Winner {
+            expr,
+            cost,
+            child_props: vec![any],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_sort_order() {
+        let stats = stats(2, [10000.0, 100.0]);
+        let rel = join(
+            scan(TableId(0)),
+            scan(TableId(1)),
+            eq_pred(column_ref_pred(0), column_ref_pred(2)),
+        );
+        let mut optimizer = Optimizer::new(&stats);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(rel));
+        optimizer.implement();
+
+        let asc = |column| SortKey {
+            column,
+            descending: false,
+        };
+        let sorted_scan = |table| {
+            Arc::new(sort(
+                RelNode::TableScan(Scan {
+                    table: TableId(table),
+                }),
+                vec![asc(0)],
+            ))
+        };
+
+        // ordered by the join key: the inputs are sorted and merged, instead of sorting the
+        // much larger output of the join
+        let required = PhysicalProps::sorted(vec![asc(0)]);
+        let best = optimizer.best_plan(group, &required).unwrap();
+        let expected = RelNode::SortMergeJoin(Join {
+            join_type: JoinType::Inner,
+            left: sorted_scan(0),
+            right: sorted_scan(1),
+            cond: Arc::new(eq_pred(column_ref_pred(0), column_ref_pred(2))),
+        });
+        assert_eq!(best.as_ref(), &expected);
+
+        // ordered by a column of the right side: only an enforcer on top can deliver it
+        let required = PhysicalProps::sorted(vec![asc(3)]);
+        let best = optimizer.best_plan(group, &required).unwrap();
+        let RelNode::Sort(sort) = &*best else {
+            panic!("expected a sort, got {best:?}");
+        };
+        assert!(matches!(&*sort.child, RelNode::HashJoin(_)));
+
+        // winners are cached per required properties
+        let unordered = optimizer
+            .optimize_group(group, &PhysicalProps::default())
+            .unwrap();
+        let ordered = optimizer.optimize_group(group, &required).unwrap();
+        assert!(matches!(unordered.expr, MemoRelNode::HashJoin(_)));
+        assert!(matches!(ordered.expr, MemoRelNode::Sort(_)));
+        assert!(unordered.cost < ordered.cost);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::*;
+
+// `join_commute` and `join_assoc` generate every bushy join tree, so the memo grows exponentially
+// with the number of joins. The optimizer works within a budget: once it runs out, exploration
+// stops, and the search returns the best plan among the alternatives found so far.
+
+#[derive(Debug, Clone)]
+pub struct OptimizerConfig {
+    /// Wall-clock time for exploration and search. `None` means no limit.
+    pub time_limit: Option<Duration>,
+    /// Stop exploring when the memo has this many groups.
+    pub max_groups: usize,
+    /// Stop exploring when the memo has this many expressions.
+    pub max_exprs: usize,
+    /// Stop exploring after this many rule applications.
+    pub max_rule_applications: usize,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        Self {
+            time_limit: None,
+            max_groups: 100_000,
+            max_exprs: 1_000_000,
+            max_rule_applications: 1_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizeResult {
+    pub plan: Arc<RelNode>,
+    /// Whether the budget ran out before the search space was fully explored.
+    pub truncated: bool,
+}
+
+/// Implement a logical plan without exploring alternatives: keep the join order, and use a hash
+/// join wherever there is an equi-join condition.
+pub fn heuristic_plan(rel: &Arc<RelNode>, stats: &dyn TableStats) -> Arc<RelNode> {
+    let children = rel
+        .children()
+        .iter()
+        .map(|child| heuristic_plan(child, stats))
+        .collect();
+    let rel = rel.clone_with_children(children);
+    let plan = match rel {
+        RelNode::Scan(scan) => RelNode::TableScan(scan),
+        RelNode::Filter(filter) => RelNode::PhysicalFilter(filter),
+        RelNode::Join(join) => {
+            let left_columns = column_count(&join.left, stats);
+            if equi_join_keys(&join.cond, left_columns).is_empty() {
+                RelNode::NestedLoopJoin(join)
+            } else {
+                RelNode::HashJoin(join)
+            }
+        }
+        rel => rel,
+    };
+    Arc::new(plan)
+}
+
+impl Optimizer<'_> {
+    pub fn out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn memo_exhausted(&self) -> bool {
+        self.memo.num_groups() >= self.config.max_groups
+            || self.memo.num_exprs() >= self.config.max_exprs
+    }
+
+    pub fn rules_exhausted(&self) -> bool {
+        self.counters.rule_applications >= self.config.max_rule_applications
+    }
+
+    /// Explore, implement and search within the budget of `self.config`. Falls back to the
+    /// heuristic plan if the search didn't find any plan.
+    pub fn optimize_with_budget(&mut self, rel: Arc<RelNode>) -> OptimizeResult {
+        self.deadline = self.config.time_limit.map(|limit| Instant::now() + limit);
+        self.truncated = false;
+        let group = memorize_rel(&mut self.memo, rel.clone());
+        self.explore();
+        self.implement();
+        let plan = self
+            .best_plan(group, &PhysicalProps::default())
+            .unwrap_or_else(|| {
+                self.truncated = true;
+                heuristic_plan(&rel, self.stats)
+            });
+        OptimizeResult {
+            plan,
+            truncated: self.truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::test_fixtures;
+
+    fn chain(n: usize) -> (Arc<RelNode>, HashMap<TableId, TableInfo>) {
+        let stats = test_fixtures::stats(1, (0..n).map(|idx| (10 + idx * 37 % 100) as f64));
+        let mut rel = scan(TableId(0));
+        for idx in 1..n {
+            let cond = eq_pred(column_ref_pred(idx - 1), column_ref_pred(idx));
+            rel = join(rel, scan(TableId(idx)), cond);
+        }
+        (Arc::new(rel), stats)
+    }
+
+    #[test]
+    fn test_budget() {
+        // a small query is optimized completely
+        let (rel, stats) = chain(4);
+        let mut optimizer = Optimizer::new(&stats);
+        let result = optimizer.optimize_with_budget(rel);
+        assert!(!result.truncated);
+
+        // a 12-way join doesn't fit into a small memo
+        let (rel, stats) = chain(12);
+        let mut db = Database::new();
+        for idx in 0..12 {
+            db.insert(
+                &TableId(idx),
+                (0..3).map(|value| vec![Some(value)]).collect(),
+            );
+        }
+        let mut optimizer = Optimizer::new(&stats);
+        optimizer.config.max_exprs = 500;
+        let result = optimizer.optimize_with_budget(rel.clone());
+        assert!(result.truncated);
+        assert!(check_equivalence(&rel, [result.plan.clone()], &db).is_empty());
+        // exploration stops shortly after the limit, and implementation adds at most three
+        // physical expressions per logical one
+        assert!(optimizer.memo.num_exprs() < 4 * 600);
+        assert_eq!(
+            column_count(&result.plan, &stats),
+            column_count(&rel, &stats)
+        );
+
+        // without any time, the first plan found is returned
+        let mut optimizer = Optimizer::new(&stats);
+        optimizer.config.time_limit = Some(Duration::ZERO);
+        let result = optimizer.optimize_with_budget(rel.clone());
+        assert!(result.truncated);
+        assert_eq!(
+            column_count(&result.plan, &stats),
+            column_count(&rel, &stats)
+        );
+        assert!(check_equivalence(&rel, [result.plan], &db).is_empty());
+
+        let mut optimizer = Optimizer::new(&stats);
+        optimizer.config.max_rule_applications = 10;
+        assert!(optimizer.optimize_with_budget(rel.clone()).truncated);
+        assert_eq!(optimizer.counters.rule_applications, 10);
+
+        let plan = heuristic_plan(&rel, &stats);
+        assert!(matches!(&*plan, RelNode::HashJoin(_)));
+        assert!(check_equivalence(&rel, [plan], &db).is_empty());
+
+        // exploring swaps and reassociates joins with conditions on both sides, the plan must
+        // still refer to the right columns
+        let rel = Arc::new(
+            parse_sexp("(join (join (scan 1) (scan 2) (= #0 #2)) (scan 0) (= #3 #4))").unwrap(),
+        );
+        let stats = test_fixtures::stats(2, [10.0, 100000.0, 100.0]);
+        let mut db = Database::new();
+        for idx in 0..3 {
+            let rows = (0..4)
+                .map(|row| vec![Some(row), Some((row + idx as i64) % 3)])
+                .collect();
+            db.insert(&TableId(idx), rows);
+        }
+        assert!(!execute(&rel, &db).is_empty());
+        let result = Optimizer::new(&stats).optimize_with_budget(rel.clone());
+        assert!(!result.truncated);
+        assert!(check_equivalence(&rel, [result.plan], &db).is_empty());
+    }
+}
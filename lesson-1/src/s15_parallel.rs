@@ -0,0 +1,347 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, RwLock,
+    },
+    thread,
+};
+
+use super::*;
+
+// Making the bindings of an expression only reads the memo, so the expressions of a round can be
+// explored by many threads at once. The bindings are added in a fixed order, so the memo and the
+// chosen plan don't depend on the number of threads. For rules that add to the memo while others
+// read it, the concurrent memo shards the expression table and the group table, so that threads
+// adding expressions to different groups rarely wait for each other. Merging two groups touches
+// many shards, and takes the memo exclusively.
+
+/// The operations rules need from a memo.
+pub trait MemoStore {
+    fn add_expr(&mut self, expr: MemoRelNode) -> GroupId;
+    fn add_expr_to_group(&mut self, group: GroupId, expr: MemoRelNode) -> GroupId;
+    fn merge_group(&mut self, group1: GroupId, group2: GroupId) -> GroupId;
+    fn get_all_exprs_in_group(&self, group: GroupId) -> Vec<MemoRelNode>;
+    fn get_group(&self, expr: MemoRelNode) -> Option<GroupId>;
+}
+
+impl MemoStore for Memo {
+    fn add_expr(&mut self, expr: MemoRelNode) -> GroupId {
+        Memo::add_expr(self, expr)
+    }
+
+    fn add_expr_to_group(&mut self, group: GroupId, expr: MemoRelNode) -> GroupId {
+        Memo::add_expr_to_group(self, group, expr)
+    }
+
+    fn merge_group(&mut self, group1: GroupId, group2: GroupId) -> GroupId {
+        Memo::merge_group(self, group1, group2)
+    }
+
+    fn get_all_exprs_in_group(&self, group: GroupId) -> Vec<MemoRelNode> {
+        Memo::get_all_exprs_in_group(self, group)
+    }
+
+    fn get_group(&self, expr: MemoRelNode) -> Option<GroupId> {
+        Memo::get_group(self, expr)
+    }
+}
+
+const SHARDS: usize = 16;
+
+pub struct ConcurrentMemo {
+    /// Sharded by group id.
+    groups: Vec<RwLock<HashMap<GroupId, Vec<MemoRelNode>>>>,
+    /// Sharded by the hash of the expression.
+    expr_to_group: Vec<Mutex<HashMap<MemoRelNode, GroupId>>>,
+    /// Adding expressions holds the read lock, merging groups holds the write lock.
+    merged_groups: RwLock<HashMap<GroupId, GroupId>>,
+    next_group: AtomicUsize,
+}
+
+fn reduce_group(merged_groups: &HashMap<GroupId, GroupId>, mut group: GroupId) -> GroupId {
+    while let Some(&merged_into) = merged_groups.get(&group) {
+        group = merged_into;
+    }
+    group
+}
+
+fn reduce_expr(merged_groups: &HashMap<GroupId, GroupId>, expr: MemoRelNode) -> MemoRelNode {
+    let children = expr
+        .children()
+        .into_iter()
+        .map(|child| reduce_group(merged_groups, child))
+        .collect();
+    canonicalize_memo_expr(expr.clone_with_children(children))
+}
+
+impl ConcurrentMemo {
+    pub fn from_memo(memo: Memo) -> Self {
+        let (groups, merged_groups) = memo.into_parts();
+        let res = Self {
+            groups: (0..SHARDS).map(|_| RwLock::default()).collect(),
+            expr_to_group: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            next_group: AtomicUsize::new(groups.len()),
+            merged_groups: RwLock::new(merged_groups),
+        };
+        for (idx, exprs) in groups.into_iter().enumerate() {
+            let group = GroupId(idx);
+            for expr in &exprs {
+                res.expr_shard(expr)
+                    .lock()
+                    .unwrap()
+                    .insert(expr.clone(), group);
+            }
+            res.group_shard(group).write().unwrap().insert(group, exprs);
+        }
+        res
+    }
+
+    pub fn into_memo(self) -> Memo {
+        let mut groups = vec![vec![]; self.next_group.into_inner()];
+        for shard in self.groups {
+            for (group, exprs) in shard.into_inner().unwrap() {
+                groups[group.0] = exprs;
+            }
+        }
+        Memo::from_parts(groups, self.merged_groups.into_inner().unwrap())
+    }
+
+    fn group_shard(&self, group: GroupId) -> &RwLock<HashMap<GroupId, Vec<MemoRelNode>>> {
+        &self.groups[group.0 % SHARDS]
+    }
+
+    fn expr_shard(&self, expr: &MemoRelNode) -> &Mutex<HashMap<MemoRelNode, GroupId>> {
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        &self.expr_to_group[hasher.finish() as usize % SHARDS]
+    }
+
+    pub fn reduce_group(&self, group: GroupId) -> GroupId {
+        reduce_group(&self.merged_groups.read().unwrap(), group)
+    }
+
+    pub fn num_exprs(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|shard| shard.read().unwrap().values().map(Vec::len).sum::<usize>())
+            .sum()
+    }
+
+    /// All groups that have not been merged into another group, in order.
+    pub fn group_ids(&self) -> Vec<GroupId> {
+        let merged_groups = self.merged_groups.read().unwrap();
+        (0..self.next_group.load(Ordering::SeqCst))
+            .map(GroupId)
+            .filter(|group| !merged_groups.contains_key(group))
+            .collect()
+    }
+
+    /// Add the expression to `group`, or to a new group if `None`. Returns the group holding the
+    /// expression and the group it was already in, if it was somewhere else.
+    fn insert(&self, group: Option<GroupId>, expr: MemoRelNode) -> (GroupId, Option<GroupId>) {
+        let merged_groups = self.merged_groups.read().unwrap();
+        let expr = reduce_expr(&merged_groups, expr);
+        let mut expr_to_group = self.expr_shard(&expr).lock().unwrap();
+        if let Some(existing) = expr_to_group.get(&expr) {
+            let existing = reduce_group(&merged_groups, *existing);
+            let group = group.map(|group| reduce_group(&merged_groups, group));
+            return match group {
+                Some(group) if group != existing => (group, Some(existing)),
+                _ => (existing, None),
+            };
+        }
+        let group = match group {
+            Some(group) => reduce_group(&merged_groups, group),
+            None => GroupId(self.next_group.fetch_add(1, Ordering::SeqCst)),
+        };
+        expr_to_group.insert(expr.clone(), group);
+        let mut groups = self.group_shard(group).write().unwrap();
+        groups.entry(group).or_default().push(expr);
+        (group, None)
+    }
+}
+
+impl MemoStore for &ConcurrentMemo {
+    fn add_expr(&mut self, expr: MemoRelNode) -> GroupId {
+        self.insert(None, expr).0
+    }
+
+    fn add_expr_to_group(&mut self, group: GroupId, expr: MemoRelNode) -> GroupId {
+        match self.insert(Some(group), expr) {
+            (group, Some(existing)) => self.merge_group(group, existing),
+            (group, None) => group,
+        }
+    }
+
+    /// Like `Memo::merge_group`, including the congruence closure.
+    fn merge_group(&mut self, group1: GroupId, group2: GroupId) -> GroupId {
+        let mut merged_groups = self.merged_groups.write().unwrap();
+        let mut pending = vec![(group1, group2)];
+        while let Some((group1, group2)) = pending.pop() {
+            let group1 = reduce_group(&merged_groups, group1);
+            let group2 = reduce_group(&merged_groups, group2);
+            if group1 == group2 {
+                continue;
+            }
+            let exprs = self
+                .group_shard(group2)
+                .write()
+                .unwrap()
+                .remove(&group2)
+                .unwrap_or_default();
+            let mut groups = self.group_shard(group1).write().unwrap();
+            groups.entry(group1).or_default().extend(exprs);
+            drop(groups);
+            merged_groups.insert(group2, group1);
+            let mut touched = vec![group1];
+            let mut parents = vec![];
+            for shard in &self.expr_to_group {
+                let mut shard = shard.lock().unwrap();
+                let keys = shard
+                    .keys()
+                    .filter(|expr| expr.children().contains(&group2))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for key in keys {
+                    let group = shard.remove(&key).unwrap();
+                    parents.push((key, group));
+                }
+            }
+            parents.sort();
+            for (parent, group) in parents {
+                let parent = reduce_expr(&merged_groups, parent);
+                let mut shard = self.expr_shard(&parent).lock().unwrap();
+                match shard.get(&parent) {
+                    Some(&existing) => pending.push((existing, group)),
+                    None => {
+                        shard.insert(parent, group);
+                    }
+                }
+                touched.push(group);
+            }
+            for group in touched {
+                let group = reduce_group(&merged_groups, group);
+                let mut groups = self.group_shard(group).write().unwrap();
+                let group = groups.entry(group).or_default();
+                let mut exprs = vec![];
+                for expr in std::mem::take(group) {
+                    let expr = reduce_expr(&merged_groups, expr);
+                    if !exprs.contains(&expr) {
+                        exprs.push(expr);
+                    }
+                }
+                *group = exprs;
+            }
+        }
+        reduce_group(&merged_groups, group1)
+    }
+
+    fn get_all_exprs_in_group(&self, group: GroupId) -> Vec<MemoRelNode> {
+        let merged_groups = self.merged_groups.read().unwrap();
+        let group = reduce_group(&merged_groups, group);
+        let groups = self.group_shard(group).read().unwrap();
+        groups.get(&group).cloned().unwrap_or_default()
+    }
+
+    fn get_group(&self, expr: MemoRelNode) -> Option<GroupId> {
+        let merged_groups = self.merged_groups.read().unwrap();
+        let expr = reduce_expr(&merged_groups, expr);
+        let group = *self.expr_shard(&expr).lock().unwrap().get(&expr)?;
+        Some(reduce_group(&merged_groups, group))
+    }
+}
+
+impl Optimizer<'_> {
+    /// Like `explore`, with `threads` workers making the bindings of each round. The bindings are
+    /// added in the same order as by `explore`, so the memo and the chosen plan are the same.
+    pub fn explore_parallel(&mut self, threads: usize) {
+        self.explore_in_rounds(|optimizer, exprs| {
+            let chunk = exprs.len().div_ceil(threads).max(1);
+            thread::scope(|scope| {
+                let workers = exprs
+                    .chunks(chunk)
+                    .map(|exprs| {
+                        scope.spawn(move || {
+                            exprs
+                                .iter()
+                                .map(|expr| optimizer.explore_expr(expr))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap())
+                    .collect()
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_parallel_exploration() {
+        let n = 4;
+        let stats = stats(2, (0..n).map(|idx| (10 + idx * 37 % 100) as f64));
+        let mut db = Database::new();
+        let mut rel = scan(TableId(0));
+        for idx in 0..n {
+            let rows = (0..5)
+                .map(|row| vec![Some(row), Some((row + idx as i64) % 3)])
+                .collect();
+            db.insert(&TableId(idx), rows);
+            if idx > 0 {
+                let cond = eq_pred(column_ref_pred(idx * 2 - 1), column_ref_pred(idx * 2));
+                rel = join(rel, scan(TableId(idx)), cond);
+            }
+        }
+        let rel = Arc::new(rel);
+        assert!(!execute(&rel, &db).is_empty());
+
+        let optimize = |threads: Option<usize>| {
+            let mut optimizer = Optimizer::new(&stats);
+            let group = memorize_rel(&mut optimizer.memo, rel.clone());
+            match threads {
+                Some(threads) => optimizer.explore_parallel(threads),
+                None => optimizer.explore(),
+            }
+            optimizer.implement();
+            let required = PhysicalProps::default();
+            let cost = optimizer.optimize_group(group, &required).unwrap().cost;
+            let plan = optimizer.best_plan(group, &required).unwrap();
+            assert!(check_equivalence(&rel, [plan.clone()], &db).is_empty());
+            (plan, cost, optimizer.memo.num_groups())
+        };
+        let sequential = optimize(None);
+        for threads in [1, 2, 4, 8] {
+            assert_eq!(optimize(Some(threads)), sequential);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_merge_group() {
+        let mut memo = Memo::new();
+        let pred = || eq_pred(column_ref_pred(1), const_pred(3));
+        let filter0 = memorize_rel(&mut memo, Arc::new(filter(scan(TableId(0)), pred())));
+        let filter1 = memorize_rel(&mut memo, Arc::new(filter(scan(TableId(1)), pred())));
+        let scan0 = memorize_rel(&mut memo, Arc::new(scan(TableId(0))));
+        let scan1 = memorize_rel(&mut memo, Arc::new(scan(TableId(1))));
+        let memo = ConcurrentMemo::from_memo(memo);
+
+        // once the scans are equivalent, so are the filters over them
+        (&memo).merge_group(scan0, scan1);
+        let filter = memo.reduce_group(filter0);
+        assert_eq!(memo.reduce_group(filter1), filter);
+        let filters = (&memo).get_all_exprs_in_group(filter);
+        assert_eq!(filters.len(), 1);
+        assert_eq!((&memo).get_group(filters[0].clone()), Some(filter));
+    }
+}
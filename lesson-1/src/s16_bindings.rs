@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use super::*;
+
+// `generate_one_binding` picks the first expression of every group. To test rules, we want all the
+// plans a group represents. There can be exponentially many, so they are produced lazily, and
+// `take(n)` bounds them. Merging groups can make a group its own descendant; a binding never
+// expands a group inside itself, so that every group has finitely many bindings. The expressions
+// of a group are distinct, and equal plans are always in the same group, so no binding is produced
+// twice and the iterator doesn't need to remember the ones it returned.
+
+/// Build a node from a memo expression, with `children` in the order of `MemoRelNode::children`.
+pub fn rel_from_memo_expr(expr: &MemoRelNode, children: Vec<Arc<RelNode>>) -> RelNode {
+    let mut children = children.into_iter();
+    let mut next = || children.next().unwrap();
+    let join = |join: &MemoJoin, next: &mut dyn FnMut() -> Arc<RelNode>| Join {
+        join_type: join.join_type,
+        left: next(),
+        right: next(),
+        cond: next(),
+    };
+    match expr {
+        MemoRelNode::Scan(scan) => RelNode::Scan(scan.clone()),
+        MemoRelNode::TableScan(scan) => RelNode::TableScan(scan.clone()),
+        MemoRelNode::Join(j) => RelNode::Join(join(j, &mut next)),
+        MemoRelNode::HashJoin(j) => RelNode::HashJoin(join(j, &mut next)),
+        MemoRelNode::NestedLoopJoin(j) => RelNode::NestedLoopJoin(join(j, &mut next)),
+        MemoRelNode::SortMergeJoin(j) => RelNode::SortMergeJoin(join(j, &mut next)),
+        MemoRelNode::IndexScan(scan) => RelNode::IndexScan(scan.clone()),
+        MemoRelNode::IndexLookup(lookup) => RelNode::IndexLookup(lookup.clone()),
+        MemoRelNode::IndexNestedLoopJoin(j) => RelNode::IndexNestedLoopJoin(join(j, &mut next)),
+        MemoRelNode::Filter(_) => RelNode::Filter(Filter {
+            child: next(),
+            predicate: next(),
+        }),
+        MemoRelNode::PhysicalFilter(_) => RelNode::PhysicalFilter(Filter {
+            child: next(),
+            predicate: next(),
+        }),
+        MemoRelNode::Project(project) => RelNode::Project(Project {
+            child: next(),
+            exprs: project.exprs.iter().map(|_| next()).collect(),
+        }),
+        MemoRelNode::Sort(sort) => RelNode::Sort(Sort {
+            child: next(),
+            keys: sort.keys.clone(),
+        }),
+        MemoRelNode::Aggregate(aggregate) => RelNode::Aggregate(Aggregate {
+            child: next(),
+            group_by: aggregate.group_by.clone(),
+            aggs: aggregate.aggs.clone(),
+        }),
+        MemoRelNode::Limit(limit) => RelNode::Limit(Limit {
+            child: next(),
+            limit: limit.limit,
+        }),
+        MemoRelNode::Eq(_) => eq_pred(next(), next()),
+        MemoRelNode::And(_) => and_pred(next(), next()),
+        MemoRelNode::Or(_) => or_pred(next(), next()),
+        MemoRelNode::Add(_) => add_pred(next(), next()),
+        MemoRelNode::ColumnRef(column_ref) => RelNode::ColumnRef(column_ref.clone()),
+        MemoRelNode::Const(const_pred) => RelNode::Const(const_pred.clone()),
+    }
+}
+
+/// Iterates over the distinct bindings of a group.
+pub struct Bindings<'a> {
+    memo: &'a Memo,
+    /// The group and its ancestors, which can't be expanded again.
+    path: Vec<GroupId>,
+    exprs: Vec<MemoRelNode>,
+    current: Option<ExprBindings<'a>>,
+}
+
+/// Iterates over the bindings of one expression, as an odometer over the bindings of its
+/// children: the last child changes the fastest.
+struct ExprBindings<'a> {
+    expr: MemoRelNode,
+    iters: Vec<Bindings<'a>>,
+    children: Vec<Arc<RelNode>>,
+    started: bool,
+}
+
+/// Iterate over all bindings of the group. Use `take(n)` to get at most `n` of them.
+pub fn bindings(memo: &Memo, group: GroupId) -> Bindings<'_> {
+    Bindings::new(memo, group, vec![])
+}
+
+impl<'a> Bindings<'a> {
+    fn new(memo: &'a Memo, group: GroupId, mut path: Vec<GroupId>) -> Self {
+        let group = memo.reduce_group(group);
+        let exprs = if path.contains(&group) {
+            vec![]
+        } else {
+            memo.get_distinct_exprs_in_group(group)
+        };
+        path.push(group);
+        Self {
+            memo,
+            path,
+            exprs,
+            current: None,
+        }
+    }
+}
+
+impl Iterator for Bindings<'_> {
+    type Item = Arc<RelNode>;
+
+    fn next(&mut self) -> Option<Arc<RelNode>> {
+        loop {
+            if let Some(current) = &mut self.current {
+                if let Some(binding) = current.next(self.memo, &self.path) {
+                    return Some(binding);
+                }
+            }
+            if self.exprs.is_empty() {
+                return None;
+            }
+            let expr = self.exprs.remove(0);
+            self.current = Some(ExprBindings {
+                expr,
+                iters: vec![],
+                children: vec![],
+                started: false,
+            });
+        }
+    }
+}
+
+impl<'a> ExprBindings<'a> {
+    fn next(&mut self, memo: &'a Memo, path: &[GroupId]) -> Option<Arc<RelNode>> {
+        let groups = self.expr.children();
+        if !self.started {
+            self.started = true;
+            for group in &groups {
+                let mut iter = Bindings::new(memo, *group, path.to_vec());
+                self.children.push(iter.next()?);
+                self.iters.push(iter);
+            }
+        } else {
+            // advance the last child that has more bindings, and restart the ones after it
+            let mut idx = groups.len();
+            loop {
+                if idx == 0 {
+                    return None;
+                }
+                idx -= 1;
+                if let Some(binding) = self.iters[idx].next() {
+                    self.children[idx] = binding;
+                    break;
+                }
+            }
+            for (idx, group) in groups.iter().enumerate().skip(idx + 1) {
+                let mut iter = Bindings::new(memo, *group, path.to_vec());
+                self.children[idx] = iter.next()?;
+                self.iters[idx] = iter;
+            }
+        }
+        Some(Arc::new(rel_from_memo_expr(
+            &self.expr,
+            self.children.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_bindings() {
+        let stats = stats(2, [10.0; 3]);
+        let mut memo = Memo::new();
+        let rel = filter(
+            join(
+                scan(TableId(0)),
+                scan(TableId(1)),
+                eq_pred(column_ref_pred(0), column_ref_pred(2)),
+            ),
+            eq_pred(column_ref_pred(1), const_pred(3)),
+        );
+        let group = memorize_rel(&mut memo, Arc::new(rel.clone()));
+        assert_eq!(
+            bindings(&memo, group).collect::<Vec<_>>(),
+            vec![Arc::new(rel)]
+        );
+
+        // after exploring the join, both orders are bindings of the filter
+        for group in memo.group_ids() {
+            for expr in memo.get_all_exprs_in_group(group) {
+                apply_join_commute_rules_on_node(&mut memo, &stats, group, expr);
+            }
+        }
+        let all = bindings(&memo, group).collect::<Vec<_>>();
+        assert_eq!(all.len(), 2);
+        assert_ne!(all[0], all[1]);
+        assert_eq!(bindings(&memo, group).take(1).count(), 1);
+
+        // a fully explored join of three tables has no duplicate bindings
+        let rel =
+            parse_sexp("(join (join (scan 0) (scan 1) (= #1 #2)) (scan 2) (= #3 #4))").unwrap();
+        let mut optimizer = Optimizer::new(&stats);
+        let join_group = memorize_rel(&mut optimizer.memo, Arc::new(rel));
+        optimizer.explore();
+        let all = bindings(&optimizer.memo, join_group).collect::<Vec<_>>();
+        let distinct = all.iter().collect::<HashSet<_>>();
+        assert_eq!(distinct.len(), all.len());
+        assert!(all.len() > 2);
+
+        // a scan and a filter over it, merged into one group: the filter would need to contain
+        // itself, so only the scan is a binding
+        let mut memo = Memo::new();
+        let scan_group = memorize_rel(&mut memo, Arc::new(scan(TableId(0))));
+        let rel = filter(scan(TableId(0)), eq_pred(column_ref_pred(0), const_pred(1)));
+        let filter_group = memorize_rel(&mut memo, Arc::new(rel));
+        let group = memo.merge_group(scan_group, filter_group);
+        assert_eq!(
+            bindings(&memo, group).collect::<Vec<_>>(),
+            vec![Arc::new(scan(TableId(0)))]
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+
+use super::*;
+
+// The number of plans in a group is the sum over its expressions of the product of the numbers of
+// plans of their children. It easily exceeds 64 bits. Knowing the counts, we can also draw a plan
+// uniformly at random (Waas and Galindo-Legaria, 2000): pick an expression with probability
+// proportional to its count, then a random plan for each child.
+//
+// Plans are the bindings of `s16`: a group is never expanded inside itself. Without cycles, the
+// count of a group doesn't depend on where it appears and is computed once. Groups that can reach
+// a cycle are counted along each path.
+
+/// An arbitrary-precision unsigned integer, little-endian in base 2^32.
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
+pub struct BigUint(Vec<u32>);
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self(vec![])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn normalize(mut self) -> Self {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        self
+    }
+
+    pub fn add(&self, other: &BigUint) -> BigUint {
+        let mut res = vec![];
+        let mut carry = 0u64;
+        for idx in 0..self.0.len().max(other.0.len()) {
+            let sum = *self.0.get(idx).unwrap_or(&0) as u64
+                + *other.0.get(idx).unwrap_or(&0) as u64
+                + carry;
+            res.push(sum as u32);
+            carry = sum >> 32;
+        }
+        res.push(carry as u32);
+        Self(res).normalize()
+    }
+
+    /// `self - other`, which must not be negative.
+    pub fn sub(&self, other: &BigUint) -> BigUint {
+        assert!(*self >= *other);
+        let mut res = vec![];
+        let mut borrow = 0i64;
+        for idx in 0..self.0.len() {
+            let mut diff = self.0[idx] as i64 - *other.0.get(idx).unwrap_or(&0) as i64 - borrow;
+            borrow = (diff < 0) as i64;
+            diff += borrow << 32;
+            res.push(diff as u32);
+        }
+        Self(res).normalize()
+    }
+
+    pub fn mul(&self, other: &BigUint) -> BigUint {
+        let mut res = vec![0u32; self.0.len() + other.0.len()];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.0.iter().enumerate() {
+                let prod = *a as u64 * *b as u64 + res[i + j] as u64 + carry;
+                res[i + j] = prod as u32;
+                carry = prod >> 32;
+            }
+            res[i + other.0.len()] = carry as u32;
+        }
+        Self(res).normalize()
+    }
+
+    /// Divide by a small number, returning the quotient and the remainder.
+    fn div_rem_small(&self, divisor: u32) -> (BigUint, u32) {
+        let mut res = vec![0u32; self.0.len()];
+        let mut rem = 0u64;
+        for idx in (0..self.0.len()).rev() {
+            let cur = (rem << 32) | self.0[idx] as u64;
+            res[idx] = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        (Self(res).normalize(), rem as u32)
+    }
+
+    /// A uniformly random number in `0..self`, which must not be zero.
+    pub fn random_below(&self, rng: &mut Rng) -> BigUint {
+        assert!(!self.is_zero());
+        let top_bits = 32 - self.0.last().unwrap().leading_zeros();
+        loop {
+            let mut limbs = (0..self.0.len())
+                .map(|_| rng.next_u64() as u32)
+                .collect::<Vec<_>>();
+            *limbs.last_mut().unwrap() &= (u64::MAX >> (64 - top_bits)) as u32;
+            let candidate = Self(limbs).normalize();
+            if candidate < *self {
+                return candidate;
+            }
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        Self(vec![value as u32, (value >> 32) as u32]).normalize()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut chunks = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, rem) = rest.div_rem_small(1_000_000_000);
+            chunks.push(rem);
+            rest = quotient;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct PlanSpace<'a> {
+    memo: &'a Memo,
+    /// Groups from which a cycle can be reached.
+    cyclic: HashSet<GroupId>,
+    counts: HashMap<GroupId, BigUint>,
+}
+
+/// Tarjan's algorithm, marking the groups of cyclic components.
+struct CycleFinder<'a> {
+    memo: &'a Memo,
+    index: HashMap<GroupId, usize>,
+    low_link: HashMap<GroupId, usize>,
+    stack: Vec<GroupId>,
+    on_stack: HashSet<GroupId>,
+    in_cycle: HashSet<GroupId>,
+}
+
+fn child_groups(memo: &Memo, group: GroupId) -> Vec<GroupId> {
+    memo.get_distinct_exprs_in_group(group)
+        .iter()
+        .flat_map(|expr| expr.children())
+        .collect()
+}
+
+impl CycleFinder<'_> {
+    fn visit(&mut self, group: GroupId) {
+        let index = self.index.len();
+        self.index.insert(group, index);
+        self.low_link.insert(group, index);
+        self.stack.push(group);
+        self.on_stack.insert(group);
+        for child in child_groups(self.memo, group) {
+            if child == group {
+                self.in_cycle.insert(group);
+            }
+            if !self.index.contains_key(&child) {
+                self.visit(child);
+                let low = self.low_link[&group].min(self.low_link[&child]);
+                self.low_link.insert(group, low);
+            } else if self.on_stack.contains(&child) {
+                let low = self.low_link[&group].min(self.index[&child]);
+                self.low_link.insert(group, low);
+            }
+        }
+        if self.low_link[&group] == self.index[&group] {
+            let mut component = vec![];
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack.remove(&member);
+                component.push(member);
+                if member == group {
+                    break;
+                }
+            }
+            if component.len() > 1 {
+                self.in_cycle.extend(component);
+            }
+        }
+    }
+}
+
+impl<'a> PlanSpace<'a> {
+    pub fn new(memo: &'a Memo) -> Self {
+        let mut finder = CycleFinder {
+            memo,
+            index: HashMap::new(),
+            low_link: HashMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            in_cycle: HashSet::new(),
+        };
+        for group in memo.group_ids() {
+            if !finder.index.contains_key(&group) {
+                finder.visit(group);
+            }
+        }
+        // propagate to the groups that reach a cycle
+        let mut cyclic = finder.in_cycle;
+        loop {
+            let reaching = memo
+                .group_ids()
+                .into_iter()
+                .filter(|group| !cyclic.contains(group))
+                .filter(|group| {
+                    child_groups(memo, *group)
+                        .iter()
+                        .any(|child| cyclic.contains(child))
+                })
+                .collect::<Vec<_>>();
+            if reaching.is_empty() {
+                break;
+            }
+            cyclic.extend(reaching);
+        }
+        Self {
+            memo,
+            cyclic,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Whether a cycle can be reached from the group.
+    pub fn is_cyclic(&self, group: GroupId) -> bool {
+        self.cyclic.contains(&self.memo.reduce_group(group))
+    }
+
+    /// The number of distinct plans of the group.
+    pub fn count(&mut self, group: GroupId) -> BigUint {
+        self.count_on_path(group, &mut vec![])
+    }
+
+    fn count_on_path(&mut self, group: GroupId, path: &mut Vec<GroupId>) -> BigUint {
+        let group = self.memo.reduce_group(group);
+        if path.contains(&group) {
+            return BigUint::zero();
+        }
+        if let Some(count) = self.counts.get(&group) {
+            return count.clone();
+        }
+        path.push(group);
+        let mut count = BigUint::zero();
+        for expr in self.memo.get_distinct_exprs_in_group(group) {
+            count = count.add(&self.count_expr(&expr, path));
+        }
+        path.pop();
+        if !self.cyclic.contains(&group) {
+            self.counts.insert(group, count.clone());
+        }
+        count
+    }
+
+    fn count_expr(&mut self, expr: &MemoRelNode, path: &mut Vec<GroupId>) -> BigUint {
+        let mut count = BigUint::from(1);
+        for child in expr.children() {
+            count = count.mul(&self.count_on_path(child, path));
+        }
+        count
+    }
+
+    /// Draw a plan of the group uniformly at random. Returns `None` if the group has no plan.
+    pub fn sample(&mut self, group: GroupId, rng: &mut Rng) -> Option<Arc<RelNode>> {
+        self.sample_on_path(group, rng, &mut vec![])
+    }
+
+    fn sample_on_path(
+        &mut self,
+        group: GroupId,
+        rng: &mut Rng,
+        path: &mut Vec<GroupId>,
+    ) -> Option<Arc<RelNode>> {
+        let group = self.memo.reduce_group(group);
+        let total = self.count_on_path(group, path);
+        if total.is_zero() {
+            return None;
+        }
+        let mut rank = total.random_below(rng);
+        path.push(group);
+        for expr in self.memo.get_distinct_exprs_in_group(group) {
+            let count = self.count_expr(&expr, path);
+            if rank >= count {
+                rank = rank.sub(&count);
+                continue;
+            }
+            // the plans of the children are independent, so each is drawn on its own
+            let mut children = vec![];
+            for child in expr.children() {
+                children.push(self.sample_on_path(child, rng, path)?);
+            }
+            path.pop();
+            return Some(Arc::new(rel_from_memo_expr(&expr, children)));
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_plan_space() {
+        let two_64 = BigUint::from(u64::MAX).add(&BigUint::from(1));
+        assert_eq!(
+            two_64.mul(&two_64).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(two_64.sub(&BigUint::from(1)), BigUint::from(u64::MAX));
+
+        // explore a chain of three joins
+        let stats = stats(1, [10.0; 3]);
+        let mut memo = Memo::new();
+        let rel = join(
+            join(
+                scan(TableId(0)),
+                scan(TableId(1)),
+                eq_pred(column_ref_pred(0), column_ref_pred(1)),
+            ),
+            scan(TableId(2)),
+            eq_pred(column_ref_pred(1), column_ref_pred(2)),
+        );
+        let group = memorize_rel(&mut memo, Arc::new(rel));
+        for _ in 0..3 {
+            for group in memo.group_ids() {
+                for expr in memo.get_all_exprs_in_group(group) {
+                    apply_join_commute_rules_on_node(&mut memo, &stats, group, expr.clone());
+                    apply_join_assoc_rules_on_node(&mut memo, &stats, group, expr);
+                }
+            }
+        }
+        let all = bindings(&memo, group).collect::<HashSet<_>>();
+        let mut space = PlanSpace::new(&memo);
+        assert!(!space.is_cyclic(group));
+        assert_eq!(space.count(group), BigUint::from(all.len() as u64));
+
+        // every plan is drawn, about equally often
+        let mut rng = Rng::new(0);
+        let mut drawn: HashMap<Arc<RelNode>, usize> = HashMap::new();
+        let samples = 200 * all.len();
+        for _ in 0..samples {
+            let plan = space.sample(group, &mut rng).unwrap();
+            assert!(all.contains(&plan));
+            *drawn.entry(plan).or_default() += 1;
+        }
+        assert_eq!(drawn.len(), all.len());
+        assert!(drawn.values().all(|count| (100..300).contains(count)));
+
+        // a scan merged with a filter over it
+        let mut memo = Memo::new();
+        let scan_group = memorize_rel(&mut memo, Arc::new(scan(TableId(0))));
+        let rel = filter(scan(TableId(0)), eq_pred(column_ref_pred(0), const_pred(1)));
+        let filter_group = memorize_rel(&mut memo, Arc::new(rel));
+        let group = memo.merge_group(scan_group, filter_group);
+        let mut space = PlanSpace::new(&memo);
+        assert!(space.is_cyclic(group));
+        assert_eq!(space.count(group), BigUint::from(1));
+        assert_eq!(
+            space.sample(group, &mut rng),
+            Some(Arc::new(scan(TableId(0))))
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use super::*;
+
+// Render plans and the memo as Graphviz DOT, e.g. `dot -Tsvg plan.dot > plan.svg`. In the memo,
+// every group is a cluster, and the edges go from an expression to the groups of its children.
+
+/// Format a scalar expression, e.g. `#1 = #3 AND #2 = 3`.
+pub fn format_scalar(node: &RelNode) -> String {
+    match node {
+        RelNode::ColumnRef(column_ref) => format!("#{}", column_ref.column),
+        RelNode::Const(const_pred) => const_pred.value.to_string(),
+        RelNode::Eq(eq) => format!("{} = {}", format_scalar(&eq.left), format_scalar(&eq.right)),
+        RelNode::Add(add) => format!(
+            "{} + {}",
+            format_scalar(&add.left),
+            format_scalar(&add.right)
+        ),
+        RelNode::And(and) => format!(
+            "{} AND {}",
+            format_scalar(&and.left),
+            format_scalar(&and.right)
+        ),
+        RelNode::Or(or) => format!(
+            "({} OR {})",
+            format_scalar(&or.left),
+            format_scalar(&or.right)
+        ),
+        node => format!("{node:?}"),
+    }
+}
+
+pub fn agg_func_name(func: AggFunc) -> &'static str {
+    match func {
+        AggFunc::Count => "count",
+        AggFunc::Sum => "sum",
+        AggFunc::Min => "min",
+        AggFunc::Max => "max",
+    }
+}
+
+pub fn format_agg_call(call: &AggCall) -> String {
+    let func = agg_func_name(call.func);
+    match call.column {
+        Some(column) => format!("{func}(#{column})"),
+        None => format!("{func}(*)"),
+    }
+}
+
+/// The group-by columns in brackets, followed by the aggregates, e.g. `[#0] count(*), sum(#2)`.
+pub fn format_aggregate(group_by: &[usize], aggs: &[AggCall]) -> String {
+    let group_by = group_by
+        .iter()
+        .map(|column| format!("#{column}"))
+        .collect::<Vec<_>>();
+    let mut out = format!("[{}]", group_by.join(", "));
+    let aggs = aggs.iter().map(format_agg_call).collect::<Vec<_>>();
+    if !aggs.is_empty() {
+        out.push(' ');
+        out.push_str(&aggs.join(", "));
+    }
+    out
+}
+
+pub fn format_sort_keys(keys: &[SortKey]) -> String {
+    keys.iter()
+        .map(|key| {
+            let order = if key.descending { " DESC" } else { "" };
+            format!("#{}{order}", key.column)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An index access with the values it looks up, e.g. `t0.t0_pkey: 42` or `t1.t1_a_key: #0`.
+pub fn format_index_access(table: &TableId, index: &str, values: &[String]) -> String {
+    if values.is_empty() {
+        format!("t{}.{index}", table.0)
+    } else {
+        format!("t{}.{index}: {}", table.0, values.join(", "))
+    }
+}
+
+fn index_scan_args(scan: &IndexScan) -> String {
+    let prefix = scan.prefix.iter().map(|value| value.to_string());
+    format_index_access(&scan.table, &scan.index, &prefix.collect::<Vec<_>>())
+}
+
+fn index_lookup_args(lookup: &IndexLookup) -> String {
+    let keys = lookup.keys.iter().map(|key| format!("#{key}"));
+    format_index_access(&lookup.table, &lookup.index, &keys.collect::<Vec<_>>())
+}
+
+/// The name of the operator, e.g. `HashJoin` or `LeftSemi NestedLoopJoin`.
+pub fn operator_name(node: &RelNode) -> String {
+    let join_name = |name: &str, join: &Join| match join.join_type {
+        JoinType::Inner => name.to_string(),
+        join_type => format!("{join_type:?} {name}"),
+    };
+    match node {
+        RelNode::Scan(_) => "Scan".to_string(),
+        RelNode::TableScan(_) => "TableScan".to_string(),
+        RelNode::Join(join) => join_name("Join", join),
+        RelNode::HashJoin(join) => join_name("HashJoin", join),
+        RelNode::NestedLoopJoin(join) => join_name("NestedLoopJoin", join),
+        RelNode::SortMergeJoin(join) => join_name("SortMergeJoin", join),
+        RelNode::IndexScan(_) => "IndexScan".to_string(),
+        RelNode::IndexLookup(_) => "IndexLookup".to_string(),
+        RelNode::IndexNestedLoopJoin(join) => join_name("IndexNestedLoopJoin", join),
+        RelNode::Filter(_) => "Filter".to_string(),
+        RelNode::PhysicalFilter(_) => "PhysicalFilter".to_string(),
+        RelNode::Project(_) => "Project".to_string(),
+        RelNode::Sort(_) => "Sort".to_string(),
+        RelNode::Aggregate(_) => "Aggregate".to_string(),
+        RelNode::Limit(_) => "Limit".to_string(),
+        _ => "Scalar".to_string(),
+    }
+}
+
+/// The operator with its arguments on one line, e.g. `Join: #1 = #3` or `Scan t0`.
+pub fn format_operator(node: &RelNode) -> String {
+    let name = operator_name(node);
+    match node {
+        RelNode::Scan(scan) | RelNode::TableScan(scan) => format!("{name} t{}", scan.table.0),
+        RelNode::IndexScan(scan) => format!("{name} {}", index_scan_args(scan)),
+        RelNode::IndexLookup(lookup) => format!("{name} {}", index_lookup_args(lookup)),
+        RelNode::Join(join)
+        | RelNode::HashJoin(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::IndexNestedLoopJoin(join) => format!("{name}: {}", format_scalar(&join.cond)),
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => {
+            format!("{name}: {}", format_scalar(&filter.predicate))
+        }
+        RelNode::Project(project) => {
+            let exprs = project
+                .exprs
+                .iter()
+                .map(|expr| format_scalar(expr))
+                .collect::<Vec<_>>();
+            format!("{name}: {}", exprs.join(", "))
+        }
+        RelNode::Sort(sort) => format!("{name}: {}", format_sort_keys(&sort.keys)),
+        RelNode::Aggregate(aggregate) => format!(
+            "{name}: {}",
+            format_aggregate(&aggregate.group_by, &aggregate.aggs)
+        ),
+        RelNode::Limit(limit) => format!("{name}: {}", limit.limit),
+        scalar => format_scalar(scalar),
+    }
+}
+
+/// The children that produce rows, as opposed to scalar expressions.
+pub fn rel_inputs(node: &RelNode) -> Vec<Arc<RelNode>> {
+    match node {
+        RelNode::Join(join)
+        | RelNode::HashJoin(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::IndexNestedLoopJoin(join) => vec![join.left.clone(), join.right.clone()],
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => vec![filter.child.clone()],
+        RelNode::Project(project) => vec![project.child.clone()],
+        RelNode::Sort(sort) => vec![sort.child.clone()],
+        RelNode::Aggregate(aggregate) => vec![aggregate.child.clone()],
+        RelNode::Limit(limit) => vec![limit.child.clone()],
+        _ => vec![],
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render a plan as a DOT graph, with estimated cardinalities if `stats` is given.
+pub fn rel_to_dot(rel: &RelNode, stats: Option<&dyn TableStats>) -> String {
+    fn visit(
+        out: &mut String,
+        rel: &RelNode,
+        stats: Option<&dyn TableStats>,
+        next_id: &mut usize,
+    ) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let mut label = format_operator(rel);
+        if let Some(stats) = stats {
+            write!(label, "\\nrows={:.0}", estimate_cardinality(rel, stats)).unwrap();
+        }
+        writeln!(
+            out,
+            "  n{id} [label=\"{}\"];",
+            escape(&label).replace("\\\\n", "\\n")
+        )
+        .unwrap();
+        for input in rel_inputs(rel) {
+            let child = visit(out, &input, stats, next_id);
+            writeln!(out, "  n{id} -> n{child};").unwrap();
+        }
+        id
+    }
+    let mut out = "digraph plan {\n  node [shape=box];\n".to_string();
+    visit(&mut out, rel, stats, &mut 0);
+    out.push_str("}\n");
+    out
+}
+
+/// A memo expression without its children, e.g. `Join` or `Scan t0`.
+pub fn format_memo_expr(expr: &MemoRelNode) -> String {
+    let join_name = |name: &str, join: &MemoJoin| match join.join_type {
+        JoinType::Inner => name.to_string(),
+        join_type => format!("{join_type:?} {name}"),
+    };
+    match expr {
+        MemoRelNode::Scan(scan) => format!("Scan t{}", scan.table.0),
+        MemoRelNode::TableScan(scan) => format!("TableScan t{}", scan.table.0),
+        MemoRelNode::Join(join) => join_name("Join", join),
+        MemoRelNode::HashJoin(join) => join_name("HashJoin", join),
+        MemoRelNode::NestedLoopJoin(join) => join_name("NestedLoopJoin", join),
+        MemoRelNode::SortMergeJoin(join) => join_name("SortMergeJoin", join),
+        MemoRelNode::IndexScan(scan) => format!("IndexScan {}", index_scan_args(scan)),
+        MemoRelNode::IndexLookup(lookup) => format!("IndexLookup {}", index_lookup_args(lookup)),
+        MemoRelNode::IndexNestedLoopJoin(join) => join_name("IndexNestedLoopJoin", join),
+        MemoRelNode::Filter(_) => "Filter".to_string(),
+        MemoRelNode::PhysicalFilter(_) => "PhysicalFilter".to_string(),
+        MemoRelNode::Project(_) => "Project".to_string(),
+        MemoRelNode::Sort(sort) => format!("Sort: {}", format_sort_keys(&sort.keys)),
+        MemoRelNode::Aggregate(aggregate) => format!(
+            "Aggregate: {}",
+            format_aggregate(&aggregate.group_by, &aggregate.aggs)
+        ),
+        MemoRelNode::Limit(limit) => format!("Limit: {}", limit.limit),
+        MemoRelNode::Eq(_) => "=".to_string(),
+        MemoRelNode::And(_) => "AND".to_string(),
+        MemoRelNode::Or(_) => "OR".to_string(),
+        MemoRelNode::Add(_) => "+".to_string(),
+        MemoRelNode::ColumnRef(column_ref) => format!("#{}", column_ref.column),
+        MemoRelNode::Const(const_pred) => const_pred.value.to_string(),
+    }
+}
+
+/// Extra information to render with the memo.
+#[derive(Debug, Clone, Default)]
+pub struct MemoAnnotations {
+    /// A line added to the label of each group, e.g. its cardinality.
+    pub groups: HashMap<GroupId, String>,
+    /// Expressions to highlight in each group, and the cost of each.
+    pub chosen: HashMap<GroupId, Vec<(MemoRelNode, f64)>>,
+}
+
+/// Render the memo as a DOT graph.
+pub fn memo_to_dot(memo: &Memo, annotations: &MemoAnnotations) -> String {
+    let mut out = "digraph memo {\n  compound=true;\n  node [shape=box];\n".to_string();
+    let node_id = |group: GroupId, idx: usize| format!("g{}e{idx}", group.0);
+    let mut edges = vec![];
+    for group in memo.group_ids() {
+        let mut label = format!("Group {}", group.0);
+        if let Some(annotation) = annotations.groups.get(&group) {
+            write!(label, "\\n{}", escape(annotation)).unwrap();
+        }
+        writeln!(out, "  subgraph cluster_{} {{", group.0).unwrap();
+        writeln!(out, "    label=\"{label}\";").unwrap();
+        let mut exprs = memo.get_all_exprs_in_group(group);
+        let chosen = annotations.chosen.get(&group).cloned().unwrap_or_default();
+        // enforcers are not in the memo, but are part of the chosen plan
+        for (expr, _) in &chosen {
+            if !exprs.contains(expr) {
+                exprs.push(expr.clone());
+            }
+        }
+        for (idx, expr) in exprs.iter().enumerate() {
+            let mut label = escape(&format_memo_expr(expr));
+            let mut style = String::new();
+            if let Some((_, cost)) = chosen.iter().find(|(chosen, _)| chosen == expr) {
+                write!(label, "\\ncost={cost:.1}").unwrap();
+                style.push_str(", style=filled, fillcolor=lightblue");
+            }
+            writeln!(
+                out,
+                "    {} [label=\"{label}\"{style}];",
+                node_id(group, idx)
+            )
+            .unwrap();
+            for child in expr.children() {
+                edges.push((node_id(group, idx), memo.reduce_group(child)));
+            }
+        }
+        writeln!(out, "  }}").unwrap();
+    }
+    for (from, group) in edges {
+        writeln!(
+            out,
+            "  {from} -> {} [lhead=cluster_{}];",
+            node_id(group, 0),
+            group.0
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+impl Optimizer<'_> {
+    /// Render the memo annotated with the cardinality of each group, and the chosen plan for
+    /// `root` highlighted with the cost of each operator's subtree.
+    pub fn memo_to_dot(&mut self, root: GroupId, required: &PhysicalProps) -> String {
+        let mut annotations = MemoAnnotations::default();
+        for group in self.memo.group_ids() {
+            let exprs = self.memo.get_all_exprs_in_group(group);
+            if exprs
+                .iter()
+                .any(|expr| expr.is_logical() || expr.is_physical())
+            {
+                let rows = self.logical_props(group).cardinality;
+                annotations.groups.insert(group, format!("rows={rows:.0}"));
+            }
+        }
+        let mut todo = vec![(root, required.clone())];
+        while let Some((group, required)) = todo.pop() {
+            let Some(winner) = self.optimize_group(group, &required) else {
+                continue;
+            };
+            let group = self.memo.reduce_group(group);
+            let chosen = annotations.chosen.entry(group).or_default();
+            if chosen.iter().any(|(expr, _)| *expr == winner.expr) {
+                continue;
+            }
+            chosen.push((winner.expr.clone(), winner.cost));
+            todo.extend(
+                winner
+                    .expr
+                    .rel_children()
+                    .into_iter()
+                    .zip(winner.child_props),
+            );
+        }
+        memo_to_dot(&self.memo, &annotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_dot() {
+        let rel = plan();
+        let stats = stats(2, [1000.0, 10.0]);
+        let dot = rel_to_dot(&rel, Some(&stats));
+        assert_eq!(
+            dot,
+            "digraph plan {
+  node [shape=box];
+  n0 [label=\"Filter: #2 = 3\\nrows=100\"];
+  n1 [label=\"Join: #1 = #3\\nrows=1000\"];
+  n2 [label=\"Scan t0\\nrows=1000\"];
+  n1 -> n2;
+  n3 [label=\"Scan t1\\nrows=10\"];
+  n1 -> n3;
+  n0 -> n1;
+}
+"
+        );
+
+        let mut optimizer = Optimizer::new(&stats);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(rel));
+        optimizer.implement();
+        let required = PhysicalProps::sorted(vec![SortKey {
+            column: 3,
+            descending: false,
+        }]);
+        let dot = optimizer.memo_to_dot(group, &required);
+        assert_eq!(
+            dot.matches("subgraph cluster_").count(),
+            optimizer.memo.num_groups()
+        );
+        assert!(dot.contains("label=\"Group 0\\nrows=1000\";"));
+        // sort, filter, join and the two scans
+        assert_eq!(dot.matches("fillcolor=lightblue").count(), 5);
+        assert!(dot.contains("[label=\"Sort: #3\\ncost="));
+    }
+}
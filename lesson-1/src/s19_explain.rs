@@ -0,0 +1,452 @@
+use std::fmt::Write;
+
+use super::*;
+
+// `{:?}` of a plan nests every node in its parent's payload, which is unreadable past three
+// levels. EXPLAIN prints one operator per line with its children indented below it, e.g.
+//
+// Filter: #2 = 3
+//   Join: #1 = #3
+//     Scan t0
+//     Scan t1
+//
+// The output only depends on the plan, so that tests can compare it with a snapshot.
+
+#[derive(Debug, Clone, Default)]
+pub struct ExplainOptions {
+    /// The output columns of each operator.
+    pub schema: bool,
+    /// The estimated number of output rows of each operator.
+    pub cardinality: bool,
+    /// The estimated cost of each physical operator, including its inputs.
+    pub cost: bool,
+    pub cost_model: CostModel,
+}
+
+impl ExplainOptions {
+    pub fn all() -> Self {
+        Self {
+            schema: true,
+            cardinality: true,
+            cost: true,
+            cost_model: CostModel::default(),
+        }
+    }
+}
+
+fn format_schema(columns: usize) -> String {
+    let columns = (0..columns)
+        .map(|column| format!("#{column}"))
+        .collect::<Vec<_>>();
+    format!("[{}]", columns.join(", "))
+}
+
+/// The memo operator of a plan node, for the cost model. Only the operator matters, so the
+/// children point to an arbitrary group.
+pub(crate) fn memo_operator(rel: &RelNode) -> MemoRelNode {
+    let g = GroupId(0);
+    let join = |join: &Join| MemoJoin {
+        join_type: join.join_type,
+        left: g,
+        right: g,
+        cond: g,
+    };
+    let filter = MemoFilter {
+        child: g,
+        predicate: g,
+    };
+    match rel {
+        RelNode::Scan(scan) => MemoRelNode::Scan(scan.clone()),
+        RelNode::TableScan(scan) => MemoRelNode::TableScan(scan.clone()),
+        RelNode::Join(j) => MemoRelNode::Join(join(j)),
+        RelNode::HashJoin(j) => MemoRelNode::HashJoin(join(j)),
+        RelNode::NestedLoopJoin(j) => MemoRelNode::NestedLoopJoin(join(j)),
+        RelNode::SortMergeJoin(j) => MemoRelNode::SortMergeJoin(join(j)),
+        RelNode::IndexScan(scan) => MemoRelNode::IndexScan(scan.clone()),
+        RelNode::IndexLookup(lookup) => MemoRelNode::IndexLookup(lookup.clone()),
+        RelNode::IndexNestedLoopJoin(j) => MemoRelNode::IndexNestedLoopJoin(join(j)),
+        RelNode::Filter(_) => MemoRelNode::Filter(filter),
+        RelNode::PhysicalFilter(_) => MemoRelNode::PhysicalFilter(filter),
+        RelNode::Project(project) => MemoRelNode::Project(MemoProject {
+            child: g,
+            exprs: vec![g; project.exprs.len()],
+        }),
+        RelNode::Sort(sort) => MemoRelNode::Sort(MemoSort {
+            child: g,
+            keys: sort.keys.clone(),
+        }),
+        RelNode::Aggregate(aggregate) => MemoRelNode::Aggregate(MemoAggregate {
+            child: g,
+            group_by: aggregate.group_by.clone(),
+            aggs: aggregate.aggs.clone(),
+        }),
+        RelNode::Limit(limit) => MemoRelNode::Limit(MemoLimit {
+            child: g,
+            limit: limit.limit,
+        }),
+        RelNode::Eq(_) => MemoRelNode::Eq(MemoEqPred { left: g, right: g }),
+        RelNode::And(_) => MemoRelNode::And(MemoAndPred { left: g, right: g }),
+        RelNode::Or(_) => MemoRelNode::Or(MemoOrPred { left: g, right: g }),
+        RelNode::Add(_) => MemoRelNode::Add(MemoAddPred { left: g, right: g }),
+        RelNode::ColumnRef(column_ref) => MemoRelNode::ColumnRef(column_ref.clone()),
+        RelNode::Const(const_pred) => MemoRelNode::Const(const_pred.clone()),
+    }
+}
+
+/// The estimated cost of a physical plan. Logical operators cost nothing.
+pub fn plan_cost(rel: &RelNode, stats: &dyn TableStats, cost_model: &CostModel) -> f64 {
+    let inputs = rel_inputs(rel);
+    let input_rows = inputs
+        .iter()
+        .map(|input| estimate_cardinality(input, stats))
+        .collect::<Vec<_>>();
+    let output = estimate_cardinality(rel, stats);
+    let own = cost_model.operator_cost(&memo_operator(rel), output, &input_rows);
+    own + inputs
+        .iter()
+        .map(|input| plan_cost(input, stats, cost_model))
+        .sum::<f64>()
+}
+
+/// EXPLAIN a plan without annotations.
+pub fn explain(rel: &RelNode) -> String {
+    let mut out = String::new();
+    explain_rel(&mut out, rel, 0, &|_| String::new());
+    out
+}
+
+/// EXPLAIN a plan with the annotations selected in `options`.
+pub fn explain_with(rel: &RelNode, stats: &dyn TableStats, options: &ExplainOptions) -> String {
+    let annotate = |rel: &RelNode| {
+        let mut annotations = vec![];
+        if options.schema {
+            annotations.push(format!(
+                "schema={}",
+                format_schema(column_count(rel, stats))
+            ));
+        }
+        if options.cardinality {
+            annotations.push(format!("rows={:.0}", estimate_cardinality(rel, stats)));
+        }
+        if options.cost && memo_operator(rel).is_physical() {
+            let cost = plan_cost(rel, stats, &options.cost_model);
+            annotations.push(format!("cost={cost:.1}"));
+        }
+        if annotations.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", annotations.join(" "))
+        }
+    };
+    let mut out = String::new();
+    explain_rel(&mut out, rel, 0, &annotate);
+    out
+}
+
+fn explain_rel(
+    out: &mut String,
+    rel: &RelNode,
+    depth: usize,
+    annotate: &dyn Fn(&RelNode) -> String,
+) {
+    let indent = "  ".repeat(depth);
+    writeln!(out, "{indent}{}{}", format_operator(rel), annotate(rel)).unwrap();
+    for input in rel_inputs(rel) {
+        explain_rel(out, &input, depth + 1, annotate);
+    }
+}
+
+/// Format a scalar expression of a binding, with `G3` for a group placeholder.
+pub fn format_bind_scalar(node: &BindRelNode) -> String {
+    let binary = |left: &BindRelNode, op: &str, right: &BindRelNode| {
+        format!(
+            "{} {op} {}",
+            format_bind_scalar(left),
+            format_bind_scalar(right)
+        )
+    };
+    match node {
+        BindRelNode::ColumnRef(column_ref) => format!("#{}", column_ref.column),
+        BindRelNode::Const(const_pred) => const_pred.value.to_string(),
+        BindRelNode::Eq(eq) => binary(&eq.left, "=", &eq.right),
+        BindRelNode::Add(add) => binary(&add.left, "+", &add.right),
+        BindRelNode::And(and) => binary(&and.left, "AND", &and.right),
+        BindRelNode::Or(or) => format!("({})", binary(&or.left, "OR", &or.right)),
+        BindRelNode::Group(group) => format!("G{}", group.0),
+        node => format!("{node:?}"),
+    }
+}
+
+/// EXPLAIN a binding. Groups that were not expanded are printed as `G3`.
+pub fn explain_binding(node: &BindRelNode) -> String {
+    fn visit(out: &mut String, node: &BindRelNode, depth: usize) {
+        let join_name = |join: &BindJoin| match join.join_type {
+            JoinType::Inner => "Join".to_string(),
+            join_type => format!("{join_type:?} Join"),
+        };
+        let (line, inputs) = match node {
+            BindRelNode::Scan(scan) => (format!("Scan t{}", scan.table.0), vec![]),
+            BindRelNode::Join(join) => (
+                format!("{}: {}", join_name(join), format_bind_scalar(&join.cond)),
+                vec![join.left.clone(), join.right.clone()],
+            ),
+            BindRelNode::Filter(filter) => (
+                format!("Filter: {}", format_bind_scalar(&filter.predicate)),
+                vec![filter.child.clone()],
+            ),
+            BindRelNode::Project(project) => {
+                let exprs = project
+                    .exprs
+                    .iter()
+                    .map(|expr| format_bind_scalar(expr))
+                    .collect::<Vec<_>>();
+                (
+                    format!("Project: {}", exprs.join(", ")),
+                    vec![project.child.clone()],
+                )
+            }
+            BindRelNode::Sort(sort) => (
+                format!("Sort: {}", format_sort_keys(&sort.keys)),
+                vec![sort.child.clone()],
+            ),
+            scalar => (format_bind_scalar(scalar), vec![]),
+        };
+        writeln!(out, "{}{line}", "  ".repeat(depth)).unwrap();
+        for input in inputs {
+            visit(out, &input, depth + 1);
+        }
+    }
+    let mut out = String::new();
+    visit(&mut out, node, 0);
+    out
+}
+
+/// Format a scalar expression of the generic representation of s06.
+fn format_generic_scalar(node: &s06_new_repr::RelNode) -> String {
+    use s06_new_repr::{RelAttrType, RelNodeType};
+    match (&node.typ, &*node.data) {
+        (RelNodeType::ColumnRef, RelAttrType::ColumnRef(column)) => format!("#{column}"),
+        (RelNodeType::Const, RelAttrType::Const(value)) => value.to_string(),
+        (RelNodeType::Eq, _) => format!(
+            "{} = {}",
+            format_generic_scalar(&node.children[0]),
+            format_generic_scalar(&node.children[1])
+        ),
+        _ => "?".to_string(),
+    }
+}
+
+/// EXPLAIN a plan in the generic representation of s06. It has no statistics, so there are no
+/// annotations.
+pub fn explain_generic(node: &s06_new_repr::RelNode) -> String {
+    use s06_new_repr::{RelAttrType, RelNodeType};
+    fn visit(out: &mut String, node: &s06_new_repr::RelNode, depth: usize) {
+        let children = &node.children;
+        let (line, inputs) = match (&node.typ, &*node.data) {
+            (RelNodeType::Scan, RelAttrType::TableId(table)) => {
+                (format!("Scan t{}", table.0), &[][..])
+            }
+            (RelNodeType::Filter, _) => (
+                format!("Filter: {}", format_generic_scalar(&children[1])),
+                &children[..1],
+            ),
+            (RelNodeType::Join, _) => (
+                format!("Join: {}", format_generic_scalar(&children[2])),
+                &children[..2],
+            ),
+            _ => (format_generic_scalar(node), &[][..]),
+        };
+        writeln!(out, "{}{line}", "  ".repeat(depth)).unwrap();
+        for input in inputs {
+            visit(out, input, depth + 1);
+        }
+    }
+    let mut out = String::new();
+    visit(&mut out, node, 0);
+    out
+}
+
+/// A memo expression with its scalar children inlined and its inputs as groups, e.g.
+/// `HashJoin: #1 = #3 [G0, G1]`.
+pub fn format_memo_operator(memo: &Memo, expr: &MemoRelNode) -> String {
+    let name = format_memo_expr(expr);
+    let scalar = |group: GroupId| format_scalar(&generate_one_binding(memo, group));
+    let line = match expr {
+        MemoRelNode::Join(join)
+        | MemoRelNode::HashJoin(join)
+        | MemoRelNode::NestedLoopJoin(join)
+        | MemoRelNode::SortMergeJoin(join)
+        | MemoRelNode::IndexNestedLoopJoin(join) => format!("{name}: {}", scalar(join.cond)),
+        MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter) => {
+            format!("{name}: {}", scalar(filter.predicate))
+        }
+        MemoRelNode::Project(project) => {
+            let exprs = project
+                .exprs
+                .iter()
+                .map(|expr| scalar(*expr))
+                .collect::<Vec<_>>();
+            format!("{name}: {}", exprs.join(", "))
+        }
+        _ => name,
+    };
+    let inputs = expr
+        .rel_children()
+        .into_iter()
+        .map(|group| format!("G{}", memo.reduce_group(group).0))
+        .collect::<Vec<_>>();
+    if inputs.is_empty() {
+        line
+    } else {
+        format!("{line} [{}]", inputs.join(", "))
+    }
+}
+
+/// EXPLAIN the relational groups of the memo, one expression per line.
+pub fn explain_memo(memo: &Memo) -> String {
+    let mut out = String::new();
+    for group in memo.group_ids() {
+        explain_memo_group(&mut out, memo, group, String::new());
+    }
+    out
+}
+
+fn explain_memo_group(out: &mut String, memo: &Memo, group: GroupId, annotation: String) {
+    let exprs = memo.get_distinct_exprs_in_group(group);
+    if !exprs
+        .iter()
+        .any(|expr| expr.is_logical() || expr.is_physical())
+    {
+        return;
+    }
+    writeln!(out, "G{}{annotation}", group.0).unwrap();
+    for expr in exprs {
+        writeln!(out, "  {}", format_memo_operator(memo, &expr)).unwrap();
+    }
+}
+
+impl Optimizer<'_> {
+    /// EXPLAIN the relational groups of the memo, with the schema and cardinality of each group,
+    /// and the cost of its best plan without a required order.
+    pub fn explain_memo(&mut self, options: &ExplainOptions) -> String {
+        let mut out = String::new();
+        for group in self.memo.group_ids() {
+            let mut annotations = vec![];
+            let exprs = self.memo.get_all_exprs_in_group(group);
+            if exprs
+                .iter()
+                .any(|expr| expr.is_logical() || expr.is_physical())
+            {
+                let props = self.logical_props(group);
+                if options.schema {
+                    annotations.push(format!("schema={}", format_schema(props.columns)));
+                }
+                if options.cardinality {
+                    annotations.push(format!("rows={:.0}", props.cardinality));
+                }
+                if options.cost {
+                    if let Some(winner) = self.optimize_group(group, &PhysicalProps::default()) {
+                        annotations.push(format!("cost={:.1}", winner.cost));
+                    }
+                }
+            }
+            let annotation = if annotations.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", annotations.join(" "))
+            };
+            explain_memo_group(&mut out, &self.memo, group, annotation);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_explain() {
+        let rel = plan();
+        assert_eq!(
+            explain(&rel),
+            "Filter: #2 = 3
+  Join: #1 = #3
+    Scan t0
+    Scan t1
+"
+        );
+
+        let stats = stats(2, [1000.0, 10.0]);
+        let mut optimizer = Optimizer::new(&stats);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(rel.clone()));
+        optimizer.implement();
+        let required = PhysicalProps::default();
+        let physical = optimizer.best_plan(group, &required).unwrap();
+        let cost = optimizer.optimize_group(group, &required).unwrap().cost;
+        assert_eq!(plan_cost(&physical, &stats, &CostModel::default()), cost);
+        assert_eq!(
+            explain_with(&physical, &stats, &ExplainOptions::all()),
+            format!(
+                "PhysicalFilter: #2 = 3 (schema=[#0, #1, #2, #3] rows=100 cost={cost:.1})
+  HashJoin: #1 = #3 (schema=[#0, #1, #2, #3] rows=1000 cost=3530.0)
+    TableScan t0 (schema=[#0, #1] rows=1000 cost=1000.0)
+    TableScan t1 (schema=[#0, #1] rows=10 cost=10.0)
+"
+            )
+        );
+        assert_eq!(
+            explain_memo(&optimizer.memo),
+            "G0
+  Scan t0
+  TableScan t0
+G1
+  Scan t1
+  TableScan t1
+G5
+  Join: #1 = #3 [G0, G1]
+  NestedLoopJoin: #1 = #3 [G0, G1]
+  HashJoin: #1 = #3 [G0, G1]
+  SortMergeJoin: #1 = #3 [G0, G1]
+G9
+  Filter: #2 = 3 [G5]
+  PhysicalFilter: #2 = 3 [G5]
+"
+        );
+        let options = ExplainOptions {
+            cardinality: true,
+            ..Default::default()
+        };
+        assert!(optimizer
+            .explain_memo(&options)
+            .starts_with("G0 (rows=1000)\n"));
+
+        let binding = BindRelNode::Filter(BindFilter {
+            child: Arc::new(BindRelNode::Group(GroupId(5))),
+            predicate: Arc::new(BindRelNode::Eq(BindEqPred {
+                left: Arc::new(BindRelNode::ColumnRef(ColumnRefPred { column: 2 })),
+                right: Arc::new(BindRelNode::Const(ConstPred { value: 3 })),
+            })),
+        });
+        assert_eq!(explain_binding(&binding), "Filter: #2 = 3\n  G5\n");
+
+        // the generic representation prints the same as the plan it was converted from
+        let generic = to_generic_repr(&rel).unwrap();
+        assert_eq!(explain_generic(&generic), explain(&rel));
+        let generic = s06_new_repr::join(
+            s06_new_repr::scan(s06_new_repr::TableId(0)),
+            s06_new_repr::scan(s06_new_repr::TableId(1)),
+            s06_new_repr::eq_pred(
+                s06_new_repr::column_ref_pred(1),
+                s06_new_repr::column_ref_pred(3),
+            ),
+        );
+        assert_eq!(
+            explain_generic(&generic),
+            "Join: #1 = #3\n  Scan t0\n  Scan t1\n"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use std::{fmt, sync::Arc};
+
+use super::*;
+
+// Plans as text, so that rule tests, bug reports and fixtures don't have to nest builder calls:
+//
+// (filter (join (scan 0) (scan 1) (= #1 #3)) (= #2 3))
+//
+// Every node is `(operator children...)`, a column reference is `#1` and a constant is an
+// integer. Joins take an optional type, `(join :semi ...)`, and sort keys are `#1` or `(desc #1)`.
+// An aggregate lists its group-by columns, then its aggregates:
+// `(aggregate (scan 0) (#0) (count) (sum #1))`. Index accesses name the table and the index, then
+// the looked up values: `(index_scan 0 t0_pkey 42)` or `(index_lookup 1 t1_pkey #0)`.
+// Printing a parsed plan gives back the same text, modulo whitespace.
+
+/// An error in a text plan or query, at a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// An error at the byte `offset` of `text`.
+    pub fn at(text: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |idx| idx + 1) + 1;
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn join_sexp(name: &str, join: &Join) -> String {
+    let join_type = match join.join_type {
+        JoinType::Inner => "",
+        JoinType::LeftSemi => " :semi",
+        JoinType::LeftAnti => " :anti",
+    };
+    format!(
+        "({name}{join_type} {} {} {})",
+        to_sexp(&join.left),
+        to_sexp(&join.right),
+        to_sexp(&join.cond)
+    )
+}
+
+/// Print a plan as an S-expression.
+pub fn to_sexp(rel: &RelNode) -> String {
+    let binary = |op: &str, left: &RelNode, right: &RelNode| {
+        format!("({op} {} {})", to_sexp(left), to_sexp(right))
+    };
+    match rel {
+        RelNode::Scan(scan) => format!("(scan {})", scan.table.0),
+        RelNode::TableScan(scan) => format!("(table_scan {})", scan.table.0),
+        RelNode::Join(join) => join_sexp("join", join),
+        RelNode::HashJoin(join) => join_sexp("hash_join", join),
+        RelNode::NestedLoopJoin(join) => join_sexp("nested_loop_join", join),
+        RelNode::SortMergeJoin(join) => join_sexp("sort_merge_join", join),
+        RelNode::IndexScan(scan) => {
+            let mut out = format!("(index_scan {} {}", scan.table.0, scan.index);
+            for value in &scan.prefix {
+                out.push_str(&format!(" {value}"));
+            }
+            out.push(')');
+            out
+        }
+        RelNode::IndexLookup(lookup) => {
+            let mut out = format!("(index_lookup {} {}", lookup.table.0, lookup.index);
+            for key in &lookup.keys {
+                out.push_str(&format!(" #{key}"));
+            }
+            out.push(')');
+            out
+        }
+        RelNode::IndexNestedLoopJoin(join) => join_sexp("index_nested_loop_join", join),
+        RelNode::Filter(filter) => binary("filter", &filter.child, &filter.predicate),
+        RelNode::PhysicalFilter(filter) => {
+            binary("physical_filter", &filter.child, &filter.predicate)
+        }
+        RelNode::Project(project) => {
+            let mut out = format!("(project {}", to_sexp(&project.child));
+            for expr in &project.exprs {
+                out.push(' ');
+                out.push_str(&to_sexp(expr));
+            }
+            out.push(')');
+            out
+        }
+        RelNode::Sort(sort) => {
+            let mut out = format!("(sort {}", to_sexp(&sort.child));
+            for key in &sort.keys {
+                if key.descending {
+                    out.push_str(&format!(" (desc #{})", key.column));
+                } else {
+                    out.push_str(&format!(" #{}", key.column));
+                }
+            }
+            out.push(')');
+            out
+        }
+        RelNode::Aggregate(aggregate) => {
+            let group_by = aggregate
+                .group_by
+                .iter()
+                .map(|column| format!("#{column}"))
+                .collect::<Vec<_>>();
+            let mut out = format!(
+                "(aggregate {} ({})",
+                to_sexp(&aggregate.child),
+                group_by.join(" ")
+            );
+            for agg in &aggregate.aggs {
+                let func = agg_func_name(agg.func);
+                match agg.column {
+                    Some(column) => out.push_str(&format!(" ({func} #{column})")),
+                    None => out.push_str(&format!(" ({func})")),
+                }
+            }
+            out.push(')');
+            out
+        }
+        RelNode::Limit(limit) => format!("(limit {} {})", to_sexp(&limit.child), limit.limit),
+        RelNode::Eq(eq) => binary("=", &eq.left, &eq.right),
+        RelNode::And(and) => binary("and", &and.left, &and.right),
+        RelNode::Or(or) => binary("or", &or.left, &or.right),
+        RelNode::Add(add) => binary("+", &add.left, &add.right),
+        RelNode::ColumnRef(column_ref) => format!("#{}", column_ref.column),
+        RelNode::Const(const_pred) => const_pred.value.to_string(),
+    }
+}
+
+fn is_scalar(rel: &RelNode) -> bool {
+    matches!(
+        rel,
+        RelNode::Eq(_)
+            | RelNode::And(_)
+            | RelNode::Or(_)
+            | RelNode::Add(_)
+            | RelNode::ColumnRef(_)
+            | RelNode::Const(_)
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Atom(&'a str),
+    End,
+}
+
+struct SexpParser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> SexpParser<'a> {
+    fn error(&self, offset: usize, message: impl Into<String>) -> ParseError {
+        ParseError::at(self.text, offset, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Returns the next token and its offset, without consuming it.
+    fn peek(&mut self) -> (Token<'a>, usize) {
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let token = match rest.chars().next() {
+            None => Token::End,
+            Some('(') => Token::Open,
+            Some(')') => Token::Close,
+            Some(_) => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .unwrap_or(rest.len());
+                Token::Atom(&rest[..len])
+            }
+        };
+        (token, self.offset)
+    }
+
+    fn next(&mut self) -> (Token<'a>, usize) {
+        let (token, offset) = self.peek();
+        self.offset += match token {
+            Token::Open | Token::Close => 1,
+            Token::Atom(atom) => atom.len(),
+            Token::End => 0,
+        };
+        (token, offset)
+    }
+
+    fn expect_close(&mut self) -> Result<(), ParseError> {
+        match self.next() {
+            (Token::Close, _) => Ok(()),
+            (_, offset) => Err(self.error(offset, "expected `)`")),
+        }
+    }
+
+    fn parse_usize(&self, atom: &str, offset: usize) -> Result<usize, ParseError> {
+        atom.parse()
+            .map_err(|_| self.error(offset, format!("expected a number, found `{atom}`")))
+    }
+
+    fn parse_column(&mut self) -> Result<usize, ParseError> {
+        let (_, offset) = self.peek();
+        match &*self.parse_node()? {
+            RelNode::ColumnRef(column_ref) => Ok(column_ref.column),
+            _ => Err(self.error(offset, "expected a column reference")),
+        }
+    }
+
+    /// Parse the table id and the index name of an index access.
+    fn parse_index(&mut self) -> Result<(TableId, String), ParseError> {
+        let table = match self.next() {
+            (Token::Atom(atom), offset) => TableId(self.parse_usize(atom, offset)?),
+            (_, offset) => return Err(self.error(offset, "expected a table id")),
+        };
+        match self.next() {
+            (Token::Atom(atom), _) => Ok((table, atom.to_string())),
+            (_, offset) => Err(self.error(offset, "expected an index name")),
+        }
+    }
+
+    fn parse_rel(&mut self) -> Result<Arc<RelNode>, ParseError> {
+        let (_, offset) = self.peek();
+        let node = self.parse_node()?;
+        if is_scalar(&node) {
+            return Err(self.error(offset, "expected a relational expression"));
+        }
+        Ok(node)
+    }
+
+    fn parse_scalar(&mut self) -> Result<Arc<RelNode>, ParseError> {
+        let (_, offset) = self.peek();
+        let node = self.parse_node()?;
+        if !is_scalar(&node) {
+            return Err(self.error(offset, "expected a scalar expression"));
+        }
+        Ok(node)
+    }
+
+    fn parse_node(&mut self) -> Result<Arc<RelNode>, ParseError> {
+        match self.next() {
+            (Token::Atom(atom), offset) => {
+                if let Some(column) = atom.strip_prefix('#') {
+                    let column = self.parse_usize(column, offset + 1)?;
+                    return Ok(Arc::new(column_ref_pred(column)));
+                }
+                let value = atom.parse().map_err(|_| {
+                    self.error(offset, format!("expected an expression, found `{atom}`"))
+                })?;
+                Ok(Arc::new(const_pred(value)))
+            }
+            (Token::Open, _) => {
+                let node = self.parse_list()?;
+                self.expect_close()?;
+                Ok(Arc::new(node))
+            }
+            (Token::Close, offset) => Err(self.error(offset, "unexpected `)`")),
+            (Token::End, offset) => Err(self.error(offset, "unexpected end of input")),
+        }
+    }
+
+    /// Parse the inside of a list after `(`, up to but excluding `)`.
+    fn parse_list(&mut self) -> Result<RelNode, ParseError> {
+        let (operator, offset) = match self.next() {
+            (Token::Atom(atom), offset) => (atom, offset),
+            (_, offset) => return Err(self.error(offset, "expected an operator")),
+        };
+        let node = match operator {
+            "scan" | "table_scan" => {
+                let (table, offset) = match self.next() {
+                    (Token::Atom(atom), offset) => (atom, offset),
+                    (_, offset) => return Err(self.error(offset, "expected a table id")),
+                };
+                let scan = Scan {
+                    table: TableId(self.parse_usize(table, offset)?),
+                };
+                if operator == "scan" {
+                    RelNode::Scan(scan)
+                } else {
+                    RelNode::TableScan(scan)
+                }
+            }
+            "index_scan" => {
+                let (table, index) = self.parse_index()?;
+                let mut prefix = vec![];
+                while !matches!(self.peek().0, Token::Close | Token::End) {
+                    let (_, offset) = self.peek();
+                    match &*self.parse_node()? {
+                        RelNode::Const(const_pred) => prefix.push(const_pred.value),
+                        _ => return Err(self.error(offset, "expected a constant")),
+                    }
+                }
+                RelNode::IndexScan(IndexScan {
+                    table,
+                    index,
+                    prefix,
+                })
+            }
+            "index_lookup" => {
+                let (table, index) = self.parse_index()?;
+                let mut keys = vec![];
+                while !matches!(self.peek().0, Token::Close | Token::End) {
+                    keys.push(self.parse_column()?);
+                }
+                RelNode::IndexLookup(IndexLookup { table, index, keys })
+            }
+            "join"
+            | "hash_join"
+            | "nested_loop_join"
+            | "sort_merge_join"
+            | "index_nested_loop_join" => {
+                let join_type = match self.peek() {
+                    (Token::Atom(":semi"), _) => Some(JoinType::LeftSemi),
+                    (Token::Atom(":anti"), _) => Some(JoinType::LeftAnti),
+                    (Token::Atom(atom), offset) if atom.starts_with(':') => {
+                        return Err(self.error(offset, format!("unknown join type `{atom}`")));
+                    }
+                    _ => None,
+                };
+                if join_type.is_some() {
+                    self.next();
+                }
+                let join = Join {
+                    join_type: join_type.unwrap_or(JoinType::Inner),
+                    left: self.parse_rel()?,
+                    right: self.parse_rel()?,
+                    cond: self.parse_scalar()?,
+                };
+                match operator {
+                    "join" => RelNode::Join(join),
+                    "hash_join" => RelNode::HashJoin(join),
+                    "nested_loop_join" => RelNode::NestedLoopJoin(join),
+                    "sort_merge_join" => RelNode::SortMergeJoin(join),
+                    _ => RelNode::IndexNestedLoopJoin(join),
+                }
+            }
+            "filter" | "physical_filter" => {
+                let filter = Filter {
+                    child: self.parse_rel()?,
+                    predicate: self.parse_scalar()?,
+                };
+                if operator == "filter" {
+                    RelNode::Filter(filter)
+                } else {
+                    RelNode::PhysicalFilter(filter)
+                }
+            }
+            "project" => {
+                let child = self.parse_rel()?;
+                let mut exprs = vec![];
+                while self.peek().0 != Token::Close && self.peek().0 != Token::End {
+                    exprs.push(self.parse_scalar()?);
+                }
+                RelNode::Project(Project { child, exprs })
+            }
+            "sort" => {
+                let child = self.parse_rel()?;
+                let mut keys = vec![];
+                loop {
+                    let descending = match self.peek() {
+                        (Token::Close | Token::End, _) => break,
+                        (Token::Open, _) => {
+                            self.next();
+                            match self.next() {
+                                (Token::Atom("desc"), _) => true,
+                                (_, offset) => return Err(self.error(offset, "expected `desc`")),
+                            }
+                        }
+                        _ => false,
+                    };
+                    let column = self.parse_column()?;
+                    if descending {
+                        self.expect_close()?;
+                    }
+                    keys.push(SortKey { column, descending });
+                }
+                RelNode::Sort(Sort { child, keys })
+            }
+            "aggregate" => {
+                let child = self.parse_rel()?;
+                let mut group_by = vec![];
+                match self.next() {
+                    (Token::Open, _) => {}
+                    (_, offset) => return Err(self.error(offset, "expected group-by columns")),
+                }
+                while !matches!(self.peek().0, Token::Close | Token::End) {
+                    group_by.push(self.parse_column()?);
+                }
+                self.expect_close()?;
+                let mut aggs = vec![];
+                while let (Token::Open, _) = self.peek() {
+                    self.next();
+                    let func = match self.next() {
+                        (Token::Atom("count"), _) => AggFunc::Count,
+                        (Token::Atom("sum"), _) => AggFunc::Sum,
+                        (Token::Atom("min"), _) => AggFunc::Min,
+                        (Token::Atom("max"), _) => AggFunc::Max,
+                        (_, offset) => {
+                            return Err(self.error(offset, "expected an aggregate function"))
+                        }
+                    };
+                    let column = match self.peek().0 {
+                        Token::Close => None,
+                        _ => Some(self.parse_column()?),
+                    };
+                    self.expect_close()?;
+                    aggs.push(AggCall { func, column });
+                }
+                RelNode::Aggregate(Aggregate {
+                    child,
+                    group_by,
+                    aggs,
+                })
+            }
+            "limit" => {
+                let child = self.parse_rel()?;
+                let (limit, offset) = match self.next() {
+                    (Token::Atom(atom), offset) => (atom, offset),
+                    (_, offset) => return Err(self.error(offset, "expected a limit")),
+                };
+                RelNode::Limit(Limit {
+                    child,
+                    limit: self.parse_usize(limit, offset)?,
+                })
+            }
+            "=" => eq_pred(self.parse_scalar()?, self.parse_scalar()?),
+            "and" => and_pred(self.parse_scalar()?, self.parse_scalar()?),
+            "or" => or_pred(self.parse_scalar()?, self.parse_scalar()?),
+            "+" => add_pred(self.parse_scalar()?, self.parse_scalar()?),
+            _ => return Err(self.error(offset, format!("unknown operator `{operator}`"))),
+        };
+        Ok(node)
+    }
+}
+
+/// Parse a plan printed by `to_sexp`.
+pub fn parse_sexp(text: &str) -> Result<RelNode, ParseError> {
+    let mut parser = SexpParser { text, offset: 0 };
+    let node = parser.parse_node()?;
+    match parser.next() {
+        (Token::End, _) => Ok(Arc::unwrap_or_clone(node)),
+        (_, offset) => Err(parser.error(offset, "expected end of input")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sexp() {
+        let text = "(filter (join (scan 0) (scan 1) (= #1 #3)) (= #2 3))";
+        assert_eq!(parse_sexp(text), Ok(plan()));
+        assert_eq!(to_sexp(&plan()), text);
+
+        let text = "(sort (project (hash_join :semi (table_scan 0) (scan 1) \
+                    (and (= #0 #2) (or (= #1 -1) (= (+ #1 #0) 7)))) #1 #0) (desc #1) #0)";
+        assert_eq!(to_sexp(&parse_sexp(text).unwrap()), text);
+        let spaced = "(sort\n  (project\n    (hash_join :semi (table_scan 0) (scan 1)\n      \
+                      (and (= #0 #2) (or (= #1 -1) (= (+ #1 #0) 7))))\n    #1 #0)\n  (desc #1) #0)";
+        assert_eq!(parse_sexp(spaced), parse_sexp(text));
+        let text = "(limit (aggregate (scan 0) (#1 #0) (count) (sum #2) (max #0)) 10)";
+        assert_eq!(to_sexp(&parse_sexp(text).unwrap()), text);
+        let text = "(index_nested_loop_join (index_scan 0 t0_pkey 42 -1) \
+                    (index_lookup 1 t1_a_b #1 #0) (= #1 #2))";
+        assert_eq!(to_sexp(&parse_sexp(text).unwrap()), text);
+
+        let error = |text| parse_sexp(text).unwrap_err().to_string();
+        assert_eq!(
+            error("(filter (scan 0) (scan 1))"),
+            "1:18: expected a scalar expression"
+        );
+        assert_eq!(
+            error("(join (scan 0)\n  (scn 1) #0)"),
+            "2:4: unknown operator `scn`"
+        );
+        assert_eq!(error("(scan x)"), "1:7: expected a number, found `x`");
+        assert_eq!(error("(scan 0"), "1:8: expected `)`");
+        assert_eq!(error("(scan 0) (scan 1)"), "1:10: expected end of input");
+        assert_eq!(
+            error("(join :left (scan 0) (scan 1) 1)"),
+            "1:7: unknown join type `:left`"
+        );
+    }
+}
@@ -0,0 +1,819 @@
+use std::sync::Arc;
+
+use super::*;
+
+// A SQL frontend for a subset of SELECT:
+//
+// SELECT items FROM t [AS a] [[INNER] JOIN u [AS b] ON cond]... [WHERE cond]
+//   [GROUP BY columns] [ORDER BY items [ASC | DESC]] [LIMIT n]
+//
+// The parser builds a syntax tree that still has names in it. The binder resolves the names
+// against the schema, turning tables into `TableId`s and columns into their index in the row, and
+// lowers the query into `Scan`, `Join`, `Filter`, `Aggregate`, `Project`, `Sort` and `Limit`.
+// Every error points to the token it is about.
+
+/// The tables and columns that queries can refer to by name.
+pub trait SchemaProvider {
+    fn table_by_name(&self, name: &str) -> Option<TableId>;
+    fn column_names(&self, table: &TableId) -> Vec<String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SqlToken {
+    Ident(String),
+    Number(i64),
+    Symbol(char),
+    End,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(SqlToken, usize)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push((SqlToken::Ident(ident), offset));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            let number = number
+                .parse()
+                .map_err(|_| ParseError::at(text, offset, "number out of range"))?;
+            tokens.push((SqlToken::Number(number), offset));
+        } else if "(),.*=+-<>;".contains(c) {
+            tokens.push((SqlToken::Symbol(c), offset));
+            chars.next();
+        } else {
+            return Err(ParseError::at(
+                text,
+                offset,
+                format!("unexpected character `{c}`"),
+            ));
+        }
+    }
+    tokens.push((SqlToken::End, text.len()));
+    Ok(tokens)
+}
+
+const KEYWORDS: &[&str] = &[
+    "select", "from", "join", "inner", "on", "where", "group", "by", "order", "asc", "desc",
+    "limit", "and", "or", "as",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlExprKind {
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    Number(i64),
+    Eq(Box<SqlExpr>, Box<SqlExpr>),
+    And(Box<SqlExpr>, Box<SqlExpr>),
+    Or(Box<SqlExpr>, Box<SqlExpr>),
+    Add(Box<SqlExpr>, Box<SqlExpr>),
+    /// `None` is `COUNT(*)`.
+    Agg(AggFunc, Option<Box<SqlExpr>>),
+}
+
+/// An expression and the offset where it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlExpr {
+    pub kind: SqlExprKind,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectItem {
+    /// `*`
+    Wildcard,
+    Expr {
+        expr: SqlExpr,
+        alias: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderByItem {
+    pub expr: SqlExpr,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectStmt {
+    pub items: Vec<SelectItem>,
+    pub from: TableRef,
+    pub joins: Vec<(TableRef, SqlExpr)>,
+    pub filter: Option<SqlExpr>,
+    pub group_by: Vec<SqlExpr>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<usize>,
+}
+
+struct SqlParser<'a> {
+    text: &'a str,
+    tokens: Vec<(SqlToken, usize)>,
+    pos: usize,
+}
+
+impl SqlParser<'_> {
+    fn peek(&self) -> &SqlToken {
+        &self.tokens[self.pos].0
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> (SqlToken, usize) {
+        let token = self.tokens[self.pos].clone();
+        if token.0 != SqlToken::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::at(self.text, self.offset(), message)
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            SqlToken::Ident(ident) => format!("`{ident}`"),
+            SqlToken::Number(number) => format!("`{number}`"),
+            SqlToken::Symbol(symbol) => format!("`{symbol}`"),
+            SqlToken::End => "end of input".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), SqlToken::Ident(ident) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "expected {}, found {}",
+                keyword.to_uppercase(),
+                self.describe()
+            )))
+        }
+    }
+
+    fn eat_symbol(&mut self, symbol: char) -> bool {
+        let found = *self.peek() == SqlToken::Symbol(symbol);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), ParseError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{symbol}`, found {}", self.describe())))
+        }
+    }
+
+    /// Whether the next token is an identifier that is not a keyword.
+    fn is_name(&self) -> bool {
+        matches!(self.peek(), SqlToken::Ident(ident) if !KEYWORDS.contains(&ident.to_lowercase().as_str()))
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        if !self.is_name() {
+            return Err(self.error(format!("expected a name, found {}", self.describe())));
+        }
+        match self.advance() {
+            (SqlToken::Ident(ident), _) => Ok(ident),
+            _ => unreachable!(),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, ParseError> {
+        match self.peek() {
+            SqlToken::Number(number) => {
+                let number = *number;
+                self.advance();
+                Ok(number)
+            }
+            _ => Err(self.error(format!("expected a number, found {}", self.describe()))),
+        }
+    }
+
+    fn select(&mut self) -> Result<SelectStmt, ParseError> {
+        self.expect_keyword("select")?;
+        let mut items = vec![];
+        loop {
+            if self.eat_symbol('*') {
+                items.push(SelectItem::Wildcard);
+            } else {
+                let expr = self.expr()?;
+                let alias = if self.eat_keyword("as") {
+                    Some(self.ident()?)
+                } else {
+                    None
+                };
+                items.push(SelectItem::Expr { expr, alias });
+            }
+            if !self.eat_symbol(',') {
+                break;
+            }
+        }
+        self.expect_keyword("from")?;
+        let from = self.table_ref()?;
+        let mut joins = vec![];
+        loop {
+            if self.eat_keyword("inner") {
+                self.expect_keyword("join")?;
+            } else if !self.eat_keyword("join") {
+                break;
+            }
+            let table = self.table_ref()?;
+            self.expect_keyword("on")?;
+            joins.push((table, self.expr()?));
+        }
+        let filter = if self.eat_keyword("where") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        let mut group_by = vec![];
+        if self.eat_keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                group_by.push(self.expr()?);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+        }
+        let mut order_by = vec![];
+        if self.eat_keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let expr = self.expr()?;
+                let descending = if self.eat_keyword("desc") {
+                    true
+                } else {
+                    self.eat_keyword("asc");
+                    false
+                };
+                order_by.push(OrderByItem { expr, descending });
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+        }
+        let limit = if self.eat_keyword("limit") {
+            let offset = self.offset();
+            let limit = self.number()?;
+            Some(
+                usize::try_from(limit)
+                    .map_err(|_| ParseError::at(self.text, offset, "invalid limit"))?,
+            )
+        } else {
+            None
+        };
+        self.eat_symbol(';');
+        if *self.peek() != SqlToken::End {
+            return Err(self.error(format!("unexpected {}", self.describe())));
+        }
+        Ok(SelectStmt {
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            order_by,
+            limit,
+        })
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef, ParseError> {
+        let offset = self.offset();
+        let name = self.ident()?;
+        let alias = if self.eat_keyword("as") || self.is_name() {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        Ok(TableRef {
+            name,
+            alias,
+            offset,
+        })
+    }
+
+    fn expr(&mut self) -> Result<SqlExpr, ParseError> {
+        let mut left = self.and_expr()?;
+        while self.eat_keyword("or") {
+            let right = self.and_expr()?;
+            left = binary(left, right, SqlExprKind::Or);
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<SqlExpr, ParseError> {
+        let mut left = self.comparison()?;
+        while self.eat_keyword("and") {
+            let right = self.comparison()?;
+            left = binary(left, right, SqlExprKind::And);
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<SqlExpr, ParseError> {
+        let left = self.sum()?;
+        if self.eat_symbol('=') {
+            let right = self.sum()?;
+            return Ok(binary(left, right, SqlExprKind::Eq));
+        }
+        if let SqlToken::Symbol(symbol @ ('<' | '>')) = self.peek() {
+            return Err(self.error(format!("unsupported operator `{symbol}`")));
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<SqlExpr, ParseError> {
+        let mut left = self.primary()?;
+        loop {
+            if self.eat_symbol('+') {
+                let right = self.primary()?;
+                left = binary(left, right, SqlExprKind::Add);
+            } else if let SqlToken::Symbol('-') = self.peek() {
+                return Err(self.error("unsupported operator `-`"));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<SqlExpr, ParseError> {
+        let offset = self.offset();
+        let kind = match self.peek().clone() {
+            SqlToken::Number(number) => {
+                self.advance();
+                SqlExprKind::Number(number)
+            }
+            SqlToken::Symbol('-') => {
+                self.advance();
+                SqlExprKind::Number(-self.number()?)
+            }
+            SqlToken::Symbol('(') => {
+                self.advance();
+                let expr = self.expr()?;
+                self.expect_symbol(')')?;
+                return Ok(SqlExpr { offset, ..expr });
+            }
+            SqlToken::Ident(ident) if self.tokens[self.pos + 1].0 == SqlToken::Symbol('(') => {
+                let func = match ident.to_lowercase().as_str() {
+                    "count" => AggFunc::Count,
+                    "sum" => AggFunc::Sum,
+                    "min" => AggFunc::Min,
+                    "max" => AggFunc::Max,
+                    _ => return Err(self.error(format!("unknown function `{ident}`"))),
+                };
+                self.advance();
+                self.advance();
+                let arg = if func == AggFunc::Count && self.eat_symbol('*') {
+                    None
+                } else {
+                    Some(Box::new(self.expr()?))
+                };
+                self.expect_symbol(')')?;
+                SqlExprKind::Agg(func, arg)
+            }
+            _ => {
+                let name = self.ident()?;
+                if self.eat_symbol('.') {
+                    SqlExprKind::Column {
+                        table: Some(name),
+                        name: self.ident()?,
+                    }
+                } else {
+                    SqlExprKind::Column { table: None, name }
+                }
+            }
+        };
+        Ok(SqlExpr { kind, offset })
+    }
+}
+
+fn binary(
+    left: SqlExpr,
+    right: SqlExpr,
+    kind: fn(Box<SqlExpr>, Box<SqlExpr>) -> SqlExprKind,
+) -> SqlExpr {
+    SqlExpr {
+        offset: left.offset,
+        kind: kind(Box::new(left), Box::new(right)),
+    }
+}
+
+/// Parse a SELECT statement.
+pub fn parse_sql(text: &str) -> Result<SelectStmt, ParseError> {
+    let mut parser = SqlParser {
+        text,
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    parser.select()
+}
+
+/// A column of the rows flowing through the plan, and the table (or alias) it can be qualified
+/// with.
+struct ScopeColumn {
+    table: String,
+    name: String,
+}
+
+struct Binder<'a> {
+    text: &'a str,
+    schema: &'a dyn SchemaProvider,
+    scope: Vec<ScopeColumn>,
+}
+
+/// After GROUP BY, expressions can only refer to the group-by columns and to aggregates, which
+/// are added to `aggs` as they are found.
+struct Grouping {
+    group_by: Vec<usize>,
+    aggs: Vec<AggCall>,
+}
+
+impl Binder<'_> {
+    fn error(&self, offset: usize, message: impl Into<String>) -> ParseError {
+        ParseError::at(self.text, offset, message)
+    }
+
+    fn add_table(&mut self, table: &TableRef) -> Result<RelNode, ParseError> {
+        let id = self
+            .schema
+            .table_by_name(&table.name)
+            .ok_or_else(|| self.error(table.offset, format!("unknown table `{}`", table.name)))?;
+        let alias = table.alias.clone().unwrap_or_else(|| table.name.clone());
+        if self.scope.iter().any(|column| column.table == alias) {
+            return Err(self.error(table.offset, format!("duplicate table name `{alias}`")));
+        }
+        for name in self.schema.column_names(&id) {
+            self.scope.push(ScopeColumn {
+                table: alias.clone(),
+                name,
+            });
+        }
+        Ok(scan(id))
+    }
+
+    fn resolve_column(
+        &self,
+        table: &Option<String>,
+        name: &str,
+        offset: usize,
+    ) -> Result<usize, ParseError> {
+        if let Some(table) = table {
+            if !self.scope.iter().any(|column| column.table == *table) {
+                return Err(self.error(offset, format!("unknown table `{table}`")));
+            }
+        }
+        let mut matches = self.scope.iter().enumerate().filter(|(_, column)| {
+            column.name == name && table.as_ref().is_none_or(|table| column.table == *table)
+        });
+        match (matches.next(), matches.next()) {
+            (Some((idx, _)), None) => Ok(idx),
+            (Some(_), Some(_)) => Err(self.error(offset, format!("ambiguous column `{name}`"))),
+            (None, _) => Err(self.error(offset, format!("unknown column `{name}`"))),
+        }
+    }
+
+    /// Bind an expression over the columns in scope, or over the output of the aggregate if
+    /// `grouping` is given.
+    fn bind_expr(
+        &self,
+        expr: &SqlExpr,
+        mut grouping: Option<&mut Grouping>,
+    ) -> Result<Arc<RelNode>, ParseError> {
+        let node = match &expr.kind {
+            SqlExprKind::Column { table, name } => {
+                let column = self.resolve_column(table, name, expr.offset)?;
+                match grouping {
+                    Some(grouping) => {
+                        let idx = grouping
+                            .group_by
+                            .iter()
+                            .position(|group_column| *group_column == column)
+                            .ok_or_else(|| {
+                                self.error(
+                                    expr.offset,
+                                    format!("column `{name}` must appear in GROUP BY"),
+                                )
+                            })?;
+                        column_ref_pred(idx)
+                    }
+                    None => column_ref_pred(column),
+                }
+            }
+            SqlExprKind::Number(value) => const_pred(*value),
+            SqlExprKind::Eq(left, right) => eq_pred(
+                self.bind_expr(left, grouping.as_deref_mut())?,
+                self.bind_expr(right, grouping)?,
+            ),
+            SqlExprKind::And(left, right) => and_pred(
+                self.bind_expr(left, grouping.as_deref_mut())?,
+                self.bind_expr(right, grouping)?,
+            ),
+            SqlExprKind::Or(left, right) => or_pred(
+                self.bind_expr(left, grouping.as_deref_mut())?,
+                self.bind_expr(right, grouping)?,
+            ),
+            SqlExprKind::Add(left, right) => add_pred(
+                self.bind_expr(left, grouping.as_deref_mut())?,
+                self.bind_expr(right, grouping)?,
+            ),
+            SqlExprKind::Agg(func, arg) => {
+                let Some(grouping) = grouping else {
+                    return Err(self.error(expr.offset, "aggregate not allowed here"));
+                };
+                let column = match arg {
+                    None => None,
+                    Some(arg) => match &*self.bind_expr(arg, None)? {
+                        RelNode::ColumnRef(column_ref) => Some(column_ref.column),
+                        _ => {
+                            return Err(
+                                self.error(arg.offset, "aggregate argument must be a column")
+                            )
+                        }
+                    },
+                };
+                let call = AggCall {
+                    func: *func,
+                    column,
+                };
+                let idx = match grouping.aggs.iter().position(|agg| *agg == call) {
+                    Some(idx) => idx,
+                    None => {
+                        grouping.aggs.push(call);
+                        grouping.aggs.len() - 1
+                    }
+                };
+                column_ref_pred(grouping.group_by.len() + idx)
+            }
+        };
+        Ok(Arc::new(node))
+    }
+
+    fn bind(&mut self, stmt: &SelectStmt) -> Result<RelNode, ParseError> {
+        let mut rel = self.add_table(&stmt.from)?;
+        for (table, cond) in &stmt.joins {
+            let right = self.add_table(table)?;
+            rel = join(rel, right, self.bind_expr(cond, None)?);
+        }
+        if let Some(pred) = &stmt.filter {
+            rel = filter(rel, self.bind_expr(pred, None)?);
+        }
+
+        let has_aggs = stmt.items.iter().any(|item| match item {
+            SelectItem::Expr { expr, .. } => contains_agg(expr),
+            SelectItem::Wildcard => false,
+        });
+        let mut grouping = if has_aggs || !stmt.group_by.is_empty() {
+            let mut group_by = vec![];
+            for expr in &stmt.group_by {
+                let RelNode::ColumnRef(column_ref) = &*self.bind_expr(expr, None)? else {
+                    return Err(self.error(expr.offset, "GROUP BY must be a column"));
+                };
+                group_by.push(column_ref.column);
+            }
+            Some(Grouping {
+                group_by,
+                aggs: vec![],
+            })
+        } else {
+            None
+        };
+
+        // the output columns, and their names for ORDER BY
+        let mut exprs = vec![];
+        let mut names = vec![];
+        for item in &stmt.items {
+            match item {
+                SelectItem::Wildcard => {
+                    if let Some(grouping) = &grouping {
+                        for (idx, column) in grouping.group_by.iter().enumerate() {
+                            exprs.push(Arc::new(column_ref_pred(idx)));
+                            names.push(Some(self.scope[*column].name.clone()));
+                        }
+                    } else {
+                        for (idx, column) in self.scope.iter().enumerate() {
+                            exprs.push(Arc::new(column_ref_pred(idx)));
+                            names.push(Some(column.name.clone()));
+                        }
+                    }
+                }
+                SelectItem::Expr { expr, alias } => {
+                    exprs.push(self.bind_expr(expr, grouping.as_mut())?);
+                    names.push(alias.clone().or_else(|| match &expr.kind {
+                        SqlExprKind::Column { name, .. } => Some(name.clone()),
+                        _ => None,
+                    }));
+                }
+            }
+        }
+        let mut keys = vec![];
+        for item in &stmt.order_by {
+            let column = match &item.expr.kind {
+                SqlExprKind::Number(position) => usize::try_from(*position)
+                    .ok()
+                    .filter(|position| (1..=exprs.len()).contains(position))
+                    .map(|position| position - 1)
+                    .ok_or_else(|| {
+                        self.error(item.expr.offset, "ORDER BY position out of range")
+                    })?,
+                SqlExprKind::Column { table: None, name }
+                    if names.iter().any(|n| n.as_deref() == Some(name)) =>
+                {
+                    names
+                        .iter()
+                        .position(|n| n.as_deref() == Some(name))
+                        .unwrap()
+                }
+                _ => {
+                    let expr = self.bind_expr(&item.expr, grouping.as_mut())?;
+                    exprs.iter().position(|e| *e == expr).ok_or_else(|| {
+                        self.error(
+                            item.expr.offset,
+                            "ORDER BY expression must appear in the select list",
+                        )
+                    })?
+                }
+            };
+            keys.push(SortKey {
+                column,
+                descending: item.descending,
+            });
+        }
+
+        let input_columns = match &grouping {
+            Some(grouping) => {
+                rel = aggregate(rel, grouping.group_by.clone(), grouping.aggs.clone());
+                grouping.group_by.len() + grouping.aggs.len()
+            }
+            None => self.scope.len(),
+        };
+        // a projection that passes all columns through in order is left out
+        let identity = exprs.len() == input_columns
+            && exprs
+                .iter()
+                .enumerate()
+                .all(|(idx, expr)| **expr == column_ref_pred(idx));
+        if !identity {
+            rel = project(rel, exprs);
+        }
+        if !keys.is_empty() {
+            rel = sort(rel, keys);
+        }
+        if let Some(n) = stmt.limit {
+            rel = limit(rel, n);
+        }
+        Ok(rel)
+    }
+}
+
+fn contains_agg(expr: &SqlExpr) -> bool {
+    match &expr.kind {
+        SqlExprKind::Agg(..) => true,
+        SqlExprKind::Column { .. } | SqlExprKind::Number(_) => false,
+        SqlExprKind::Eq(left, right)
+        | SqlExprKind::And(left, right)
+        | SqlExprKind::Or(left, right)
+        | SqlExprKind::Add(left, right) => contains_agg(left) || contains_agg(right),
+    }
+}
+
+/// Parse a SELECT statement and lower it into a logical plan.
+pub fn sql_to_rel(text: &str, schema: &dyn SchemaProvider) -> Result<RelNode, ParseError> {
+    let stmt = parse_sql(text)?;
+    let mut binder = Binder {
+        text,
+        schema,
+        scope: vec![],
+    };
+    binder.bind(&stmt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestSchema;
+
+    impl SchemaProvider for TestSchema {
+        fn table_by_name(&self, name: &str) -> Option<TableId> {
+            ["t0", "t1"]
+                .iter()
+                .position(|table| *table == name)
+                .map(TableId)
+        }
+
+        fn column_names(&self, table: &TableId) -> Vec<String> {
+            let columns = [["a", "b"], ["c", "d"]][table.0];
+            columns.iter().map(|column| column.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn test_sql() {
+        let sql = |text| sql_to_rel(text, &TestSchema);
+        assert_eq!(
+            sql("SELECT * FROM t0 JOIN t1 ON b = d WHERE c = 3"),
+            Ok(plan())
+        );
+        assert_eq!(
+            sql(
+                "select t1.c, a + 1 from t0 x inner join t1 on x.b = t1.d order by 2 desc limit 5;"
+            )
+            .map(|rel| to_sexp(&rel)),
+            Ok(
+                "(limit (sort (project (join (scan 0) (scan 1) (= #1 #3)) #2 (+ #0 1)) \
+                (desc #1)) 5)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            sql(
+                "SELECT b, COUNT(*), SUM(a) AS total FROM t0 WHERE a = 1 OR a = 2 \
+                 GROUP BY b ORDER BY total"
+            )
+            .map(|rel| to_sexp(&rel)),
+            Ok(
+                "(sort (aggregate (filter (scan 0) (or (= #0 1) (= #0 2))) (#1) (count) (sum #0)) \
+                #2)"
+                .to_string()
+            )
+        );
+
+        let error = |text| sql(text).unwrap_err().to_string();
+        assert_eq!(error("SELECT * FROM t2"), "1:15: unknown table `t2`");
+        assert_eq!(
+            error("SELECT *\nFROM t0 JOIN t1 ON b = e"),
+            "2:24: unknown column `e`"
+        );
+        assert_eq!(
+            error("SELECT * FROM t0 JOIN t0 ON a = a"),
+            "1:23: duplicate table name `t0`"
+        );
+        assert_eq!(
+            error("SELECT * FROM t0 JOIN t0 AS u ON a = u.a"),
+            "1:34: ambiguous column `a`"
+        );
+        assert_eq!(error("SELECT u.a FROM t0"), "1:8: unknown table `u`");
+        assert_eq!(
+            error("SELECT a, COUNT(*) FROM t0 GROUP BY b"),
+            "1:8: column `a` must appear in GROUP BY"
+        );
+        assert_eq!(
+            error("SELECT * FROM t0 WHERE COUNT(*) = 1"),
+            "1:24: aggregate not allowed here"
+        );
+        assert_eq!(
+            error("SELECT * FROM t0 WHERE a < 1"),
+            "1:26: unsupported operator `<`"
+        );
+        assert_eq!(error("SELECT * t0"), "1:10: expected FROM, found `t0`");
+        assert_eq!(
+            error("SELECT a FROM t0 ORDER BY b"),
+            "1:27: ORDER BY expression must appear in the select list"
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use std::fmt;
+
+use super::*;
+
+// The catalog holds what the optimizer knows about each table: named, typed columns, the number
+// of rows, constraints and indexes. Constraints are facts about the data that the optimizer can
+// rely on, e.g. a filter on a primary key returns at most one row, and a foreign key always finds
+// its referenced row.
+
+#[derive(Copy, Debug, Clone, Hash, Eq, PartialEq)]
+pub enum DataType {
+    Int,
+    Bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub not_null: bool,
+}
+
+impl ColumnDef {
+    pub fn new(name: &str, data_type: DataType) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type,
+            not_null: false,
+        }
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.not_null = true;
+        self
+    }
+}
+
+/// Every row of the table has a matching row in `referenced_table`, unless one of the columns is
+/// NULL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub columns: Vec<usize>,
+    pub referenced_table: TableId,
+    pub referenced_columns: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    pub name: String,
+    pub columns: Vec<usize>,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableDef {
+    pub id: TableId,
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+    pub rows: f64,
+    pub primary_key: Option<Vec<usize>>,
+    /// Unique keys other than the primary key.
+    pub unique_keys: Vec<Vec<usize>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub indexes: Vec<Index>,
+}
+
+impl TableDef {
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column.name == name)
+    }
+
+    /// The primary key and the unique keys.
+    pub fn keys(&self) -> impl Iterator<Item = &Vec<usize>> {
+        self.primary_key.iter().chain(&self.unique_keys)
+    }
+
+    /// Whether `columns` contain a key, so that no two rows have the same values in them.
+    pub fn is_unique(&self, columns: &[usize]) -> bool {
+        self.keys()
+            .any(|key| key.iter().all(|column| columns.contains(column)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatalogError {
+    DuplicateTable(String),
+    DuplicateColumn {
+        table: String,
+        column: String,
+    },
+    UnknownColumn {
+        table: String,
+        column: String,
+    },
+    DuplicatePrimaryKey(String),
+    DuplicateIndex(String),
+    /// A foreign key must reference a key, with columns of the same types.
+    InvalidForeignKey(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::DuplicateTable(table) => write!(f, "table `{table}` already exists"),
+            CatalogError::DuplicateColumn { table, column } => {
+                write!(f, "column `{column}` appears twice in table `{table}`")
+            }
+            CatalogError::UnknownColumn { table, column } => {
+                write!(f, "table `{table}` has no column `{column}`")
+            }
+            CatalogError::DuplicatePrimaryKey(table) => {
+                write!(f, "table `{table}` already has a primary key")
+            }
+            CatalogError::DuplicateIndex(index) => write!(f, "index `{index}` already exists"),
+            CatalogError::InvalidForeignKey(message) => write!(f, "invalid foreign key: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    /// Indexed by `TableId`.
+    tables: Vec<TableDef>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_table(
+        &mut self,
+        name: &str,
+        columns: Vec<ColumnDef>,
+        rows: f64,
+    ) -> Result<TableId, CatalogError> {
+        if self.table_by_name(name).is_some() {
+            return Err(CatalogError::DuplicateTable(name.to_string()));
+        }
+        for (idx, column) in columns.iter().enumerate() {
+            if columns[..idx].iter().any(|other| other.name == column.name) {
+                return Err(CatalogError::DuplicateColumn {
+                    table: name.to_string(),
+                    column: column.name.clone(),
+                });
+            }
+        }
+        let id = TableId(self.tables.len());
+        self.tables.push(TableDef {
+            id: id.clone(),
+            name: name.to_string(),
+            columns,
+            rows,
+            primary_key: None,
+            unique_keys: vec![],
+            foreign_keys: vec![],
+            indexes: vec![],
+        });
+        Ok(id)
+    }
+
+    pub fn table(&self, table: &TableId) -> &TableDef {
+        &self.tables[table.0]
+    }
+
+    pub fn table_by_name(&self, name: &str) -> Option<&TableDef> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    pub fn tables(&self) -> &[TableDef] {
+        &self.tables
+    }
+
+    pub fn set_row_count(&mut self, table: &TableId, rows: f64) {
+        self.tables[table.0].rows = rows;
+    }
+
+    fn resolve_columns(&self, table: &TableId, names: &[&str]) -> Result<Vec<usize>, CatalogError> {
+        let table = self.table(table);
+        names
+            .iter()
+            .map(|name| {
+                table
+                    .column_index(name)
+                    .ok_or_else(|| CatalogError::UnknownColumn {
+                        table: table.name.clone(),
+                        column: name.to_string(),
+                    })
+            })
+            .collect()
+    }
+
+    /// The primary key is unique, NOT NULL and has an index named `<table>_pkey`.
+    pub fn add_primary_key(
+        &mut self,
+        table: &TableId,
+        columns: &[&str],
+    ) -> Result<(), CatalogError> {
+        let columns = self.resolve_columns(table, columns)?;
+        let name = self.table(table).name.clone();
+        if self.table(table).primary_key.is_some() {
+            return Err(CatalogError::DuplicatePrimaryKey(name));
+        }
+        self.add_index_on(table, &format!("{name}_pkey"), columns.clone(), true)?;
+        let def = &mut self.tables[table.0];
+        for column in &columns {
+            def.columns[*column].not_null = true;
+        }
+        def.primary_key = Some(columns);
+        Ok(())
+    }
+
+    /// A unique key has an index named `<table>_<columns>_key`.
+    pub fn add_unique_key(
+        &mut self,
+        table: &TableId,
+        columns: &[&str],
+    ) -> Result<(), CatalogError> {
+        let name = format!("{}_{}_key", self.table(table).name, columns.join("_"));
+        let columns = self.resolve_columns(table, columns)?;
+        self.add_index_on(table, &name, columns.clone(), true)?;
+        self.tables[table.0].unique_keys.push(columns);
+        Ok(())
+    }
+
+    pub fn add_foreign_key(
+        &mut self,
+        table: &TableId,
+        columns: &[&str],
+        referenced_table: &TableId,
+        referenced_columns: &[&str],
+    ) -> Result<(), CatalogError> {
+        let columns = self.resolve_columns(table, columns)?;
+        let referenced_columns = self.resolve_columns(referenced_table, referenced_columns)?;
+        let (def, referenced) = (self.table(table), self.table(referenced_table));
+        if columns.len() != referenced_columns.len() {
+            return Err(CatalogError::InvalidForeignKey(format!(
+                "{} columns reference {} columns",
+                columns.len(),
+                referenced_columns.len()
+            )));
+        }
+        for (column, referenced_column) in columns.iter().zip(&referenced_columns) {
+            let (column, referenced_column) = (
+                &def.columns[*column],
+                &referenced.columns[*referenced_column],
+            );
+            if column.data_type != referenced_column.data_type {
+                return Err(CatalogError::InvalidForeignKey(format!(
+                    "`{}.{}` and `{}.{}` have different types",
+                    def.name, column.name, referenced.name, referenced_column.name
+                )));
+            }
+        }
+        if !referenced.keys().any(|key| {
+            key.len() == referenced_columns.len()
+                && key.iter().all(|column| referenced_columns.contains(column))
+        }) {
+            return Err(CatalogError::InvalidForeignKey(format!(
+                "the referenced columns are not a key of `{}`",
+                referenced.name
+            )));
+        }
+        self.tables[table.0].foreign_keys.push(ForeignKey {
+            columns,
+            referenced_table: referenced_table.clone(),
+            referenced_columns,
+        });
+        Ok(())
+    }
+
+    pub fn add_index(
+        &mut self,
+        table: &TableId,
+        name: &str,
+        columns: &[&str],
+    ) -> Result<(), CatalogError> {
+        let columns = self.resolve_columns(table, columns)?;
+        self.add_index_on(table, name, columns, false)
+    }
+
+    fn add_index_on(
+        &mut self,
+        table: &TableId,
+        name: &str,
+        columns: Vec<usize>,
+        unique: bool,
+    ) -> Result<(), CatalogError> {
+        let exists = self
+            .tables
+            .iter()
+            .flat_map(|table| &table.indexes)
+            .any(|index| index.name == name);
+        if exists {
+            return Err(CatalogError::DuplicateIndex(name.to_string()));
+        }
+        self.tables[table.0].indexes.push(Index {
+            name: name.to_string(),
+            columns,
+            unique,
+        });
+        Ok(())
+    }
+}
+
+impl TableStats for Catalog {
+    fn column_count(&self, table: &TableId) -> usize {
+        self.table(table).columns.len()
+    }
+
+    fn row_count(&self, table: &TableId) -> f64 {
+        self.table(table).rows
+    }
+
+    fn keys(&self, table: &TableId) -> Vec<Vec<usize>> {
+        let def = self.table(table);
+        def.keys()
+            .filter(|key| key.iter().all(|column| def.columns[*column].not_null))
+            .cloned()
+            .collect()
+    }
+
+    fn indexes(&self, table: &TableId) -> Vec<Index> {
+        self.table(table).indexes.clone()
+    }
+}
+
+impl SchemaProvider for Catalog {
+    fn table_by_name(&self, name: &str) -> Option<TableId> {
+        Catalog::table_by_name(self, name).map(|table| table.id.clone())
+    }
+
+    fn column_names(&self, table: &TableId) -> Vec<String> {
+        let columns = &self.table(table).columns;
+        columns.iter().map(|column| column.name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::test_fixtures::customers_orders;
+
+    #[test]
+    fn test_catalog() {
+        let (mut catalog, customers, orders) = customers_orders();
+        catalog
+            .add_foreign_key(&orders, &["customer_id"], &customers, &["id"])
+            .unwrap();
+        catalog
+            .add_index(&orders, "orders_customer", &["customer_id"])
+            .unwrap();
+
+        let def = catalog.table(&orders);
+        assert!(def.columns[0].not_null);
+        assert!(!def.columns[2].not_null);
+        assert!(def.is_unique(&[2, 0]));
+        assert!(!def.is_unique(&[1]));
+        assert_eq!(
+            def.indexes
+                .iter()
+                .map(|index| &index.name[..])
+                .collect::<Vec<_>>(),
+            ["orders_pkey", "orders_customer"]
+        );
+        assert_eq!(def.foreign_keys[0].referenced_columns, [0]);
+
+        assert_eq!(
+            catalog.add_table("orders", vec![], 0.0),
+            Err(CatalogError::DuplicateTable("orders".to_string()))
+        );
+        assert_eq!(
+            catalog.add_primary_key(&orders, &["amount"]),
+            Err(CatalogError::DuplicatePrimaryKey("orders".to_string()))
+        );
+        assert_eq!(
+            catalog
+                .add_foreign_key(&customers, &["region"], &orders, &["customer_id"])
+                .unwrap_err()
+                .to_string(),
+            "invalid foreign key: the referenced columns are not a key of `orders`"
+        );
+        assert_eq!(
+            catalog.add_index(&orders, "orders_customer", &["nope"]),
+            Err(CatalogError::UnknownColumn {
+                table: "orders".to_string(),
+                column: "nope".to_string()
+            })
+        );
+
+        // the catalog resolves names for the binder, and gives statistics and keys to the
+        // optimizer
+        let rel = sql_to_rel(
+            "SELECT amount FROM orders JOIN customers ON customer_id = customers.id \
+             WHERE orders.id = 42",
+            &catalog,
+        )
+        .unwrap();
+        assert_eq!(
+            to_sexp(&rel),
+            "(project (filter (join (scan 1) (scan 0) (= #1 #3)) (= #0 42)) #2)"
+        );
+        let by_key = filter(
+            scan(orders.clone()),
+            eq_pred(column_ref_pred(0), const_pred(42)),
+        );
+        assert_eq!(estimate_cardinality(&by_key, &catalog), 1.0);
+        let by_amount = filter(scan(orders), eq_pred(column_ref_pred(2), const_pred(42)));
+        assert_eq!(estimate_cardinality(&by_amount, &catalog), 1000.0);
+
+        let mut optimizer = Optimizer::new(&catalog);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(by_key));
+        assert_eq!(optimizer.logical_props(group).cardinality, 1.0);
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use super::*;
+
+// With indexes in the catalog, a table can be read in more ways than a full scan. A filter that
+// fixes the leading columns of an index with `column = constant` conjuncts (a sargable predicate)
+// becomes a range scan of the index, and the rest of the predicate is evaluated on the rows it
+// returns. A join whose condition fixes the leading index columns of the right table becomes an
+// index nested-loop join, which looks the matching rows up for each row of the left side. Index
+// scans also return the rows in index order, which can save a sort.
+
+/// The columns of the index named `index` on `table`.
+pub fn index_columns(stats: &dyn TableStats, table: &TableId, index: &str) -> Vec<usize> {
+    stats
+        .indexes(table)
+        .into_iter()
+        .find(|def| def.name == index)
+        .map(|def| def.columns)
+        .unwrap_or_else(|| panic!("table {table:?} has no index `{index}`"))
+}
+
+/// The number of rows of the table with given values in the first `fixed` columns of the index.
+pub fn index_cardinality(
+    stats: &dyn TableStats,
+    table: &TableId,
+    index: &str,
+    fixed: usize,
+) -> f64 {
+    let columns = index_columns(stats, table, index);
+    let rows = stats.row_count(table) * DEFAULT_SELECTIVITY.powi(fixed as i32);
+    if fixed > 0 && stats.is_unique(table, &columns[..fixed]) {
+        rows.min(1.0)
+    } else {
+        rows
+    }
+}
+
+/// The value compared with `column` if the predicate is `column = constant`.
+fn fixed_value(pred: &RelNode, column: usize) -> Option<i64> {
+    let RelNode::Eq(eq) = pred else {
+        return None;
+    };
+    match (&*eq.left, &*eq.right) {
+        (RelNode::ColumnRef(column_ref), RelNode::Const(const_pred))
+        | (RelNode::Const(const_pred), RelNode::ColumnRef(column_ref))
+            if column_ref.column == column =>
+        {
+            Some(const_pred.value)
+        }
+        _ => None,
+    }
+}
+
+/// Split the conjuncts of a filter into the values they fix for the leading index `columns`, and
+/// the conjuncts that remain to be evaluated on the rows of the index scan.
+pub fn sargable_prefix(columns: &[usize], pred: &Arc<RelNode>) -> (Vec<i64>, Vec<Arc<RelNode>>) {
+    let mut residual = conjuncts(pred);
+    let mut prefix = vec![];
+    for column in columns {
+        let Some((idx, value)) = residual
+            .iter()
+            .enumerate()
+            .find_map(|(idx, pred)| Some((idx, fixed_value(pred, *column)?)))
+        else {
+            break;
+        };
+        residual.remove(idx);
+        prefix.push(value);
+    }
+    (prefix, residual)
+}
+
+/// The left columns to look up the leading index `columns` of the right side with, given the
+/// equi-join keys of the join condition.
+pub fn index_lookup_keys(columns: &[usize], keys: &[(usize, usize)]) -> Vec<usize> {
+    columns
+        .iter()
+        .map_while(|column| keys.iter().find(|(_, right)| right == column))
+        .map(|(left, _)| *left)
+        .collect()
+}
+
+impl Optimizer<'_> {
+    fn scan_of(&self, group: GroupId) -> Option<Scan> {
+        self.memo
+            .get_all_exprs_in_group(group)
+            .into_iter()
+            .find_map(|expr| match expr {
+                MemoRelNode::Scan(scan) => Some(scan),
+                _ => None,
+            })
+    }
+
+    /// Read the whole table through each of its indexes, for the order of the rows.
+    pub fn full_index_scans(&mut self, scan: &Scan) -> Vec<MemoRelNode> {
+        self.stats
+            .indexes(&scan.table)
+            .into_iter()
+            .map(|index| {
+                MemoRelNode::IndexScan(IndexScan {
+                    table: scan.table.clone(),
+                    index: index.name,
+                    prefix: vec![],
+                })
+            })
+            .collect()
+    }
+
+    /// Range scans for a filter on a table, one per index whose leading columns the predicate
+    /// fixes. The remaining conjuncts are evaluated by a filter on top of the scan.
+    pub fn index_range_scans(&mut self, filter: &MemoFilter) -> Vec<MemoRelNode> {
+        let Some(scan) = self.scan_of(filter.child) else {
+            return vec![];
+        };
+        let pred = generate_one_binding(&self.memo, filter.predicate);
+        let mut res = vec![];
+        for index in self.stats.indexes(&scan.table) {
+            let (prefix, residual) = sargable_prefix(&index.columns, &pred);
+            if prefix.is_empty() {
+                continue;
+            }
+            let index_scan = MemoRelNode::IndexScan(IndexScan {
+                table: scan.table.clone(),
+                index: index.name,
+                prefix,
+            });
+            match fold_and(residual) {
+                None => res.push(index_scan),
+                Some(residual) => res.push(MemoRelNode::PhysicalFilter(MemoFilter {
+                    child: self.memo.add_expr(index_scan),
+                    predicate: memorize_rel(&mut self.memo, residual),
+                })),
+            }
+        }
+        res
+    }
+
+    /// Index nested-loop joins for a join with a table on the right side, one per index whose
+    /// leading columns are compared with left columns by the join condition.
+    pub fn index_nested_loop_joins(&mut self, join: &MemoJoin) -> Vec<MemoRelNode> {
+        let Some(scan) = self.scan_of(join.right) else {
+            return vec![];
+        };
+        let left_columns = self.logical_props(join.left).columns;
+        let cond = generate_one_binding(&self.memo, join.cond);
+        let keys = equi_join_keys(&cond, left_columns);
+        let mut res = vec![];
+        for index in self.stats.indexes(&scan.table) {
+            let lookup_keys = index_lookup_keys(&index.columns, &keys);
+            if lookup_keys.is_empty() {
+                continue;
+            }
+            let lookup = MemoRelNode::IndexLookup(IndexLookup {
+                table: scan.table.clone(),
+                index: index.name,
+                keys: lookup_keys,
+            });
+            res.push(MemoRelNode::IndexNestedLoopJoin(MemoJoin {
+                right: self.memo.add_expr(lookup),
+                ..join.clone()
+            }));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::customers_orders;
+
+    fn catalog() -> (Catalog, TableId, TableId) {
+        let (mut catalog, customers, orders) = customers_orders();
+        catalog
+            .add_index(&orders, "orders_customer", &["customer_id", "amount"])
+            .unwrap();
+        (catalog, customers, orders)
+    }
+
+    #[test]
+    fn test_sargable_prefix() {
+        let pred = Arc::new(and_pred(
+            and_pred(
+                eq_pred(column_ref_pred(2), const_pred(7)),
+                eq_pred(const_pred(3), column_ref_pred(1)),
+            ),
+            eq_pred(column_ref_pred(0), column_ref_pred(2)),
+        ));
+        let (prefix, residual) = sargable_prefix(&[1, 2], &pred);
+        assert_eq!(prefix, [3, 7]);
+        assert_eq!(
+            residual,
+            [Arc::new(eq_pred(column_ref_pred(0), column_ref_pred(2)))]
+        );
+        // only a prefix of the index columns can be used
+        let (prefix, residual) = sargable_prefix(&[0, 1], &pred);
+        assert!(prefix.is_empty());
+        assert_eq!(residual.len(), 3);
+
+        assert_eq!(index_lookup_keys(&[1, 2], &[(4, 1), (3, 0)]), [4]);
+        assert!(index_lookup_keys(&[2, 1], &[(4, 1)]).is_empty());
+    }
+
+    #[test]
+    fn test_index_selection() {
+        let (catalog, customers, orders) = catalog();
+        let optimize = |rel: RelNode| {
+            let mut optimizer = Optimizer::new(&catalog);
+            to_sexp(&optimizer.optimize(Arc::new(rel)).unwrap())
+        };
+        let col_eq = |column, value| eq_pred(column_ref_pred(column), const_pred(value));
+
+        // a point lookup on the primary key, with the rest of the predicate on top
+        let by_key = filter(scan(orders.clone()), and_pred(col_eq(0, 42), col_eq(2, 7)));
+        assert_eq!(
+            optimize(by_key),
+            "(physical_filter (index_scan 1 orders_pkey 42) (= #2 7))"
+        );
+        // a range scan on the leading columns of a secondary index
+        let by_customer = filter(scan(orders.clone()), and_pred(col_eq(2, 7), col_eq(1, 3)));
+        assert_eq!(optimize(by_customer), "(index_scan 1 orders_customer 3 7)");
+        // without a sargable predicate, the table is scanned
+        let by_amount = filter(scan(orders.clone()), col_eq(2, 7));
+        assert_eq!(
+            optimize(by_amount),
+            "(physical_filter (table_scan 1) (= #2 7))"
+        );
+
+        // an index scan delivers the rows in index order, a table scan would need a sort
+        let mut optimizer = Optimizer::new(&catalog);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(scan(orders.clone())));
+        optimizer.implement();
+        let asc = |column| SortKey {
+            column,
+            descending: false,
+        };
+        let best = optimizer
+            .best_plan(group, &PhysicalProps::sorted(vec![asc(1)]))
+            .unwrap();
+        assert_eq!(to_sexp(&best), "(index_scan 1 orders_customer)");
+        let best = optimizer
+            .best_plan(group, &PhysicalProps::default())
+            .unwrap();
+        assert_eq!(to_sexp(&best), "(table_scan 1)");
+
+        // a few orders look their customer up through the primary key
+        let cond = eq_pred(column_ref_pred(1), column_ref_pred(3));
+        let rel = Arc::new(join(
+            filter(scan(orders.clone()), col_eq(0, 42)),
+            scan(customers.clone()),
+            cond.clone(),
+        ));
+        let best = Optimizer::new(&catalog).optimize(rel.clone()).unwrap();
+        assert_eq!(
+            to_sexp(&best),
+            "(index_nested_loop_join (index_scan 1 orders_pkey 42) \
+             (index_lookup 0 customers_pkey #1) (= #1 #3))"
+        );
+        // the lookup finds the rows of the whole table
+        assert_eq!(
+            estimate_cardinality(&best, &catalog),
+            estimate_cardinality(&rel, &catalog)
+        );
+        // all orders are cheaper to join with a hash table
+        let rel = join(scan(orders), scan(customers), cond);
+        let best = Optimizer::new(&catalog).optimize(Arc::new(rel)).unwrap();
+        assert!(matches!(&*best, RelNode::HashJoin(_)));
+    }
+}
@@ -0,0 +1,424 @@
+use std::sync::Arc;
+
+use super::*;
+
+// Knowing which columns are unique lets the optimizer bound cardinalities and drop work that can't
+// change the result: a DISTINCT on a key is a projection, and a join with a side that has at most
+// one match per row can't multiply rows. Keys start at the primary and unique keys of the catalog,
+// and every operator derives its own from the ones of its inputs. Functional dependencies carry
+// what is lost on the way, e.g. after a join on `a = b`, a key of the right side that contains `b`
+// is as good as one with `a`, and a filter `c = 5` makes `c` constant.
+
+/// Two rows with the same values in `from` have the same values in `to`. An empty `from` means the
+/// `to` columns are constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionalDependency {
+    pub from: Vec<usize>,
+    pub to: Vec<usize>,
+}
+
+/// The unique keys and functional dependencies of the rows of a group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyProps {
+    /// Minimal column sets in which no two rows have the same values. The empty key means there
+    /// is at most one row.
+    pub keys: Vec<Vec<usize>>,
+    pub fds: Vec<FunctionalDependency>,
+}
+
+fn normalize(mut columns: Vec<usize>) -> Vec<usize> {
+    columns.sort();
+    columns.dedup();
+    columns
+}
+
+fn is_subset(columns: &[usize], of: &[usize]) -> bool {
+    columns.iter().all(|column| of.contains(column))
+}
+
+impl KeyProps {
+    pub fn add_key(&mut self, key: Vec<usize>) {
+        let key = normalize(key);
+        if self.keys.iter().any(|other| is_subset(other, &key)) {
+            return;
+        }
+        self.keys.retain(|other| !is_subset(&key, other));
+        self.keys.push(key);
+    }
+
+    pub fn add_fd(&mut self, from: Vec<usize>, to: Vec<usize>) {
+        let from = normalize(from);
+        let to = normalize(to)
+            .into_iter()
+            .filter(|column| !from.contains(column))
+            .collect::<Vec<_>>();
+        let fd = FunctionalDependency { from, to };
+        if !fd.to.is_empty() && !self.fds.contains(&fd) {
+            self.fds.push(fd);
+        }
+    }
+
+    /// Add the dependencies implied by the `=` conjuncts of a predicate that holds for all rows.
+    pub fn add_pred(&mut self, pred: &Arc<RelNode>) {
+        for pred in conjuncts(pred) {
+            let RelNode::Eq(eq) = &*pred else {
+                continue;
+            };
+            match (&*eq.left, &*eq.right) {
+                (RelNode::ColumnRef(a), RelNode::ColumnRef(b)) => {
+                    self.add_fd(vec![a.column], vec![b.column]);
+                    self.add_fd(vec![b.column], vec![a.column]);
+                }
+                (RelNode::ColumnRef(column_ref), RelNode::Const(_))
+                | (RelNode::Const(_), RelNode::ColumnRef(column_ref)) => {
+                    self.add_fd(vec![], vec![column_ref.column])
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The columns determined by `columns`, including themselves.
+    pub fn closure(&self, columns: &[usize]) -> Vec<usize> {
+        let mut closure = normalize(columns.to_vec());
+        loop {
+            let num_columns = closure.len();
+            for fd in &self.fds {
+                if is_subset(&fd.from, &closure) {
+                    closure.extend(&fd.to);
+                }
+            }
+            closure = normalize(closure);
+            if closure.len() == num_columns {
+                return closure;
+            }
+        }
+    }
+
+    /// Whether no two rows have the same values in `columns`.
+    pub fn is_unique(&self, columns: &[usize]) -> bool {
+        let closure = self.closure(columns);
+        self.keys.iter().any(|key| is_subset(key, &closure))
+    }
+
+    pub fn at_most_one_row(&self) -> bool {
+        self.is_unique(&[])
+    }
+
+    /// Whether two rows with the same values in `from` have the same values in `to`.
+    pub fn determines(&self, from: &[usize], to: &[usize]) -> bool {
+        self.is_unique(from) || is_subset(to, &self.closure(from))
+    }
+
+    /// Renumber the columns, and drop the ones `map` returns `None` for. Dependencies through
+    /// dropped columns are kept.
+    pub fn map_columns(&self, map: impl Fn(usize) -> Option<usize>) -> KeyProps {
+        let map_all = |columns: &[usize]| {
+            columns
+                .iter()
+                .map(|column| map(*column))
+                .collect::<Option<Vec<_>>>()
+        };
+        let mut res = KeyProps::default();
+        for key in &self.keys {
+            // replace each dropped column by columns that determine it
+            let determinants = key
+                .iter()
+                .map(|column| match map(*column) {
+                    Some(_) => Some(vec![*column]),
+                    None => self.fds.iter().find_map(|fd| {
+                        map_all(&fd.from)?;
+                        self.closure(&fd.from)
+                            .contains(column)
+                            .then(|| fd.from.clone())
+                    }),
+                })
+                .collect::<Option<Vec<_>>>();
+            if let Some(key) = determinants.and_then(|columns| map_all(&columns.concat())) {
+                res.add_key(key);
+            }
+        }
+        for fd in &self.fds {
+            let Some(from) = map_all(&fd.from) else {
+                continue;
+            };
+            let to = self
+                .closure(&fd.from)
+                .into_iter()
+                .filter_map(&map)
+                .collect();
+            res.add_fd(from, to);
+        }
+        res
+    }
+}
+
+impl Optimizer<'_> {
+    /// The keys and dependencies of the output of an expression, from the ones of its inputs.
+    pub fn derive_keys(&mut self, expr: &MemoRelNode) -> KeyProps {
+        let table_keys = |stats: &dyn TableStats, table: &TableId| {
+            let mut keys = KeyProps::default();
+            for key in stats.keys(table) {
+                keys.add_key(key);
+            }
+            keys
+        };
+        match expr {
+            MemoRelNode::Scan(scan) | MemoRelNode::TableScan(scan) => {
+                table_keys(self.stats, &scan.table)
+            }
+            MemoRelNode::IndexScan(scan) => {
+                let mut keys = table_keys(self.stats, &scan.table);
+                let columns = index_columns(self.stats, &scan.table, &scan.index);
+                keys.add_fd(vec![], columns[..scan.prefix.len()].to_vec());
+                keys
+            }
+            MemoRelNode::IndexLookup(lookup) => table_keys(self.stats, &lookup.table),
+            MemoRelNode::Filter(filter) | MemoRelNode::PhysicalFilter(filter) => {
+                let mut keys = self.logical_props(filter.child).keys;
+                keys.add_pred(&generate_one_binding(&self.memo, filter.predicate));
+                keys
+            }
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join) => self.join_keys(join),
+            MemoRelNode::Project(project) => {
+                let outputs = project
+                    .exprs
+                    .iter()
+                    .map(|expr| match &*generate_one_binding(&self.memo, *expr) {
+                        RelNode::ColumnRef(column_ref) => Some(column_ref.column),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let child = self.logical_props(project.child).keys;
+                child.map_columns(|column| outputs.iter().position(|c| *c == Some(column)))
+            }
+            MemoRelNode::Sort(sort) => self.logical_props(sort.child).keys,
+            MemoRelNode::Aggregate(aggregate) => {
+                let group_by = &aggregate.group_by;
+                let child = self.logical_props(aggregate.child).keys;
+                let mut keys =
+                    child.map_columns(|column| group_by.iter().position(|c| *c == column));
+                keys.add_key((0..group_by.len()).collect());
+                keys
+            }
+            MemoRelNode::Limit(limit) => {
+                let mut keys = self.logical_props(limit.child).keys;
+                if limit.limit <= 1 {
+                    keys.add_key(vec![]);
+                }
+                keys
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether each row of the left side matches at most one row of the right side, and the other
+    /// way around, because that side is unique on its equi-join columns.
+    pub fn unique_join_sides(&mut self, join: &MemoJoin) -> (bool, bool) {
+        let (left, right) = self.join_input_props(join);
+        let cond = generate_one_binding(&self.memo, join.cond);
+        let pairs = equi_join_keys(&cond, left.columns);
+        let left_columns = pairs.iter().map(|(left, _)| *left).collect::<Vec<_>>();
+        let right_columns = pairs.iter().map(|(_, right)| *right).collect::<Vec<_>>();
+        (
+            right.keys.is_unique(&right_columns),
+            left.keys.is_unique(&left_columns),
+        )
+    }
+
+    fn join_keys(&mut self, join: &MemoJoin) -> KeyProps {
+        let (left, right) = self.join_input_props(join);
+        if join.join_type != JoinType::Inner {
+            return left.keys;
+        }
+        let offset = left.columns;
+        let right_keys = right.keys.map_columns(|column| Some(column + offset));
+        let mut keys = KeyProps::default();
+        for fd in left.keys.fds.iter().chain(&right_keys.fds) {
+            keys.add_fd(fd.from.clone(), fd.to.clone());
+        }
+        // a key still determines the columns of its side
+        for key in &left.keys.keys {
+            keys.add_fd(key.clone(), (0..offset).collect());
+        }
+        for key in &right_keys.keys {
+            keys.add_fd(key.clone(), (offset..offset + right.columns).collect());
+        }
+        keys.add_pred(&generate_one_binding(&self.memo, join.cond));
+        let (left_matches_one, right_matches_one) = self.unique_join_sides(join);
+        if left_matches_one {
+            for key in &left.keys.keys {
+                keys.add_key(key.clone());
+            }
+        }
+        if right_matches_one {
+            for key in &right_keys.keys {
+                keys.add_key(key.clone());
+            }
+        }
+        for left_key in &left.keys.keys {
+            for right_key in &right_keys.keys {
+                keys.add_key([&left_key[..], &right_key[..]].concat());
+            }
+        }
+        keys
+    }
+
+    /// Tighten the estimated cardinality of an expression with what its keys guarantee.
+    pub fn bound_cardinality(&mut self, expr: &MemoRelNode, keys: &KeyProps, estimate: f64) -> f64 {
+        let mut cardinality = estimate;
+        match expr {
+            MemoRelNode::Join(join)
+            | MemoRelNode::HashJoin(join)
+            | MemoRelNode::NestedLoopJoin(join)
+            | MemoRelNode::SortMergeJoin(join)
+            | MemoRelNode::IndexNestedLoopJoin(join)
+                if join.join_type == JoinType::Inner =>
+            {
+                let (left, right) = self.join_input_props(join);
+                let (left_matches_one, right_matches_one) = self.unique_join_sides(join);
+                if left_matches_one {
+                    cardinality = cardinality.min(left.cardinality);
+                }
+                if right_matches_one {
+                    cardinality = cardinality.min(right.cardinality);
+                }
+            }
+            // every row is a group of its own
+            MemoRelNode::Aggregate(aggregate) => {
+                let child = self.logical_props(aggregate.child);
+                if child.keys.is_unique(&aggregate.group_by) {
+                    cardinality = child.cardinality;
+                }
+            }
+            _ => {}
+        }
+        if keys.at_most_one_row() {
+            cardinality = cardinality.min(1.0);
+        }
+        cardinality
+    }
+
+    /// A DISTINCT (an aggregate without aggregate functions) on columns that are already unique
+    /// only projects them.
+    pub fn distinct_eliminations(&mut self, aggregate: &MemoAggregate) -> Vec<MemoRelNode> {
+        let child = self.logical_props(aggregate.child);
+        if !aggregate.aggs.is_empty() || !child.keys.is_unique(&aggregate.group_by) {
+            return vec![];
+        }
+        let exprs = aggregate
+            .group_by
+            .iter()
+            .map(|column| memorize_rel(&mut self.memo, Arc::new(column_ref_pred(*column))))
+            .collect();
+        vec![MemoRelNode::Project(MemoProject {
+            child: aggregate.child,
+            exprs,
+        })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::customers_orders;
+
+    #[test]
+    fn test_key_props() {
+        let mut keys = KeyProps::default();
+        keys.add_key(vec![2, 0]);
+        keys.add_key(vec![0, 1, 2]);
+        assert_eq!(keys.keys, [vec![0, 2]]);
+        keys.add_fd(vec![1], vec![2]);
+        assert!(keys.is_unique(&[1, 0]));
+        assert!(!keys.is_unique(&[1, 2]));
+        assert!(keys.determines(&[0, 1], &[3]));
+        assert!(!keys.determines(&[1], &[0]));
+
+        // 1 -> 2 survives dropping 2 as part of the key
+        let mapped = keys.map_columns(|column| (column != 2).then_some(column));
+        assert!(mapped.is_unique(&[0, 1]));
+        assert!(!mapped.is_unique(&[0]));
+
+        keys.add_pred(&Arc::new(eq_pred(column_ref_pred(0), const_pred(1))));
+        assert!(!keys.at_most_one_row());
+        keys.add_pred(&Arc::new(eq_pred(const_pred(7), column_ref_pred(1))));
+        assert!(keys.at_most_one_row());
+    }
+
+    #[test]
+    fn test_derive_keys() {
+        let (mut catalog, customers, orders) = customers_orders();
+
+        // each order has at most one customer, so the orders stay unique and don't multiply
+        let rel = join(
+            scan(orders.clone()),
+            scan(customers),
+            eq_pred(column_ref_pred(1), column_ref_pred(3)),
+        );
+        let mut optimizer = Optimizer::new(&catalog);
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(rel));
+        let props = optimizer.logical_props(group);
+        assert_eq!(props.keys.keys, [vec![0]]);
+        assert!(props.keys.determines(&[1], &[3, 4]));
+        assert_eq!(props.cardinality, 10000.0);
+
+        // the group-by columns are a key of an aggregate
+        let rel = aggregate(
+            scan(orders.clone()),
+            vec![2, 1],
+            vec![AggCall {
+                func: AggFunc::Count,
+                column: None,
+            }],
+        );
+        let group = memorize_rel(&mut optimizer.memo, Arc::new(rel));
+        assert_eq!(optimizer.logical_props(group).keys.keys, [vec![0, 1]]);
+
+        // a DISTINCT on the primary key is a projection
+        let rel = aggregate(
+            project(
+                scan(orders),
+                vec![Arc::new(column_ref_pred(2)), Arc::new(column_ref_pred(0))],
+            ),
+            vec![1, 0],
+            vec![],
+        );
+        let mut optimizer = Optimizer::new(&catalog);
+        let best = optimizer.optimize(Arc::new(rel)).unwrap();
+        assert_eq!(
+            to_sexp(&best),
+            "(project (project (table_scan 1) #2 #0) #1 #0)"
+        );
+
+        // a unique column that may be NULL is not a key: a DISTINCT returns one row for all NULLs
+        let codes = catalog
+            .add_table(
+                "codes",
+                vec![
+                    ColumnDef::new("id", DataType::Int).not_null(),
+                    ColumnDef::new("code", DataType::Int),
+                ],
+                3.0,
+            )
+            .unwrap();
+        catalog.add_unique_key(&codes, &["code"]).unwrap();
+        let mut db = Database::from_catalog(&catalog);
+        db.insert(
+            &codes,
+            vec![
+                vec![Some(1), None],
+                vec![Some(2), None],
+                vec![Some(3), Some(7)],
+            ],
+        );
+        let rel = Arc::new(aggregate(scan(codes), vec![1], vec![]));
+        let best = Optimizer::new(&catalog).optimize(rel.clone()).unwrap();
+        assert_eq!(execute(&rel, &db).len(), 2);
+        assert!(check_equivalence(&rel, [best], &db).is_empty());
+    }
+}
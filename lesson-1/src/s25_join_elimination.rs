@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use super::*;
+
+// Star-schema queries join the fact table with every dimension table, whether or not the query
+// uses the columns of the dimension. An inner join on a NOT NULL foreign key to the key of the
+// referenced table finds exactly one match for every row, so if nothing above the join uses the
+// columns of the referenced table, the join neither adds nor removes rows and can be dropped.
+//
+// The rewrite walks the plan top-down with the set of columns each node has to produce. Removing
+// a join renumbers the columns of its output, so every node returns how its columns moved, and
+// the parent rewrites its column references.
+
+/// Where a column of a plan comes from: the position of the scan among the scans of the plan,
+/// the table and the column of the table. Two columns with the same scan come from the same row.
+type ColumnOrigin = (usize, TableId, usize);
+
+fn count_scans(rel: &RelNode) -> usize {
+    match rel {
+        RelNode::Scan(_) => 1,
+        _ => rel_inputs(rel).iter().map(|input| count_scans(input)).sum(),
+    }
+}
+
+/// Trace a column through the operators that pass it through unchanged.
+fn column_origin(rel: &RelNode, column: usize, catalog: &Catalog) -> Option<ColumnOrigin> {
+    match rel {
+        RelNode::Scan(scan) => Some((0, scan.table.clone(), column)),
+        RelNode::Filter(filter) => column_origin(&filter.child, column, catalog),
+        RelNode::Sort(sort) => column_origin(&sort.child, column, catalog),
+        RelNode::Limit(limit) => column_origin(&limit.child, column, catalog),
+        RelNode::Project(project) => match &*project.exprs[column] {
+            RelNode::ColumnRef(column_ref) => {
+                column_origin(&project.child, column_ref.column, catalog)
+            }
+            _ => None,
+        },
+        RelNode::Aggregate(aggregate) => {
+            let column = *aggregate.group_by.get(column)?;
+            column_origin(&aggregate.child, column, catalog)
+        }
+        RelNode::Join(join) => {
+            let left_columns = column_count(&join.left, catalog);
+            if column < left_columns {
+                return column_origin(&join.left, column, catalog);
+            }
+            let (scan, table, column) = column_origin(&join.right, column - left_columns, catalog)?;
+            Some((scan + count_scans(&join.left), table, column))
+        }
+        _ => None,
+    }
+}
+
+/// Whether every row of `rel` has exactly one match in `table` under `keys`, pairs of (column of
+/// `rel`, column of `table`): the pairs are a NOT NULL foreign key of a table scanned by `rel`.
+fn is_foreign_key_join(
+    rel: &RelNode,
+    table: &TableId,
+    keys: &[(usize, usize)],
+    catalog: &Catalog,
+) -> bool {
+    let mut origins = vec![];
+    for (column, referenced_column) in keys {
+        let Some(origin) = column_origin(rel, *column, catalog) else {
+            return false;
+        };
+        origins.push((origin, *referenced_column));
+    }
+    let Some(((scan, fact_table, _), _)) = origins.first().cloned() else {
+        return false;
+    };
+    if origins.iter().any(|((other, _, _), _)| *other != scan) {
+        return false;
+    }
+    let def = catalog.table(&fact_table);
+    let pairs = origins
+        .iter()
+        .map(|((_, _, column), referenced_column)| (*column, *referenced_column))
+        .collect::<Vec<_>>();
+    def.foreign_keys.iter().any(|fk| {
+        let fk_pairs = fk
+            .columns
+            .iter()
+            .copied()
+            .zip(fk.referenced_columns.iter().copied())
+            .collect::<Vec<_>>();
+        fk.referenced_table == *table
+            && fk
+                .columns
+                .iter()
+                .all(|column| def.columns[*column].not_null)
+            && fk_pairs.iter().all(|pair| pairs.contains(pair))
+            && pairs.iter().all(|pair| fk_pairs.contains(pair))
+    })
+}
+
+/// Where each output column of a node went after the rewrite, `None` for removed columns.
+type ColumnMap = Vec<Option<usize>>;
+
+fn identity(columns: usize) -> ColumnMap {
+    (0..columns).map(Some).collect()
+}
+
+fn remap(pred: &Arc<RelNode>, map: &ColumnMap) -> Arc<RelNode> {
+    map_column_refs(pred.clone(), &|column| {
+        map[column].expect("a removed column is still referenced")
+    })
+}
+
+fn eliminate(
+    rel: &Arc<RelNode>,
+    required: &[usize],
+    catalog: &Catalog,
+) -> (Arc<RelNode>, ColumnMap) {
+    let with_child = |child: &Arc<RelNode>, required: &[usize]| {
+        let mut required = required.to_vec();
+        required.sort();
+        required.dedup();
+        eliminate(child, &required, catalog)
+    };
+    match &**rel {
+        RelNode::Filter(filter) => {
+            let required = [required, &column_refs(&filter.predicate)].concat();
+            let (child, map) = with_child(&filter.child, &required);
+            let predicate = remap(&filter.predicate, &map);
+            (Arc::new(RelNode::Filter(Filter { child, predicate })), map)
+        }
+        RelNode::Project(project) => {
+            let required = project
+                .exprs
+                .iter()
+                .flat_map(|expr| column_refs(expr))
+                .collect::<Vec<_>>();
+            let (child, map) = with_child(&project.child, &required);
+            let exprs = project.exprs.iter().map(|expr| remap(expr, &map)).collect();
+            let columns = project.exprs.len();
+            (
+                Arc::new(RelNode::Project(Project { child, exprs })),
+                identity(columns),
+            )
+        }
+        RelNode::Sort(sort) => {
+            let keys = sort.keys.iter().map(|key| key.column).collect::<Vec<_>>();
+            let (child, map) = with_child(&sort.child, &[required, &keys].concat());
+            let keys = sort
+                .keys
+                .iter()
+                .map(|key| SortKey {
+                    column: map[key.column].unwrap(),
+                    descending: key.descending,
+                })
+                .collect();
+            (Arc::new(RelNode::Sort(Sort { child, keys })), map)
+        }
+        RelNode::Aggregate(aggregate) => {
+            let mut required = aggregate.group_by.clone();
+            required.extend(aggregate.aggs.iter().filter_map(|agg| agg.column));
+            let (child, map) = with_child(&aggregate.child, &required);
+            let rel = RelNode::Aggregate(Aggregate {
+                child,
+                group_by: aggregate
+                    .group_by
+                    .iter()
+                    .map(|column| map[*column].unwrap())
+                    .collect(),
+                aggs: aggregate
+                    .aggs
+                    .iter()
+                    .map(|agg| AggCall {
+                        func: agg.func,
+                        column: agg.column.map(|column| map[column].unwrap()),
+                    })
+                    .collect(),
+            });
+            let columns = aggregate.group_by.len() + aggregate.aggs.len();
+            (Arc::new(rel), identity(columns))
+        }
+        RelNode::Limit(limit) => {
+            let (child, map) = with_child(&limit.child, required);
+            let rel = RelNode::Limit(Limit {
+                child,
+                limit: limit.limit,
+            });
+            (Arc::new(rel), map)
+        }
+        RelNode::Join(join) => {
+            let left_columns = column_count(&join.left, catalog);
+            let right_columns = column_count(&join.right, catalog);
+            let used = [required, &column_refs(&join.cond)].concat();
+            let left_required = used
+                .iter()
+                .filter(|column| **column < left_columns)
+                .copied()
+                .collect::<Vec<_>>();
+            let right_required = used
+                .iter()
+                .filter(|column| **column >= left_columns)
+                .map(|column| column - left_columns)
+                .collect::<Vec<_>>();
+            let (left, left_map) = with_child(&join.left, &left_required);
+            let (right, right_map) = with_child(&join.right, &right_required);
+            let offset = column_count(&left, catalog);
+            let mut map = left_map.clone();
+            map.extend(right_map.iter().map(|column| column.map(|c| c + offset)));
+            let cond = remap(&join.cond, &map);
+            let rel = Arc::new(RelNode::Join(Join {
+                join_type: join.join_type,
+                left: left.clone(),
+                right: right.clone(),
+                cond: cond.clone(),
+            }));
+            if join.join_type != JoinType::Inner {
+                map.truncate(left_columns);
+                return (rel, map);
+            }
+            let keys = equi_join_keys(&cond, offset);
+            // the condition must only compare the foreign key with the referenced key
+            if keys.len() != conjuncts(&cond).len() {
+                return (rel, map);
+            }
+            let uses_left = required.iter().any(|column| *column < left_columns);
+            let uses_right = required.iter().any(|column| *column >= left_columns);
+            if let RelNode::Scan(scan) = &*right {
+                if !uses_right && is_foreign_key_join(&left, &scan.table, &keys, catalog) {
+                    let mut map = left_map;
+                    map.extend(vec![None; right_columns]);
+                    return (left, map);
+                }
+            }
+            if let RelNode::Scan(scan) = &*left {
+                let keys = keys
+                    .iter()
+                    .map(|(left, right)| (*right, *left))
+                    .collect::<Vec<_>>();
+                if !uses_left && is_foreign_key_join(&right, &scan.table, &keys, catalog) {
+                    let mut map = vec![None; left_columns];
+                    map.extend(right_map);
+                    return (right, map);
+                }
+            }
+            (rel, map)
+        }
+        _ => (rel.clone(), identity(column_count(rel, catalog))),
+    }
+}
+
+/// Remove the inner joins with tables that only check that a foreign key has a match. Works on
+/// logical plans.
+pub fn eliminate_joins(rel: Arc<RelNode>, catalog: &Catalog) -> Arc<RelNode> {
+    let columns = (0..column_count(&rel, catalog)).collect::<Vec<_>>();
+    eliminate(&rel, &columns, catalog).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_elimination() {
+        let mut catalog = Catalog::new();
+        let int = |name| ColumnDef::new(name, DataType::Int);
+        let products = catalog
+            .add_table("products", vec![int("id"), int("name")], 100.0)
+            .unwrap();
+        let stores = catalog
+            .add_table("stores", vec![int("id"), int("city")], 10.0)
+            .unwrap();
+        let sales = catalog
+            .add_table(
+                "sales",
+                vec![
+                    int("id"),
+                    int("product_id").not_null(),
+                    int("store_id").not_null(),
+                    int("promotion_id"),
+                    int("amount"),
+                ],
+                100000.0,
+            )
+            .unwrap();
+        catalog.add_primary_key(&products, &["id"]).unwrap();
+        catalog.add_primary_key(&stores, &["id"]).unwrap();
+        catalog
+            .add_foreign_key(&sales, &["product_id"], &products, &["id"])
+            .unwrap();
+        catalog
+            .add_foreign_key(&sales, &["store_id"], &stores, &["id"])
+            .unwrap();
+        catalog
+            .add_foreign_key(&sales, &["promotion_id"], &products, &["id"])
+            .unwrap();
+        let eliminate = |sql| {
+            let rel = sql_to_rel(sql, &catalog).unwrap();
+            to_sexp(&eliminate_joins(Arc::new(rel), &catalog))
+        };
+
+        // products isn't used, stores is filtered on
+        assert_eq!(
+            eliminate(
+                "SELECT amount FROM sales \
+                 JOIN products ON product_id = products.id \
+                 JOIN stores ON store_id = stores.id WHERE city = 3"
+            ),
+            "(project (filter (join (scan 2) (scan 1) (= #2 #5)) (= #6 3)) #4)"
+        );
+        // the referenced table can be on either side, and the columns above are renumbered
+        assert_eq!(
+            eliminate(
+                "SELECT store_id, SUM(amount) FROM products \
+                 JOIN sales ON products.id = product_id GROUP BY store_id"
+            ),
+            "(aggregate (scan 2) (#2) (sum #4))"
+        );
+
+        // a column of products is used
+        let sql = "SELECT name FROM sales JOIN products ON product_id = products.id";
+        assert_eq!(eliminate(sql), to_sexp(&sql_to_rel(sql, &catalog).unwrap()));
+        // sales with a NULL promotion have no match
+        let sql = "SELECT amount FROM sales JOIN products ON promotion_id = products.id";
+        assert_eq!(eliminate(sql), to_sexp(&sql_to_rel(sql, &catalog).unwrap()));
+        // not a foreign key
+        let sql = "SELECT amount FROM sales JOIN stores ON product_id = stores.id";
+        assert_eq!(eliminate(sql), to_sexp(&sql_to_rel(sql, &catalog).unwrap()));
+    }
+}
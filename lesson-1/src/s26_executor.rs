@@ -0,0 +1,473 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::*;
+
+// A rewrite rule is only correct if the plan it produces returns the same rows. To check that, we
+// run plans on small in-memory tables. The executor is a volcano-style iterator tree: every
+// operator pulls rows from its children one at a time, and blocking operators (sort, aggregate,
+// the build side of a join) collect their input first. It aims to be obviously right rather than
+// fast: `SortMergeJoin` is evaluated like a nested-loop join, and index accesses search the
+// table.
+//
+// Values are integers or NULL. Predicates evaluate to 1 (true), 0 (false) or NULL (unknown), and
+// a filter or a join only keeps rows for which the predicate is true.
+
+pub type Value = Option<i64>;
+pub type Row = Vec<Value>;
+
+/// The rows of every table, and the columns of their indexes.
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    tables: HashMap<TableId, Vec<Row>>,
+    indexes: HashMap<(TableId, String), Vec<usize>>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty database with the indexes of the catalog.
+    pub fn from_catalog(catalog: &Catalog) -> Self {
+        let mut db = Self::new();
+        for table in catalog.tables() {
+            db.tables.insert(table.id.clone(), vec![]);
+            for index in &table.indexes {
+                db.add_index(&table.id, &index.name, index.columns.clone());
+            }
+        }
+        db
+    }
+
+    pub fn add_index(&mut self, table: &TableId, name: &str, columns: Vec<usize>) {
+        self.indexes
+            .insert((table.clone(), name.to_string()), columns);
+    }
+
+    /// Append rows to the table.
+    pub fn insert(&mut self, table: &TableId, rows: Vec<Row>) {
+        self.tables.entry(table.clone()).or_default().extend(rows);
+    }
+
+    /// Append the rows of a CSV text to the table, see `parse_csv`.
+    pub fn load_csv(&mut self, table: &TableId, text: &str) -> Result<(), ParseError> {
+        self.insert(table, parse_csv(text)?);
+        Ok(())
+    }
+
+    pub fn rows(&self, table: &TableId) -> &[Row] {
+        self.tables
+            .get(table)
+            .unwrap_or_else(|| panic!("no rows for table {table:?}"))
+    }
+
+    fn index_columns(&self, table: &TableId, index: &str) -> &[usize] {
+        self.indexes
+            .get(&(table.clone(), index.to_string()))
+            .unwrap_or_else(|| panic!("table {table:?} has no index `{index}`"))
+    }
+
+    /// The rows of the table whose leading index columns are equal to `prefix`, in index order.
+    fn index_rows(&self, table: &TableId, index: &str, prefix: &[Value]) -> Vec<Row> {
+        let columns = self.index_columns(table, index);
+        let mut rows = self
+            .rows(table)
+            .iter()
+            .filter(|row| {
+                columns
+                    .iter()
+                    .zip(prefix)
+                    .all(|(column, value)| value.is_some() && row[*column] == *value)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| {
+            columns
+                .iter()
+                .map(|column| row[*column])
+                .collect::<Vec<_>>()
+        });
+        rows
+    }
+}
+
+/// Parse one row per line, with values separated by commas. An empty value is NULL, and empty
+/// lines are skipped. All rows must have the same number of values.
+pub fn parse_csv(text: &str) -> Result<Vec<Row>, ParseError> {
+    let mut rows: Vec<Row> = vec![];
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let offset = line_start;
+        line_start += line.len() + 1;
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut row = vec![];
+        let mut field_start = offset;
+        for field in line.split(',') {
+            let value = field.trim();
+            let value_start = field_start + field.len() - field.trim_start().len();
+            field_start += field.len() + 1;
+            if value.is_empty() {
+                row.push(None);
+                continue;
+            }
+            let value = value.parse().map_err(|_| {
+                ParseError::at(
+                    text,
+                    value_start,
+                    format!("expected a number, found `{value}`"),
+                )
+            })?;
+            row.push(Some(value));
+        }
+        if let Some(first) = rows.first() {
+            if first.len() != row.len() {
+                let message = format!("expected {} values, found {}", first.len(), row.len());
+                return Err(ParseError::at(text, offset, message));
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn truth(value: bool) -> Value {
+    Some(value as i64)
+}
+
+fn is_true(value: Value) -> bool {
+    value.is_some_and(|value| value != 0)
+}
+
+/// Evaluate a scalar expression on a row.
+pub fn eval(expr: &RelNode, row: &Row) -> Value {
+    match expr {
+        RelNode::ColumnRef(column_ref) => row[column_ref.column],
+        RelNode::Const(const_pred) => Some(const_pred.value),
+        RelNode::Eq(eq) => truth(eval(&eq.left, row)? == eval(&eq.right, row)?),
+        RelNode::Add(add) => Some(eval(&add.left, row)?.wrapping_add(eval(&add.right, row)?)),
+        RelNode::And(and) => match (eval(&and.left, row), eval(&and.right, row)) {
+            (Some(0), _) | (_, Some(0)) => truth(false),
+            (Some(_), Some(_)) => truth(true),
+            _ => None,
+        },
+        RelNode::Or(or) => {
+            let (left, right) = (eval(&or.left, row), eval(&or.right, row));
+            if is_true(left) || is_true(right) {
+                truth(true)
+            } else if left.is_some() && right.is_some() {
+                truth(false)
+            } else {
+                None
+            }
+        }
+        _ => panic!("not a scalar expression: {expr:?}"),
+    }
+}
+
+/// The output rows of a join of `left` with the matching `right` rows.
+fn join_rows(join_type: JoinType, left: Row, matches: impl Iterator<Item = Row>) -> Vec<Row> {
+    let mut matches = matches.peekable();
+    match join_type {
+        JoinType::Inner => matches
+            .map(|right| [&left[..], &right[..]].concat())
+            .collect(),
+        JoinType::LeftSemi => matches
+            .peek()
+            .is_some()
+            .then_some(left)
+            .into_iter()
+            .collect(),
+        JoinType::LeftAnti => matches
+            .peek()
+            .is_none()
+            .then_some(left)
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn aggregate_rows(aggregate: &Aggregate, rows: impl Iterator<Item = Row>) -> Vec<Row> {
+    let mut groups: Vec<(Row, Vec<Row>)> = vec![];
+    let mut group_idx = HashMap::new();
+    for row in rows {
+        let key = aggregate
+            .group_by
+            .iter()
+            .map(|column| row[*column])
+            .collect::<Row>();
+        let idx = *group_idx.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, vec![]));
+            groups.len() - 1
+        });
+        groups[idx].1.push(row);
+    }
+    // without GROUP BY, there is one row even for no input
+    if aggregate.group_by.is_empty() && groups.is_empty() {
+        groups.push((vec![], vec![]));
+    }
+    groups
+        .into_iter()
+        .map(|(mut key, rows)| {
+            for agg in &aggregate.aggs {
+                let values = rows.iter().filter_map(|row| match agg.column {
+                    Some(column) => row[column],
+                    None => Some(1),
+                });
+                key.push(match agg.func {
+                    AggFunc::Count => Some(values.count() as i64),
+                    AggFunc::Sum => values.reduce(i64::wrapping_add),
+                    AggFunc::Min => values.min(),
+                    AggFunc::Max => values.max(),
+                });
+            }
+            key
+        })
+        .collect()
+}
+
+/// Open a plan as an iterator over its rows.
+pub fn open<'a>(rel: &'a RelNode, db: &'a Database) -> Box<dyn Iterator<Item = Row> + 'a> {
+    match rel {
+        RelNode::Scan(scan) | RelNode::TableScan(scan) => {
+            Box::new(db.rows(&scan.table).iter().cloned())
+        }
+        RelNode::IndexScan(scan) => {
+            let prefix = scan
+                .prefix
+                .iter()
+                .map(|value| Some(*value))
+                .collect::<Row>();
+            Box::new(db.index_rows(&scan.table, &scan.index, &prefix).into_iter())
+        }
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => {
+            Box::new(open(&filter.child, db).filter(|row| is_true(eval(&filter.predicate, row))))
+        }
+        RelNode::Join(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::HashJoin(join) => {
+            let right = open(&join.right, db).collect::<Vec<_>>();
+            Box::new(open(&join.left, db).flat_map(move |left| {
+                let matches = right
+                    .iter()
+                    .filter(|right| is_true(eval(&join.cond, &[&left[..], &right[..]].concat())))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                join_rows(join.join_type, left, matches.into_iter())
+            }))
+        }
+        RelNode::IndexNestedLoopJoin(join) => {
+            let RelNode::IndexLookup(lookup) = &*join.right else {
+                panic!("the right side of an index nested-loop join must be an index lookup");
+            };
+            Box::new(open(&join.left, db).flat_map(move |left| {
+                let keys = lookup.keys.iter().map(|key| left[*key]).collect::<Row>();
+                let matches = db
+                    .index_rows(&lookup.table, &lookup.index, &keys)
+                    .into_iter()
+                    .filter(|right| is_true(eval(&join.cond, &[&left[..], &right[..]].concat())))
+                    .collect::<Vec<_>>();
+                join_rows(join.join_type, left, matches.into_iter())
+            }))
+        }
+        RelNode::Project(project) => Box::new(
+            open(&project.child, db)
+                .map(|row| project.exprs.iter().map(|expr| eval(expr, &row)).collect()),
+        ),
+        RelNode::Sort(sort) => {
+            let mut rows = open(&sort.child, db).collect::<Vec<_>>();
+            rows.sort_by(|a, b| {
+                sort.keys
+                    .iter()
+                    .map(|key| {
+                        let order = a[key.column].cmp(&b[key.column]);
+                        if key.descending {
+                            order.reverse()
+                        } else {
+                            order
+                        }
+                    })
+                    .find(|order| order.is_ne())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            Box::new(rows.into_iter())
+        }
+        RelNode::Aggregate(aggregate) => {
+            Box::new(aggregate_rows(aggregate, open(&aggregate.child, db)).into_iter())
+        }
+        RelNode::Limit(limit) => Box::new(open(&limit.child, db).take(limit.limit)),
+        RelNode::IndexLookup(_) => {
+            panic!("an index lookup can only be the right side of an index nested-loop join")
+        }
+        scalar => panic!("not a relational expression: {scalar:?}"),
+    }
+}
+
+/// Run a plan to completion.
+pub fn execute(rel: &RelNode, db: &Database) -> Vec<Row> {
+    open(rel, db).collect()
+}
+
+/// A plan whose results differ from the results of the original plan.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub plan: Arc<RelNode>,
+    pub expected: Vec<Row>,
+    pub actual: Vec<Row>,
+}
+
+/// Rows in a canonical order, so that results can be compared as multisets.
+pub fn sorted_rows(mut rows: Vec<Row>) -> Vec<Row> {
+    rows.sort();
+    rows
+}
+
+/// Execute the original plan and each rewritten plan, and return the rewritten plans whose rows
+/// differ from the original ones as multisets.
+pub fn check_equivalence(
+    original: &RelNode,
+    rewritten: impl IntoIterator<Item = Arc<RelNode>>,
+    db: &Database,
+) -> Vec<Mismatch> {
+    let expected = sorted_rows(execute(original, db));
+    rewritten
+        .into_iter()
+        .filter_map(|plan| {
+            let actual = sorted_rows(execute(&plan, db));
+            (actual != expected).then(|| Mismatch {
+                plan,
+                expected: expected.clone(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// Check up to `limit` bindings of a memo group against the original plan of the group.
+pub fn check_bindings(
+    original: &RelNode,
+    memo: &Memo,
+    group: GroupId,
+    limit: usize,
+    db: &Database,
+) -> Vec<Mismatch> {
+    check_equivalence(original, bindings(memo, group).take(limit), db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    fn database() -> Database {
+        let mut db = Database::new();
+        db.load_csv(&TableId(0), "1, 10\n2, 20\n3,\n3, 30\n")
+            .unwrap();
+        db.insert(
+            &TableId(1),
+            vec![
+                vec![Some(10), Some(100)],
+                vec![Some(10), Some(101)],
+                vec![Some(30), Some(300)],
+                vec![None, Some(400)],
+            ],
+        );
+        db
+    }
+
+    #[test]
+    fn test_execute() {
+        let db = database();
+        let cond = eq_pred(column_ref_pred(1), column_ref_pred(2));
+        let rel = join(scan(TableId(0)), scan(TableId(1)), cond.clone());
+        assert_eq!(
+            execute(&rel, &db),
+            [
+                [Some(1), Some(10), Some(10), Some(100)],
+                [Some(1), Some(10), Some(10), Some(101)],
+                [Some(3), Some(30), Some(30), Some(300)],
+            ]
+        );
+        // NULL matches nothing, not even in an anti join
+        let rel = join_with_type(JoinType::LeftAnti, scan(TableId(0)), scan(TableId(1)), cond);
+        assert_eq!(execute(&rel, &db), [[Some(2), Some(20)], [Some(3), None]]);
+
+        let count = |column| AggCall {
+            func: AggFunc::Count,
+            column,
+        };
+        let rel = sort(
+            aggregate(scan(TableId(0)), vec![0], vec![count(None), count(Some(1))]),
+            vec![SortKey {
+                column: 0,
+                descending: true,
+            }],
+        );
+        assert_eq!(
+            execute(&rel, &db),
+            [
+                [Some(3), Some(2), Some(1)],
+                [Some(2), Some(1), Some(1)],
+                [Some(1), Some(1), Some(1)],
+            ]
+        );
+        let rel = aggregate(
+            filter(scan(TableId(0)), eq_pred(column_ref_pred(0), const_pred(9))),
+            vec![],
+            vec![count(None)],
+        );
+        assert_eq!(execute(&rel, &db), [[Some(0)]]);
+
+        let error = |text| parse_csv(text).unwrap_err().to_string();
+        assert_eq!(error("1, 2\n3, x\n"), "2:4: expected a number, found `x`");
+        assert_eq!(error("1, 2\n\n3\n"), "3:1: expected 2 values, found 1");
+    }
+
+    #[test]
+    fn test_equivalence() {
+        let stats = stats(2, [1000.0, 10.0]);
+        let db = database();
+        let rel = Arc::new(filter(
+            join(
+                scan(TableId(0)),
+                scan(TableId(1)),
+                eq_pred(column_ref_pred(1), column_ref_pred(2)),
+            ),
+            or_pred(
+                eq_pred(column_ref_pred(0), const_pred(1)),
+                eq_pred(column_ref_pred(0), const_pred(3)),
+            ),
+        ));
+
+        // the physical plan and the normalized plan return the same rows
+        let physical = Optimizer::new(&stats).optimize(rel.clone()).unwrap();
+        let normalized = normalize_plan(rel.clone(), DEFAULT_MAX_CNF_CLAUSES);
+        assert!(check_equivalence(&rel, [physical, normalized], &db).is_empty());
+
+        // every plan explored by the optimizer returns the same rows
+        let mut optimizer = Optimizer::new(&stats);
+        let group = memorize_rel(&mut optimizer.memo, rel.clone());
+        optimizer.explore();
+        assert_eq!(bindings(&optimizer.memo, group).count(), 2);
+        assert!(check_bindings(&rel, &optimizer.memo, group, 10, &db).is_empty());
+
+        // swapping the inputs without restoring the column order is caught
+        let swapped = Arc::new(filter(
+            join(
+                scan(TableId(1)),
+                scan(TableId(0)),
+                eq_pred(column_ref_pred(0), column_ref_pred(3)),
+            ),
+            or_pred(
+                eq_pred(column_ref_pred(2), const_pred(1)),
+                eq_pred(column_ref_pred(2), const_pred(3)),
+            ),
+        ));
+        let mismatches = check_equivalence(&rel, [swapped.clone()], &db);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].plan, swapped);
+    }
+}
@@ -0,0 +1,456 @@
+use std::{fmt, sync::Arc};
+
+use super::*;
+
+// Hand-written tests only cover the plans we thought of. Here every rewrite rule is checked on
+// random plans from the generator of s28 over random data: the rule is applied at each node of the
+// plan, and each rewritten plan must return the same rows as the original one. When a plan fails,
+// it is shrunk by removing operators and conjuncts for as long as it still fails, so that the
+// counterexample is small enough to read.
+
+/// A rewrite of a logical plan. It returns `None` if it doesn't apply to the root of the plan.
+#[derive(Clone, Copy)]
+pub struct RewriteRule {
+    pub name: &'static str,
+    pub apply: fn(Arc<RelNode>, &dyn TableStats) -> Option<Arc<RelNode>>,
+}
+
+impl fmt::Debug for RewriteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// `join_commute` of s02, with the condition remapped to the swapped inputs and a projection that
+/// restores the original column order.
+pub fn join_commute_remapped(node: Arc<RelNode>, stats: &dyn TableStats) -> Option<Arc<RelNode>> {
+    let RelNode::Join(a) = &*node else {
+        return None;
+    };
+    let left_columns = column_count(&a.left, stats);
+    let right_columns = column_count(&a.right, stats);
+    let swap = |column| {
+        if column < left_columns {
+            column + right_columns
+        } else {
+            column - left_columns
+        }
+    };
+    let RelNode::Join(swapped) = &*join_commute(node.clone())? else {
+        return None;
+    };
+    let swapped = RelNode::Join(Join {
+        cond: map_column_refs(swapped.cond.clone(), &swap),
+        ..swapped.clone()
+    });
+    let exprs = (0..left_columns + right_columns)
+        .map(|column| Arc::new(column_ref_pred(swap(column))))
+        .collect();
+    Some(Arc::new(project(swapped, exprs)))
+}
+
+/// `join_assoc` of s02, with the top condition shifted to the inputs of the new inner join. It
+/// doesn't apply if that condition refers to `A` in `(A join B) join C`.
+pub fn join_assoc_remapped(node: Arc<RelNode>, stats: &dyn TableStats) -> Option<Arc<RelNode>> {
+    let RelNode::Join(a) = &*node else {
+        return None;
+    };
+    let RelNode::Join(b) = &*a.left else {
+        return None;
+    };
+    let left_columns = column_count(&b.left, stats);
+    if column_refs(&a.cond)
+        .iter()
+        .any(|&column| column < left_columns)
+    {
+        return None;
+    }
+    let RelNode::Join(top) = &*join_assoc(node.clone())? else {
+        return None;
+    };
+    let RelNode::Join(inner) = &*top.right else {
+        return None;
+    };
+    let inner = RelNode::Join(Join {
+        cond: map_column_refs(inner.cond.clone(), &|column| column - left_columns),
+        ..inner.clone()
+    });
+    Some(Arc::new(RelNode::Join(Join {
+        right: Arc::new(inner),
+        ..top.clone()
+    })))
+}
+
+/// The rules that rewrite `RelNode` plans, and the optimizer entry points, which rewrite a logical
+/// plan into a physical one.
+pub fn rewrite_rules() -> Vec<RewriteRule> {
+    vec![
+        RewriteRule {
+            name: "join_commute",
+            apply: join_commute_remapped,
+        },
+        RewriteRule {
+            name: "join_assoc",
+            apply: join_assoc_remapped,
+        },
+        RewriteRule {
+            name: "normalize_plan",
+            apply: |rel, _| Some(normalize_plan(rel, DEFAULT_MAX_CNF_CLAUSES)),
+        },
+        RewriteRule {
+            name: "reorder_joins_dpccp",
+            apply: reorder_joins_dpccp,
+        },
+        RewriteRule {
+            name: "reorder_joins_dphyp",
+            apply: reorder_joins_dphyp,
+        },
+        RewriteRule {
+            name: "heuristic_plan",
+            apply: |rel, stats| Some(heuristic_plan(&rel, stats)),
+        },
+        RewriteRule {
+            name: "optimize",
+            apply: |rel, stats| Optimizer::new(stats).optimize(rel),
+        },
+        RewriteRule {
+            name: "optimize_with_budget",
+            apply: |rel, stats| Some(Optimizer::new(stats).optimize_with_budget(rel).plan),
+        },
+        RewriteRule {
+            name: "explore_parallel",
+            apply: |rel, stats| {
+                let mut optimizer = Optimizer::new(stats);
+                let group = memorize_rel(&mut optimizer.memo, rel);
+                optimizer.explore_parallel(4);
+                optimizer.implement();
+                optimizer.best_plan(group, &PhysicalProps::default())
+            },
+        },
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub struct PropertyTestConfig {
+    pub seed: u64,
+    /// Number of random plans to check.
+    pub cases: usize,
+    pub tables: usize,
+    pub columns_per_table: usize,
+    pub max_joins: usize,
+    pub max_rows: usize,
+    /// Values are drawn from `0..max_value`, so that joins find matches.
+    pub max_value: i64,
+}
+
+impl Default for PropertyTestConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            cases: 100,
+            tables: 4,
+            columns_per_table: 2,
+            max_joins: 3,
+            max_rows: 6,
+            max_value: 4,
+        }
+    }
+}
+
+fn is_scalar(rel: &RelNode) -> bool {
+    matches!(
+        rel,
+        RelNode::Eq(_)
+            | RelNode::And(_)
+            | RelNode::Or(_)
+            | RelNode::Add(_)
+            | RelNode::ColumnRef(_)
+            | RelNode::Const(_)
+    )
+}
+
+/// A catalog of `tables` tables with `columns_per_table` integer columns each.
+pub fn random_catalog(config: &PropertyTestConfig) -> Catalog {
+    let mut catalog = Catalog::new();
+    for table in 0..config.tables {
+        let columns = (0..config.columns_per_table)
+            .map(|column| ColumnDef::new(&format!("c{column}"), DataType::Int))
+            .collect();
+        catalog
+            .add_table(&format!("t{table}"), columns, config.max_rows as f64)
+            .unwrap();
+    }
+    catalog
+}
+
+/// A random plan of scans, filters and inner joins with up to `max_joins` joins.
+pub fn random_plan(rng: &mut Rng, config: &PropertyTestConfig, catalog: &Catalog) -> Arc<RelNode> {
+    let shapes = [
+        JoinGraphShape::Chain,
+        JoinGraphShape::Star,
+        JoinGraphShape::Cycle,
+        JoinGraphShape::Clique,
+    ];
+    let config = PlanGeneratorConfig {
+        seed: rng.next_u64(),
+        shape: shapes[rng.gen_range(shapes.len())],
+        relations: 1 + rng.gen_range(config.max_joins + 1),
+        filters: rng.gen_range(3),
+        max_value: config.max_value,
+        disjunctions: 0.5,
+        operators: OperatorMix::default(),
+    };
+    PlanGenerator::new(catalog, config).generate()
+}
+
+/// Random rows for every table, with about one NULL in ten values.
+pub fn random_database(rng: &mut Rng, config: &PropertyTestConfig, catalog: &Catalog) -> Database {
+    let mut db = Database::from_catalog(catalog);
+    for table in catalog.tables() {
+        let rows = (0..rng.gen_range(config.max_rows + 1))
+            .map(|_| {
+                (0..config.columns_per_table)
+                    .map(|_| match rng.gen_range(10) {
+                        0 => None,
+                        _ => Some(rng.gen_range(config.max_value as usize) as i64),
+                    })
+                    .collect()
+            })
+            .collect();
+        db.insert(&table.id, rows);
+    }
+    db
+}
+
+/// Whether every column reference of the plan is in range.
+fn is_well_formed(rel: &RelNode, stats: &dyn TableStats) -> bool {
+    let in_range = |pred: &RelNode, columns| column_refs(pred).iter().all(|c| *c < columns);
+    let valid = match rel {
+        RelNode::Filter(filter) | RelNode::PhysicalFilter(filter) => {
+            in_range(&filter.predicate, column_count(&filter.child, stats))
+        }
+        RelNode::Project(project) => {
+            let columns = column_count(&project.child, stats);
+            project.exprs.iter().all(|expr| in_range(expr, columns))
+        }
+        RelNode::Join(join)
+        | RelNode::HashJoin(join)
+        | RelNode::NestedLoopJoin(join)
+        | RelNode::SortMergeJoin(join)
+        | RelNode::IndexNestedLoopJoin(join) => in_range(
+            &join.cond,
+            column_count(&join.left, stats) + column_count(&join.right, stats),
+        ),
+        _ => true,
+    };
+    valid
+        && rel_inputs(rel)
+            .iter()
+            .all(|input| is_well_formed(input, stats))
+}
+
+/// The plans obtained by applying the rule at each node of the plan.
+pub fn rewrite_each_node(
+    rel: &Arc<RelNode>,
+    rule: &RewriteRule,
+    stats: &dyn TableStats,
+) -> Vec<Arc<RelNode>> {
+    if is_scalar(rel) {
+        return vec![];
+    }
+    let mut res = (rule.apply)(rel.clone(), stats)
+        .into_iter()
+        .collect::<Vec<_>>();
+    let children = rel.children();
+    for (idx, child) in children.iter().enumerate() {
+        for rewritten in rewrite_each_node(child, rule, stats) {
+            let mut children = children.clone();
+            children[idx] = rewritten;
+            res.push(Arc::new(rel.clone_with_children(children)));
+        }
+    }
+    res
+}
+
+/// Smaller plans: a node replaced by one of its inputs, or a predicate by one of its operands.
+fn shrink_candidates(rel: &Arc<RelNode>) -> Vec<Arc<RelNode>> {
+    let mut res = rel_inputs(rel);
+    if let RelNode::And(and) = &**rel {
+        res.extend([and.left.clone(), and.right.clone()]);
+    }
+    if let RelNode::Or(or) = &**rel {
+        res.extend([or.left.clone(), or.right.clone()]);
+    }
+    let children = rel.children();
+    for (idx, child) in children.iter().enumerate() {
+        for candidate in shrink_candidates(child) {
+            let mut children = children.clone();
+            children[idx] = candidate;
+            res.push(Arc::new(rel.clone_with_children(children)));
+        }
+    }
+    res
+}
+
+/// How a rewritten plan differs from the original plan.
+#[derive(Debug, Clone)]
+pub enum Failure {
+    /// The rewritten plan references a column that its input doesn't have.
+    InvalidPlan(Arc<RelNode>),
+    Mismatch(Mismatch),
+}
+
+/// A plan that the rule breaks when applied to one of its nodes.
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    pub rule: RewriteRule,
+    pub plan: Arc<RelNode>,
+    pub failure: Failure,
+}
+
+fn format_rows(rows: &[Row]) -> String {
+    let rows = rows
+        .iter()
+        .map(|row| {
+            let values = row
+                .iter()
+                .map(|value| value.map_or("NULL".to_string(), |value| value.to_string()))
+                .collect::<Vec<_>>();
+            format!("({})", values.join(", "))
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", rows.join(", "))
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "`{}` breaks", self.rule.name)?;
+        writeln!(f, "  {}", to_sexp(&self.plan))?;
+        match &self.failure {
+            Failure::InvalidPlan(plan) => {
+                writeln!(f, "rewritten to a plan with out-of-range columns")?;
+                write!(f, "  {}", to_sexp(plan))
+            }
+            Failure::Mismatch(mismatch) => {
+                writeln!(f, "rewritten to")?;
+                writeln!(f, "  {}", to_sexp(&mismatch.plan))?;
+                writeln!(f, "expected: {}", format_rows(&mismatch.expected))?;
+                write!(f, "actual:   {}", format_rows(&mismatch.actual))
+            }
+        }
+    }
+}
+
+fn find_failure(
+    rel: &Arc<RelNode>,
+    rule: &RewriteRule,
+    stats: &dyn TableStats,
+    db: &Database,
+) -> Option<Failure> {
+    let (valid, invalid): (Vec<_>, Vec<_>) = rewrite_each_node(rel, rule, stats)
+        .into_iter()
+        .partition(|rewritten| is_well_formed(rewritten, stats));
+    if let Some(rewritten) = invalid.into_iter().next() {
+        return Some(Failure::InvalidPlan(rewritten));
+    }
+    let mismatch = check_equivalence(rel, valid, db).into_iter().next()?;
+    Some(Failure::Mismatch(mismatch))
+}
+
+/// Shrink a failing plan until none of its smaller variants fails.
+pub fn shrink(
+    rel: Arc<RelNode>,
+    failure: Failure,
+    rule: &RewriteRule,
+    stats: &dyn TableStats,
+    db: &Database,
+) -> Counterexample {
+    let mut plan = rel;
+    let mut failure = failure;
+    while let Some((candidate, candidate_failure)) = shrink_candidates(&plan)
+        .into_iter()
+        .filter(|candidate| is_well_formed(candidate, stats))
+        .find_map(|candidate| {
+            let failure = find_failure(&candidate, rule, stats, db)?;
+            Some((candidate, failure))
+        })
+    {
+        plan = candidate;
+        failure = candidate_failure;
+    }
+    Counterexample {
+        rule: *rule,
+        plan,
+        failure,
+    }
+}
+
+/// Check the rule on random plans and data, and return the shrunk counterexample of the first
+/// failing plan.
+pub fn check_rule(rule: &RewriteRule, config: &PropertyTestConfig) -> Option<Counterexample> {
+    let mut rng = Rng::new(config.seed);
+    let catalog = random_catalog(config);
+    for _ in 0..config.cases {
+        let db = random_database(&mut rng, config, &catalog);
+        let rel = random_plan(&mut rng, config, &catalog);
+        if let Some(failure) = find_failure(&rel, rule, &catalog, &db) {
+            return Some(shrink(rel, failure, rule, &catalog, &db));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::stats;
+
+    #[test]
+    fn test_rewrite_rules() {
+        let config = PropertyTestConfig::default();
+        let counterexamples = rewrite_rules()
+            .iter()
+            .filter_map(|rule| check_rule(rule, &config))
+            .collect::<Vec<_>>();
+        let failing = counterexamples
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert!(failing.is_empty(), "{}", failing.join("\n"));
+
+        // the lesson's `join_commute` swaps the inputs without remapping the condition: that is
+        // caught, and shrunk to a join of two tables
+        let broken = RewriteRule {
+            name: "swap_inputs",
+            apply: |rel, _| join_commute(rel),
+        };
+        let counterexample = check_rule(&broken, &config).unwrap();
+        let mut scans = 0;
+        let mut stack = vec![counterexample.plan.clone()];
+        while let Some(rel) = stack.pop() {
+            scans += matches!(&*rel, RelNode::Scan(_)) as usize;
+            stack.extend(rel_inputs(&rel));
+        }
+        assert_eq!(scans, 2, "{counterexample}");
+        assert!(counterexample
+            .to_string()
+            .starts_with("`swap_inputs` breaks\n  (join (scan"));
+
+        let stats = stats(2, [10.0; 3]);
+        let rel = |sexp| Arc::new(parse_sexp(sexp).unwrap());
+        let commuted = join_commute_remapped(rel("(join (scan 0) (scan 1) (= #1 #3))"), &stats);
+        assert_eq!(
+            commuted.unwrap(),
+            rel("(project (join (scan 1) (scan 0) (= #3 #1)) #2 #3 #0 #1)")
+        );
+        let assoc = |sexp| join_assoc_remapped(rel(sexp), &stats);
+        assert_eq!(
+            assoc("(join (join (scan 0) (scan 1) (= #1 #3)) (scan 2) (= #2 #5))").unwrap(),
+            rel("(join (scan 0) (join (scan 1) (scan 2) (= #0 #3)) (= #1 #3))")
+        );
+        assert_eq!(
+            assoc("(join (join (scan 0) (scan 1) (= #1 #3)) (scan 2) (= #0 #5))"),
+            None
+        );
+    }
+}
@@ -0,0 +1,566 @@
+use std::sync::Arc;
+
+use super::*;
+
+// Fuzzing and benchmarking need many plans with a known structure. The generator picks tables
+// from a catalog, connects them with join predicates following a join graph shape, adds filters
+// on single tables and then some operators on top of the joins. Predicates only compare columns
+// of the same type, and every join of the tree has at least one predicate, so the plans never
+// need a cross product. With the same seed and catalog, the same plans are generated.
+//
+// Plans come out as logical or physical `RelNode`s, as groups of a memo, or in the generic
+// representation of s06 for the operators it has. The bindings of s05 are only produced by rules
+// from a memo, so there is no generator for them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinGraphShape {
+    /// `t0 - t1 - ... - tn`
+    Chain,
+    /// `t0` joined with every other table.
+    Star,
+    /// A chain, and `tn - t0`.
+    Cycle,
+    /// Every pair of tables.
+    Clique,
+}
+
+impl JoinGraphShape {
+    /// The pairs of relations joined by a predicate.
+    pub fn edges(&self, relations: usize) -> Vec<(usize, usize)> {
+        match self {
+            JoinGraphShape::Chain => (1..relations).map(|idx| (idx - 1, idx)).collect(),
+            JoinGraphShape::Star => (1..relations).map(|idx| (0, idx)).collect(),
+            JoinGraphShape::Cycle => {
+                let mut edges = JoinGraphShape::Chain.edges(relations);
+                if relations > 2 {
+                    edges.push((relations - 1, 0));
+                }
+                edges
+            }
+            JoinGraphShape::Clique => (0..relations)
+                .flat_map(|left| (left + 1..relations).map(move |right| (left, right)))
+                .collect(),
+        }
+    }
+}
+
+/// The probability of each operator on top of the joins, applied in this order.
+#[derive(Debug, Clone)]
+pub struct OperatorMix {
+    pub aggregate: f64,
+    pub project: f64,
+    pub sort: f64,
+    pub limit: f64,
+}
+
+impl Default for OperatorMix {
+    fn default() -> Self {
+        Self {
+            aggregate: 0.0,
+            project: 0.0,
+            sort: 0.0,
+            limit: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanGeneratorConfig {
+    pub seed: u64,
+    pub shape: JoinGraphShape,
+    /// Number of tables in the join, the same table can appear more than once.
+    pub relations: usize,
+    /// Number of `column = constant` filters on single tables.
+    pub filters: usize,
+    /// Constants in filters are drawn from `0..max_value`.
+    pub max_value: i64,
+    /// The probability that a filter is a disjunction of two equalities.
+    pub disjunctions: f64,
+    pub operators: OperatorMix,
+}
+
+impl Default for PlanGeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            shape: JoinGraphShape::Chain,
+            relations: 4,
+            filters: 2,
+            max_value: 10,
+            disjunctions: 0.0,
+            operators: OperatorMix::default(),
+        }
+    }
+}
+
+pub struct PlanGenerator<'a> {
+    catalog: &'a Catalog,
+    config: PlanGeneratorConfig,
+    rng: Rng,
+}
+
+/// Joined relations: the plan, and the relations in the order of their columns.
+struct Component {
+    rel: Arc<RelNode>,
+    relations: Vec<usize>,
+}
+
+impl<'a> PlanGenerator<'a> {
+    pub fn new(catalog: &'a Catalog, config: PlanGeneratorConfig) -> Self {
+        assert!(
+            catalog
+                .tables()
+                .iter()
+                .any(|table| !table.columns.is_empty()),
+            "the catalog has no tables with columns"
+        );
+        assert!(config.relations > 0, "a plan needs at least one relation");
+        assert!(
+            config.max_value > 0,
+            "constants need a positive `max_value`"
+        );
+        let rng = Rng::new(config.seed);
+        Self {
+            catalog,
+            config,
+            rng,
+        }
+    }
+
+    fn column_types(&self, table: &TableId) -> Vec<DataType> {
+        self.catalog
+            .table(table)
+            .columns
+            .iter()
+            .map(|column| column.data_type)
+            .collect()
+    }
+
+    /// The columns of a foreign key from one table to the other.
+    fn fk_columns(&self, from: &TableId, to: &TableId) -> Option<Vec<(usize, usize)>> {
+        self.catalog
+            .table(from)
+            .foreign_keys
+            .iter()
+            .find(|fk| fk.referenced_table == *to)
+            .map(|fk| {
+                fk.columns
+                    .iter()
+                    .copied()
+                    .zip(fk.referenced_columns.iter().copied())
+                    .collect()
+            })
+    }
+
+    /// The pairs of columns of the same type in the two tables.
+    fn same_type_columns(&self, left: &TableId, right: &TableId) -> Vec<(usize, usize)> {
+        let (left_types, right_types) = (self.column_types(left), self.column_types(right));
+        (0..left_types.len())
+            .flat_map(|l| (0..right_types.len()).map(move |r| (l, r)))
+            .filter(|(l, r)| left_types[*l] == right_types[*r])
+            .collect()
+    }
+
+    fn joinable(&self, left: &TableId, right: &TableId) -> bool {
+        self.fk_columns(left, right).is_some()
+            || self.fk_columns(right, left).is_some()
+            || !self.same_type_columns(left, right).is_empty()
+    }
+
+    /// A predicate joining two tables: a foreign key between them if there is one, otherwise an
+    /// equality of two random columns of the same type. Columns are local to each table, and the
+    /// tables must be joinable.
+    fn join_columns(&mut self, left: &TableId, right: &TableId) -> Vec<(usize, usize)> {
+        if let Some(pairs) = self.fk_columns(left, right) {
+            return pairs;
+        }
+        if let Some(pairs) = self.fk_columns(right, left) {
+            return pairs.into_iter().map(|(r, l)| (l, r)).collect();
+        }
+        let pairs = self.same_type_columns(left, right);
+        vec![pairs[self.rng.gen_range(pairs.len())]]
+    }
+
+    /// Pick a table with columns for every relation, so that the tables of each edge of the shape
+    /// can be joined. If some relation has no such table, every relation is a self-join of the
+    /// first table instead, which always works.
+    fn pick_tables(&mut self) -> Vec<TableId> {
+        let all = self
+            .catalog
+            .tables()
+            .iter()
+            .filter(|table| !table.columns.is_empty())
+            .map(|table| table.id.clone())
+            .collect::<Vec<_>>();
+        let edges = self.config.shape.edges(self.config.relations);
+        let mut tables: Vec<TableId> = vec![];
+        for idx in 0..self.config.relations {
+            let candidates = all
+                .iter()
+                .filter(|table| {
+                    edges.iter().all(|(left, right)| match (*left, *right) {
+                        (other, this) | (this, other) if this == idx && other < idx => {
+                            self.joinable(&tables[other], table)
+                        }
+                        _ => true,
+                    })
+                })
+                .collect::<Vec<_>>();
+            if candidates.is_empty() {
+                return vec![tables[0].clone(); self.config.relations];
+            }
+            let table = candidates[self.rng.gen_range(candidates.len())].clone();
+            tables.push(table);
+        }
+        tables
+    }
+
+    fn constant(&mut self, data_type: DataType) -> Arc<RelNode> {
+        let value = match data_type {
+            DataType::Int => self.rng.gen_range(self.config.max_value as usize) as i64,
+            DataType::Bool => self.rng.gen_range(2) as i64,
+        };
+        Arc::new(const_pred(value))
+    }
+
+    /// `column = constant` on a column of the table, or a disjunction of two of those.
+    fn filter_pred(&mut self, table: &TableId) -> Arc<RelNode> {
+        let types = self.column_types(table);
+        let atom = |this: &mut Self| {
+            let column = this.rng.gen_range(types.len());
+            let value = this.constant(types[column]);
+            Arc::new(eq_pred(column_ref_pred(column), value))
+        };
+        let pred = atom(self);
+        if self.rng.gen_f64() < self.config.disjunctions {
+            Arc::new(or_pred(pred, atom(self)))
+        } else {
+            pred
+        }
+    }
+
+    /// The position of a column of a relation in the output of a component.
+    fn position(&self, tables: &[TableId], component: &Component, relation: usize) -> usize {
+        component
+            .relations
+            .iter()
+            .take_while(|other| **other != relation)
+            .map(|other| self.catalog.table(&tables[*other]).columns.len())
+            .sum()
+    }
+
+    /// Join the relations along the edges of the shape. Components are merged in a random order
+    /// over a random edge between them, with all edges between the two components as the
+    /// condition, so the tree can be bushy.
+    fn join_tree(&mut self, tables: &[TableId]) -> (Arc<RelNode>, Vec<usize>) {
+        let mut filters = vec![vec![]; tables.len()];
+        for _ in 0..self.config.filters {
+            let relation = self.rng.gen_range(tables.len());
+            let pred = self.filter_pred(&tables[relation]);
+            filters[relation].push(pred);
+        }
+        let mut components = tables
+            .iter()
+            .zip(filters)
+            .enumerate()
+            .map(|(idx, (table, filters))| {
+                let rel = Arc::new(scan(table.clone()));
+                let rel = match fold_and(filters) {
+                    Some(pred) => Arc::new(filter(rel, pred)),
+                    None => rel,
+                };
+                Some(Component {
+                    rel,
+                    relations: vec![idx],
+                })
+            })
+            .collect::<Vec<_>>();
+        // the component each relation is in
+        let mut component_of = (0..tables.len()).collect::<Vec<_>>();
+        let edges = self
+            .config
+            .shape
+            .edges(tables.len())
+            .into_iter()
+            .map(|(left, right)| {
+                let columns = self.join_columns(&tables[left], &tables[right]);
+                (left, right, columns)
+            })
+            .collect::<Vec<_>>();
+        loop {
+            let crossing = edges
+                .iter()
+                .filter(|(left, right, _)| component_of[*left] != component_of[*right])
+                .collect::<Vec<_>>();
+            if crossing.is_empty() {
+                break;
+            }
+            let (mut a, mut b, _) = crossing[self.rng.gen_range(crossing.len())];
+            if self.rng.gen_range(2) == 0 {
+                (a, b) = (b, a);
+            }
+            let (left_idx, right_idx) = (component_of[a], component_of[b]);
+            let left = components[left_idx].take().unwrap();
+            let right = components[right_idx].take().unwrap();
+            // past the last relation, the position is the number of columns
+            let offset = self.position(tables, &left, usize::MAX);
+            let mut preds = vec![];
+            for (from, to, columns) in &edges {
+                let (l, r, columns) = match (component_of[*from], component_of[*to]) {
+                    (x, y) if x == left_idx && y == right_idx => (*from, *to, columns.clone()),
+                    (x, y) if x == right_idx && y == left_idx => {
+                        let columns = columns.iter().map(|(a, b)| (*b, *a)).collect();
+                        (*to, *from, columns)
+                    }
+                    _ => continue,
+                };
+                let (l_pos, r_pos) = (
+                    self.position(tables, &left, l),
+                    offset + self.position(tables, &right, r),
+                );
+                for (l_col, r_col) in columns {
+                    preds.push(Arc::new(eq_pred(
+                        column_ref_pred(l_pos + l_col),
+                        column_ref_pred(r_pos + r_col),
+                    )));
+                }
+            }
+            let rel = Arc::new(join(left.rel, right.rel, fold_and(preds).unwrap()));
+            let relations = [left.relations, right.relations].concat();
+            for relation in &relations {
+                component_of[*relation] = left_idx;
+            }
+            components[left_idx] = Some(Component { rel, relations });
+        }
+        let component = components.into_iter().flatten().next().unwrap();
+        (component.rel, component.relations)
+    }
+
+    fn pick_columns(&mut self, columns: usize, max: usize) -> Vec<usize> {
+        let count = 1 + self.rng.gen_range(max.min(columns));
+        let mut all = (0..columns).collect::<Vec<_>>();
+        (0..count)
+            .map(|_| all.remove(self.rng.gen_range(all.len())))
+            .collect()
+    }
+
+    /// Generate the next logical plan.
+    pub fn generate(&mut self) -> Arc<RelNode> {
+        let tables = self.pick_tables();
+        let (mut rel, relations) = self.join_tree(&tables);
+        let mut types = relations
+            .iter()
+            .flat_map(|relation| self.column_types(&tables[*relation]))
+            .collect::<Vec<_>>();
+        let operators = self.config.operators.clone();
+        if self.rng.gen_f64() < operators.aggregate {
+            let group_by = self.pick_columns(types.len(), 2);
+            let mut aggs = vec![AggCall {
+                func: AggFunc::Count,
+                column: None,
+            }];
+            let ints = (0..types.len())
+                .filter(|column| types[*column] == DataType::Int)
+                .collect::<Vec<_>>();
+            if !ints.is_empty() {
+                let funcs = [AggFunc::Sum, AggFunc::Min, AggFunc::Max];
+                aggs.push(AggCall {
+                    func: funcs[self.rng.gen_range(funcs.len())],
+                    column: Some(ints[self.rng.gen_range(ints.len())]),
+                });
+            }
+            types = group_by.iter().map(|column| types[*column]).collect();
+            types.extend(vec![DataType::Int; aggs.len()]);
+            rel = Arc::new(aggregate(rel, group_by, aggs));
+        }
+        if self.rng.gen_f64() < operators.project {
+            let columns = self.pick_columns(types.len(), 4);
+            let exprs = columns
+                .iter()
+                .map(|column| Arc::new(column_ref_pred(*column)))
+                .collect();
+            types = columns.iter().map(|column| types[*column]).collect();
+            rel = Arc::new(project(rel, exprs));
+        }
+        if self.rng.gen_f64() < operators.sort {
+            let keys = self
+                .pick_columns(types.len(), 2)
+                .into_iter()
+                .map(|column| SortKey {
+                    column,
+                    descending: self.rng.gen_range(2) == 0,
+                })
+                .collect();
+            rel = Arc::new(sort(rel, keys));
+        }
+        if self.rng.gen_f64() < operators.limit {
+            rel = Arc::new(limit(rel, 1 + self.rng.gen_range(100)));
+        }
+        rel
+    }
+
+    /// Generate the next plan, implemented with physical operators.
+    pub fn generate_physical(&mut self) -> Arc<RelNode> {
+        heuristic_plan(&self.generate(), self.catalog)
+    }
+
+    /// Generate the next plan into the memo, and return its group.
+    pub fn generate_memo(&mut self, memo: &mut Memo) -> GroupId {
+        let rel = self.generate();
+        memorize_rel(memo, rel)
+    }
+}
+
+/// Convert a plan of scans, filters and inner joins on equalities to the generic representation
+/// of s06. Returns `None` for other operators and predicates.
+pub fn to_generic_repr(rel: &RelNode) -> Option<Arc<s06_new_repr::RelNode>> {
+    use s06_new_repr::{RelAttrType, RelNodeType};
+    let node = |typ, children, data| {
+        Some(Arc::new(s06_new_repr::RelNode {
+            typ,
+            children,
+            data: Arc::new(data),
+        }))
+    };
+    match rel {
+        RelNode::Scan(scan) => node(
+            RelNodeType::Scan,
+            vec![],
+            RelAttrType::TableId(s06_new_repr::TableId(scan.table.0)),
+        ),
+        RelNode::Filter(filter) => node(
+            RelNodeType::Filter,
+            vec![
+                to_generic_repr(&filter.child)?,
+                to_generic_repr(&filter.predicate)?,
+            ],
+            RelAttrType::None,
+        ),
+        RelNode::Join(join) if join.join_type == JoinType::Inner => node(
+            RelNodeType::Join,
+            vec![
+                to_generic_repr(&join.left)?,
+                to_generic_repr(&join.right)?,
+                to_generic_repr(&join.cond)?,
+            ],
+            RelAttrType::None,
+        ),
+        RelNode::Eq(eq) => node(
+            RelNodeType::Eq,
+            vec![to_generic_repr(&eq.left)?, to_generic_repr(&eq.right)?],
+            RelAttrType::None,
+        ),
+        RelNode::ColumnRef(column_ref) => node(
+            RelNodeType::ColumnRef,
+            vec![],
+            RelAttrType::ColumnRef(column_ref.column),
+        ),
+        RelNode::Const(const_pred) => node(
+            RelNodeType::Const,
+            vec![],
+            RelAttrType::Const(const_pred.value),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::abc_catalog;
+
+    #[test]
+    fn test_join_graph_shapes() {
+        let catalog = abc_catalog();
+        for (shape, edges) in [
+            (JoinGraphShape::Chain, 4),
+            (JoinGraphShape::Star, 4),
+            (JoinGraphShape::Cycle, 5),
+            (JoinGraphShape::Clique, 10),
+        ] {
+            let config = PlanGeneratorConfig {
+                shape,
+                relations: 5,
+                ..Default::default()
+            };
+            let mut generator = PlanGenerator::new(&catalog, config.clone());
+            for _ in 0..10 {
+                let rel = generator.generate();
+                let graph = JoinGraph::extract(&rel, &catalog).unwrap();
+                assert_eq!(graph.relations.len(), 5);
+                assert_eq!(graph.edges.len(), edges, "{shape:?}: {}", to_sexp(&rel));
+                // well-formed: the plan runs, and no join needs a cross product
+                assert!(reorder_joins_dpccp(rel.clone(), &catalog).is_some());
+                execute(&rel, &Database::from_catalog(&catalog));
+            }
+            // reproducible
+            let first = PlanGenerator::new(&catalog, config.clone()).generate();
+            assert_eq!(
+                to_sexp(&first),
+                to_sexp(&PlanGenerator::new(&catalog, config).generate())
+            );
+        }
+
+        // tables without columns of the same type are never joined, and tables without columns
+        // are never picked
+        let mut catalog = Catalog::new();
+        catalog
+            .add_table("ints", vec![ColumnDef::new("x", DataType::Int)], 10.0)
+            .unwrap();
+        catalog
+            .add_table("bools", vec![ColumnDef::new("b", DataType::Bool)], 10.0)
+            .unwrap();
+        catalog.add_table("empty", vec![], 10.0).unwrap();
+        for seed in 0..10 {
+            let config = PlanGeneratorConfig {
+                seed,
+                shape: JoinGraphShape::Clique,
+                ..Default::default()
+            };
+            let mut memo = Memo::new();
+            let group = PlanGenerator::new(&catalog, config).generate_memo(&mut memo);
+            let rel = generate_one_binding(&memo, group);
+            assert!(reorder_joins_dpccp(rel.clone(), &catalog).is_some());
+            execute(&rel, &Database::from_catalog(&catalog));
+        }
+    }
+
+    #[test]
+    fn test_operator_mix() {
+        let catalog = abc_catalog();
+        let config = PlanGeneratorConfig {
+            seed: 7,
+            relations: 3,
+            operators: OperatorMix {
+                aggregate: 1.0,
+                project: 1.0,
+                sort: 1.0,
+                limit: 1.0,
+            },
+            ..Default::default()
+        };
+        let mut generator = PlanGenerator::new(&catalog, config);
+        let rel = generator.generate();
+        let RelNode::Limit(limit) = &*rel else {
+            panic!("expected a limit, got {}", to_sexp(&rel));
+        };
+        let RelNode::Sort(sort) = &*limit.child else {
+            panic!("expected a sort, got {}", to_sexp(&limit.child));
+        };
+        assert!(matches!(&*sort.child, RelNode::Project(_)));
+        assert!(to_generic_repr(&rel).is_none());
+        // a chain without filters only has single equalities
+        let config = PlanGeneratorConfig {
+            filters: 0,
+            ..Default::default()
+        };
+        let rel = PlanGenerator::new(&catalog, config).generate();
+        assert!(to_generic_repr(&rel).is_some());
+
+        let physical = generator.generate_physical();
+        assert!(matches!(&*physical, RelNode::Limit(_)));
+        let mut db = Database::from_catalog(&catalog);
+        db.insert(&TableId(2), vec![vec![Some(1)]]);
+        execute(&physical, &db);
+    }
+}
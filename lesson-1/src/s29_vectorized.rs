@@ -0,0 +1,795 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use super::*;
+
+// The reference executor of s26 moves one row at a time and allocates for every row, so it says
+// nothing about how fast a plan is. This executor moves batches of up to `BATCH_SIZE` rows, stored
+// column by column in typed vectors, and evaluates predicates on whole columns. Blocking operators
+// (the build side of a join, sort, aggregate) collect their input into one batch and return their
+// result as one batch.
+//
+// It runs the physical operators whose cost the cost model estimates: table scans, filters,
+// projections, hash joins, nested-loop joins, aggregates, sorts and limits. Plans with other
+// operators are not supported.
+
+pub const BATCH_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    Int(Vec<i64>),
+    Bool(Vec<bool>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnVector {
+    pub data: ColumnData,
+    /// Whether each value is not NULL. The data of a NULL value is unspecified.
+    pub valid: Vec<bool>,
+}
+
+impl ColumnVector {
+    pub fn empty(data_type: DataType) -> Self {
+        Self::from_values(data_type, std::iter::empty())
+    }
+
+    pub fn from_values(data_type: DataType, values: impl Iterator<Item = Value>) -> Self {
+        let (values, valid): (Vec<_>, Vec<_>) = values
+            .map(|value| (value.unwrap_or_default(), value.is_some()))
+            .unzip();
+        let data = match data_type {
+            DataType::Int => ColumnData::Int(values),
+            DataType::Bool => ColumnData::Bool(values.into_iter().map(|v| v != 0).collect()),
+        };
+        Self { data, valid }
+    }
+
+    fn from_truth(values: impl Iterator<Item = Option<bool>>) -> Self {
+        let (values, valid) = values
+            .map(|value| (value.unwrap_or_default(), value.is_some()))
+            .unzip();
+        Self {
+            data: ColumnData::Bool(values),
+            valid,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.valid.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.valid.is_empty()
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self.data {
+            ColumnData::Int(_) => DataType::Int,
+            ColumnData::Bool(_) => DataType::Bool,
+        }
+    }
+
+    /// The value as an integer, booleans are 0 or 1.
+    fn int(&self, idx: usize) -> i64 {
+        match &self.data {
+            ColumnData::Int(values) => values[idx],
+            ColumnData::Bool(values) => values[idx] as i64,
+        }
+    }
+
+    pub fn value(&self, idx: usize) -> Value {
+        self.valid[idx].then(|| self.int(idx))
+    }
+
+    fn truth(&self, idx: usize) -> Option<bool> {
+        self.valid[idx].then(|| self.int(idx) != 0)
+    }
+
+    fn gather(&self, indices: &[usize]) -> Self {
+        let data = match &self.data {
+            ColumnData::Int(values) => {
+                ColumnData::Int(indices.iter().map(|i| values[*i]).collect())
+            }
+            ColumnData::Bool(values) => {
+                ColumnData::Bool(indices.iter().map(|i| values[*i]).collect())
+            }
+        };
+        let valid = indices.iter().map(|i| self.valid[*i]).collect();
+        Self { data, valid }
+    }
+
+    fn append(&mut self, other: &ColumnVector) {
+        match (&mut self.data, &other.data) {
+            (ColumnData::Int(values), ColumnData::Int(other)) => values.extend(other),
+            (ColumnData::Bool(values), ColumnData::Bool(other)) => values.extend(other),
+            _ => panic!("cannot append a {:?} column", other.data_type()),
+        }
+        self.valid.extend(&other.valid);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub columns: Vec<ColumnVector>,
+    pub rows: usize,
+}
+
+impl Batch {
+    pub fn empty(types: &[DataType]) -> Self {
+        Self {
+            columns: types.iter().map(|t| ColumnVector::empty(*t)).collect(),
+            rows: 0,
+        }
+    }
+
+    pub fn from_rows(types: &[DataType], rows: &[Row]) -> Self {
+        let columns = types
+            .iter()
+            .enumerate()
+            .map(|(column, t)| ColumnVector::from_values(*t, rows.iter().map(|row| row[column])))
+            .collect();
+        Self {
+            columns,
+            rows: rows.len(),
+        }
+    }
+
+    pub fn to_rows(&self) -> Vec<Row> {
+        (0..self.rows)
+            .map(|idx| {
+                self.columns
+                    .iter()
+                    .map(|column| column.value(idx))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn gather(&self, indices: &[usize]) -> Self {
+        Self {
+            columns: self.columns.iter().map(|c| c.gather(indices)).collect(),
+            rows: indices.len(),
+        }
+    }
+
+    fn slice(&self, range: Range<usize>) -> Self {
+        self.gather(&range.collect::<Vec<_>>())
+    }
+
+    fn append(&mut self, other: &Batch) {
+        for (column, other) in self.columns.iter_mut().zip(&other.columns) {
+            column.append(other);
+        }
+        self.rows += other.rows;
+    }
+
+    /// The columns of `self` followed by the columns of `other`, with the same number of rows.
+    fn concat_columns(mut self, other: Batch) -> Self {
+        self.columns.extend(other.columns);
+        self
+    }
+
+    fn selected(&self, selection: &ColumnVector) -> Vec<usize> {
+        (0..self.rows)
+            .filter(|idx| selection.truth(*idx) == Some(true))
+            .collect()
+    }
+}
+
+/// Evaluate a scalar expression on every row of a batch.
+pub fn eval_vector(expr: &RelNode, batch: &Batch) -> ColumnVector {
+    let rows = 0..batch.rows;
+    match expr {
+        RelNode::ColumnRef(column_ref) => batch.columns[column_ref.column].clone(),
+        RelNode::Const(const_pred) => ColumnVector {
+            data: ColumnData::Int(vec![const_pred.value; batch.rows]),
+            valid: vec![true; batch.rows],
+        },
+        RelNode::Eq(eq) => {
+            let (left, right) = (eval_vector(&eq.left, batch), eval_vector(&eq.right, batch));
+            ColumnVector {
+                data: ColumnData::Bool(rows.clone().map(|i| left.int(i) == right.int(i)).collect()),
+                valid: rows.map(|i| left.valid[i] && right.valid[i]).collect(),
+            }
+        }
+        RelNode::Add(add) => {
+            let (left, right) = (
+                eval_vector(&add.left, batch),
+                eval_vector(&add.right, batch),
+            );
+            ColumnVector {
+                data: ColumnData::Int(
+                    rows.clone()
+                        .map(|i| left.int(i).wrapping_add(right.int(i)))
+                        .collect(),
+                ),
+                valid: rows.map(|i| left.valid[i] && right.valid[i]).collect(),
+            }
+        }
+        RelNode::And(and) => {
+            let (left, right) = (
+                eval_vector(&and.left, batch),
+                eval_vector(&and.right, batch),
+            );
+            ColumnVector::from_truth(rows.map(|i| match (left.truth(i), right.truth(i)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }))
+        }
+        RelNode::Or(or) => {
+            let (left, right) = (eval_vector(&or.left, batch), eval_vector(&or.right, batch));
+            ColumnVector::from_truth(rows.map(|i| match (left.truth(i), right.truth(i)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }))
+        }
+        _ => panic!("not a scalar expression: {expr:?}"),
+    }
+}
+
+fn expr_type(expr: &RelNode, input: &[DataType]) -> DataType {
+    match expr {
+        RelNode::ColumnRef(column_ref) => input[column_ref.column],
+        RelNode::Eq(_) | RelNode::And(_) | RelNode::Or(_) => DataType::Bool,
+        _ => DataType::Int,
+    }
+}
+
+/// A table stored as one batch.
+#[derive(Debug, Clone)]
+pub struct ColumnarTable {
+    pub types: Vec<DataType>,
+    pub batch: Batch,
+}
+
+/// The tables of a catalog in columnar form.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnarDatabase {
+    tables: HashMap<TableId, ColumnarTable>,
+}
+
+impl ColumnarDatabase {
+    /// Convert the rows of every table of the catalog, with the column types of the catalog.
+    pub fn load(db: &Database, catalog: &Catalog) -> Self {
+        let tables = catalog
+            .tables()
+            .iter()
+            .map(|def| {
+                let types = def.columns.iter().map(|c| c.data_type).collect::<Vec<_>>();
+                let batch = Batch::from_rows(&types, db.rows(&def.id));
+                (def.id.clone(), ColumnarTable { types, batch })
+            })
+            .collect();
+        Self { tables }
+    }
+
+    pub fn table(&self, table: &TableId) -> &ColumnarTable {
+        &self.tables[table]
+    }
+}
+
+impl TableStats for ColumnarDatabase {
+    fn column_count(&self, table: &TableId) -> usize {
+        self.table(table).types.len()
+    }
+
+    fn row_count(&self, table: &TableId) -> f64 {
+        self.table(table).batch.rows as f64
+    }
+}
+
+/// A vectorized operator, pulling batches from its children.
+pub trait BatchOperator {
+    /// The types of the output columns.
+    fn types(&self) -> &[DataType];
+
+    /// The next non-empty batch, or `None` at the end of the output.
+    fn next_batch(&mut self) -> Option<Batch>;
+}
+
+pub type BoxedOperator<'a> = Box<dyn BatchOperator + 'a>;
+
+fn collect_batches(op: &mut dyn BatchOperator) -> Batch {
+    let mut res = Batch::empty(op.types());
+    while let Some(batch) = op.next_batch() {
+        res.append(&batch);
+    }
+    res
+}
+
+struct ScanOp<'a> {
+    table: &'a ColumnarTable,
+    offset: usize,
+}
+
+impl BatchOperator for ScanOp<'_> {
+    fn types(&self) -> &[DataType] {
+        &self.table.types
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        let rows = self.table.batch.rows;
+        if self.offset >= rows {
+            return None;
+        }
+        let end = (self.offset + BATCH_SIZE).min(rows);
+        let batch = self.table.batch.slice(self.offset..end);
+        self.offset = end;
+        Some(batch)
+    }
+}
+
+struct FilterOp<'a> {
+    child: BoxedOperator<'a>,
+    predicate: Arc<RelNode>,
+}
+
+impl BatchOperator for FilterOp<'_> {
+    fn types(&self) -> &[DataType] {
+        self.child.types()
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        loop {
+            let batch = self.child.next_batch()?;
+            let selected = batch.selected(&eval_vector(&self.predicate, &batch));
+            if !selected.is_empty() {
+                return Some(batch.gather(&selected));
+            }
+        }
+    }
+}
+
+struct ProjectOp<'a> {
+    child: BoxedOperator<'a>,
+    exprs: Vec<Arc<RelNode>>,
+    types: Vec<DataType>,
+}
+
+impl BatchOperator for ProjectOp<'_> {
+    fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        let batch = self.child.next_batch()?;
+        let columns = self
+            .exprs
+            .iter()
+            .map(|expr| eval_vector(expr, &batch))
+            .collect();
+        Some(Batch {
+            columns,
+            rows: batch.rows,
+        })
+    }
+}
+
+/// The output of a join for the `left_rows` of a batch, given the candidate pairs of (left row,
+/// row of the build side). The join condition is evaluated on all candidates.
+fn join_candidates(
+    join: &Join,
+    left: &Batch,
+    left_rows: Range<usize>,
+    build: &Batch,
+    pairs: (Vec<usize>, Vec<usize>),
+) -> Batch {
+    let (left_idx, right_idx) = pairs;
+    let joined = left
+        .gather(&left_idx)
+        .concat_columns(build.gather(&right_idx));
+    let passed = joined.selected(&eval_vector(&join.cond, &joined));
+    if join.join_type == JoinType::Inner {
+        return joined.gather(&passed);
+    }
+    let mut matched = vec![false; left.rows];
+    for idx in passed {
+        matched[left_idx[idx]] = true;
+    }
+    let keep = join.join_type == JoinType::LeftSemi;
+    let rows = left_rows
+        .filter(|row| matched[*row] == keep)
+        .collect::<Vec<_>>();
+    left.gather(&rows)
+}
+
+fn join_types(join_type: JoinType, left: &[DataType], right: &[DataType]) -> Vec<DataType> {
+    match join_type {
+        JoinType::Inner => [left, right].concat(),
+        JoinType::LeftSemi | JoinType::LeftAnti => left.to_vec(),
+    }
+}
+
+/// The rows of the build side with each key.
+type HashTable = HashMap<Vec<i64>, Vec<usize>>;
+
+struct HashJoinOp<'a> {
+    join: &'a Join,
+    left: BoxedOperator<'a>,
+    right: BoxedOperator<'a>,
+    keys: Vec<(usize, usize)>,
+    build: Option<(Batch, HashTable)>,
+    types: Vec<DataType>,
+}
+
+/// The values of the key columns of a row, or `None` if one of them is NULL.
+fn key_values(batch: &Batch, columns: impl Iterator<Item = usize>, row: usize) -> Option<Vec<i64>> {
+    columns
+        .map(|column| batch.columns[column].value(row))
+        .collect()
+}
+
+impl BatchOperator for HashJoinOp<'_> {
+    fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        let (build, table) = self.build.get_or_insert_with(|| {
+            let build = collect_batches(&mut *self.right);
+            let mut table = HashTable::new();
+            for row in 0..build.rows {
+                let right_keys = self.keys.iter().map(|(_, right)| *right);
+                if let Some(key) = key_values(&build, right_keys, row) {
+                    table.entry(key).or_default().push(row);
+                }
+            }
+            (build, table)
+        });
+        loop {
+            let left = self.left.next_batch()?;
+            let mut pairs = (vec![], vec![]);
+            for row in 0..left.rows {
+                let left_keys = self.keys.iter().map(|(left, _)| *left);
+                let Some(matches) = key_values(&left, left_keys, row).and_then(|k| table.get(&k))
+                else {
+                    continue;
+                };
+                pairs.0.extend(std::iter::repeat_n(row, matches.len()));
+                pairs.1.extend(matches);
+            }
+            let batch = join_candidates(self.join, &left, 0..left.rows, build, pairs);
+            if batch.rows > 0 {
+                return Some(batch);
+            }
+        }
+    }
+}
+
+struct NestedLoopJoinOp<'a> {
+    join: &'a Join,
+    left: BoxedOperator<'a>,
+    right: BoxedOperator<'a>,
+    build: Option<Batch>,
+    /// The left batch being joined, and its first row not joined yet.
+    current: Option<(Batch, usize)>,
+    types: Vec<DataType>,
+}
+
+impl BatchOperator for NestedLoopJoinOp<'_> {
+    fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        let build = self
+            .build
+            .get_or_insert_with(|| collect_batches(&mut *self.right));
+        loop {
+            let (left, start) = match self.current.take() {
+                Some((left, start)) if start < left.rows => (left, start),
+                _ => (self.left.next_batch()?, 0),
+            };
+            // pair whole left rows with all build rows, about a batch of candidates at a time
+            let count = (BATCH_SIZE / build.rows.max(1)).max(1);
+            let end = (start + count).min(left.rows);
+            let left_idx = (start..end)
+                .flat_map(|row| std::iter::repeat_n(row, build.rows))
+                .collect();
+            let right_idx = (start..end).flat_map(|_| 0..build.rows).collect();
+            let batch = join_candidates(self.join, &left, start..end, build, (left_idx, right_idx));
+            self.current = Some((left, end));
+            if batch.rows > 0 {
+                return Some(batch);
+            }
+        }
+    }
+}
+
+struct SortOp<'a> {
+    child: BoxedOperator<'a>,
+    keys: Vec<SortKey>,
+    done: bool,
+}
+
+impl BatchOperator for SortOp<'_> {
+    fn types(&self) -> &[DataType] {
+        self.child.types()
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        if std::mem::replace(&mut self.done, true) {
+            return None;
+        }
+        let batch = collect_batches(&mut *self.child);
+        let mut order = (0..batch.rows).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            self.keys
+                .iter()
+                .map(|key| {
+                    let column = &batch.columns[key.column];
+                    let order = column.value(*a).cmp(&column.value(*b));
+                    if key.descending {
+                        order.reverse()
+                    } else {
+                        order
+                    }
+                })
+                .find(|order| order.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        (batch.rows > 0).then(|| batch.gather(&order))
+    }
+}
+
+struct AggregateOp<'a> {
+    child: BoxedOperator<'a>,
+    aggregate: &'a Aggregate,
+    types: Vec<DataType>,
+    done: bool,
+}
+
+impl BatchOperator for AggregateOp<'_> {
+    fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        if std::mem::replace(&mut self.done, true) {
+            return None;
+        }
+        let batch = collect_batches(&mut *self.child);
+        let group_by = &self.aggregate.group_by;
+        // the first row and all rows of each group, in the order of their first row
+        let mut groups: Vec<(usize, Vec<usize>)> = vec![];
+        let mut group_idx = HashMap::new();
+        for row in 0..batch.rows {
+            let key = group_by
+                .iter()
+                .map(|column| batch.columns[*column].value(row))
+                .collect::<Vec<_>>();
+            let idx = *group_idx.entry(key).or_insert_with(|| {
+                groups.push((row, vec![]));
+                groups.len() - 1
+            });
+            groups[idx].1.push(row);
+        }
+        let mut columns = group_by
+            .iter()
+            .map(|column| {
+                let first_rows = groups.iter().map(|(first, _)| *first).collect::<Vec<_>>();
+                batch.columns[*column].gather(&first_rows)
+            })
+            .collect::<Vec<_>>();
+        // without GROUP BY, there is one row even for no input
+        if group_by.is_empty() && groups.is_empty() {
+            groups.push((0, vec![]));
+        }
+        for agg in &self.aggregate.aggs {
+            let values = groups.iter().map(|(_, rows)| {
+                let values = rows.iter().filter_map(|row| match agg.column {
+                    Some(column) => batch.columns[column].value(*row),
+                    None => Some(1),
+                });
+                match agg.func {
+                    AggFunc::Count => Some(values.count() as i64),
+                    AggFunc::Sum => values.reduce(i64::wrapping_add),
+                    AggFunc::Min => values.min(),
+                    AggFunc::Max => values.max(),
+                }
+            });
+            columns.push(ColumnVector::from_values(DataType::Int, values));
+        }
+        Some(Batch {
+            columns,
+            rows: groups.len(),
+        })
+    }
+}
+
+struct LimitOp<'a> {
+    child: BoxedOperator<'a>,
+    remaining: usize,
+}
+
+impl BatchOperator for LimitOp<'_> {
+    fn types(&self) -> &[DataType] {
+        self.child.types()
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let batch = self.child.next_batch()?;
+        let rows = batch.rows.min(self.remaining);
+        self.remaining -= rows;
+        Some(batch.slice(0..rows))
+    }
+}
+
+/// Open a physical plan as a tree of vectorized operators. Returns `None` if the plan has an
+/// operator this executor doesn't implement.
+pub fn open_vectorized<'a>(
+    rel: &'a RelNode,
+    db: &'a ColumnarDatabase,
+) -> Option<BoxedOperator<'a>> {
+    open_vectorized_with(rel, db, &mut |_, op| op)
+}
+
+/// Like `open_vectorized`, but every operator is passed through `wrap` after its children, e.g.
+/// to instrument it.
+pub fn open_vectorized_with<'a>(
+    rel: &'a RelNode,
+    db: &'a ColumnarDatabase,
+    wrap: &mut dyn FnMut(&'a RelNode, BoxedOperator<'a>) -> BoxedOperator<'a>,
+) -> Option<BoxedOperator<'a>> {
+    let op: BoxedOperator = match rel {
+        RelNode::TableScan(scan) => Box::new(ScanOp {
+            table: db.table(&scan.table),
+            offset: 0,
+        }),
+        RelNode::PhysicalFilter(filter) => Box::new(FilterOp {
+            child: open_vectorized_with(&filter.child, db, wrap)?,
+            predicate: filter.predicate.clone(),
+        }),
+        RelNode::Project(project) => {
+            let child = open_vectorized_with(&project.child, db, wrap)?;
+            let types = project
+                .exprs
+                .iter()
+                .map(|expr| expr_type(expr, child.types()))
+                .collect();
+            Box::new(ProjectOp {
+                child,
+                exprs: project.exprs.clone(),
+                types,
+            })
+        }
+        RelNode::HashJoin(join) => {
+            let (left, right) = (
+                open_vectorized_with(&join.left, db, wrap)?,
+                open_vectorized_with(&join.right, db, wrap)?,
+            );
+            let types = join_types(join.join_type, left.types(), right.types());
+            Box::new(HashJoinOp {
+                join,
+                keys: equi_join_keys(&join.cond, left.types().len()),
+                left,
+                right,
+                build: None,
+                types,
+            })
+        }
+        RelNode::NestedLoopJoin(join) => {
+            let (left, right) = (
+                open_vectorized_with(&join.left, db, wrap)?,
+                open_vectorized_with(&join.right, db, wrap)?,
+            );
+            let types = join_types(join.join_type, left.types(), right.types());
+            Box::new(NestedLoopJoinOp {
+                join,
+                left,
+                right,
+                build: None,
+                current: None,
+                types,
+            })
+        }
+        RelNode::Sort(sort) => Box::new(SortOp {
+            child: open_vectorized_with(&sort.child, db, wrap)?,
+            keys: sort.keys.clone(),
+            done: false,
+        }),
+        RelNode::Aggregate(aggregate) => {
+            let child = open_vectorized_with(&aggregate.child, db, wrap)?;
+            let mut types = aggregate
+                .group_by
+                .iter()
+                .map(|column| child.types()[*column])
+                .collect::<Vec<_>>();
+            types.extend(vec![DataType::Int; aggregate.aggs.len()]);
+            Box::new(AggregateOp {
+                child,
+                aggregate,
+                types,
+                done: false,
+            })
+        }
+        RelNode::Limit(limit) => Box::new(LimitOp {
+            child: open_vectorized_with(&limit.child, db, wrap)?,
+            remaining: limit.limit,
+        }),
+        _ => return None,
+    };
+    Some(wrap(rel, op))
+}
+
+/// Run a physical plan to completion, or return `None` if it isn't supported.
+pub fn execute_vectorized(rel: &RelNode, db: &ColumnarDatabase) -> Option<Vec<Row>> {
+    let mut op = open_vectorized(rel, db)?;
+    Some(collect_batches(&mut *op).to_rows())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::abc_catalog_and_data;
+
+    #[test]
+    fn test_vectorized_matches_reference() {
+        let (catalog, db) = abc_catalog_and_data();
+        let columnar = ColumnarDatabase::load(&db, &catalog);
+        for shape in [JoinGraphShape::Chain, JoinGraphShape::Star] {
+            let config = PlanGeneratorConfig {
+                seed: 3,
+                shape,
+                relations: 3,
+                filters: 0,
+                operators: OperatorMix {
+                    aggregate: 0.5,
+                    project: 0.5,
+                    sort: 0.5,
+                    limit: 0.2,
+                },
+                ..Default::default()
+            };
+            let mut generator = PlanGenerator::new(&catalog, config);
+            for _ in 0..5 {
+                let plan = generator.generate_physical();
+                let rows = execute_vectorized(&plan, &columnar).unwrap();
+                assert_eq!(
+                    sorted_rows(rows),
+                    sorted_rows(execute(&plan, &db)),
+                    "{}",
+                    to_sexp(&plan)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_join_types() {
+        let (catalog, db) = abc_catalog_and_data();
+        let columnar = ColumnarDatabase::load(&db, &catalog);
+        let cond = eq_pred(column_ref_pred(1), column_ref_pred(3));
+        for join_type in [JoinType::Inner, JoinType::LeftSemi, JoinType::LeftAnti] {
+            let rel = join_with_type(join_type, scan(TableId(1)), scan(TableId(0)), cond.clone());
+            let RelNode::Join(join) = rel else {
+                unreachable!()
+            };
+            for plan in [
+                RelNode::HashJoin(join.clone()),
+                RelNode::NestedLoopJoin(join.clone()),
+            ] {
+                let plan = heuristic_plan(&Arc::new(plan), &catalog);
+                let rows = execute_vectorized(&plan, &columnar).unwrap();
+                assert_eq!(sorted_rows(rows), sorted_rows(execute(&plan, &db)));
+            }
+        }
+        // a flag is a boolean column, the filter compares it with 1
+        let rel = filter(scan(TableId(1)), eq_pred(column_ref_pred(2), const_pred(1)));
+        let plan = heuristic_plan(&Arc::new(rel), &catalog);
+        let rows = execute_vectorized(&plan, &columnar).unwrap();
+        assert!(rows.iter().all(|row| row[2] == Some(1)));
+        assert_eq!(sorted_rows(rows), sorted_rows(execute(&plan, &db)));
+
+        let index_scan = RelNode::IndexScan(IndexScan {
+            table: TableId(0),
+            index: "a_pkey".to_string(),
+            prefix: vec![],
+        });
+        assert!(execute_vectorized(&index_scan, &columnar).is_none());
+    }
+}
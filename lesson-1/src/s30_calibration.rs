@@ -0,0 +1,452 @@
+use std::{
+    cell::RefCell,
+    fmt, io,
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::*;
+
+// The coefficients of the cost model were picked by hand. To see what operators really cost, we
+// run a workload with the vectorized executor and record the rows and the time of every operator.
+// The cost of an operator is linear in the coefficients, so the coefficients that best explain
+// the timings are a least squares fit. The fitted model is saved to a text file, one
+// `name = value` per line, which the optimizer can load.
+//
+// Only the coefficients of operators the executor runs are fitted: per-tuple CPU, hash build,
+// hash probe and sort. Costs are relative, so the fitted coefficients are scaled to keep
+// `cpu_tuple` at 1, and the index coefficients keep their values.
+
+/// What one operator of an executed plan did.
+#[derive(Debug, Clone)]
+pub struct OperatorSample {
+    pub operator: MemoRelNode,
+    pub output_rows: usize,
+    pub input_rows: Vec<usize>,
+    /// The time spent in the operator itself, without its inputs.
+    pub time: Duration,
+}
+
+/// Rows and time of an operator, including the time spent in its inputs.
+type Measurement = (usize, Duration);
+
+struct ProfiledOp<'a> {
+    inner: BoxedOperator<'a>,
+    slot: usize,
+    measurements: Rc<RefCell<Vec<Measurement>>>,
+}
+
+impl BatchOperator for ProfiledOp<'_> {
+    fn types(&self) -> &[DataType] {
+        self.inner.types()
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        let start = Instant::now();
+        let batch = self.inner.next_batch();
+        let (rows, time) = &mut self.measurements.borrow_mut()[self.slot];
+        *time += start.elapsed();
+        *rows += batch.as_ref().map_or(0, |batch| batch.rows);
+        batch
+    }
+}
+
+/// Run a physical plan with the vectorized executor and return a sample for each operator, inputs
+/// first. Returns `None` if the executor doesn't support the plan.
+pub fn profile_plan(rel: &RelNode, db: &ColumnarDatabase) -> Option<Vec<OperatorSample>> {
+    let measurements = Rc::new(RefCell::new(vec![]));
+    let mut wrap = |_: &RelNode, inner| {
+        let slot = {
+            let mut measurements = measurements.borrow_mut();
+            measurements.push((0, Duration::ZERO));
+            measurements.len() - 1
+        };
+        let op = ProfiledOp {
+            inner,
+            slot,
+            measurements: measurements.clone(),
+        };
+        Box::new(op) as BoxedOperator
+    };
+    let mut op = open_vectorized_with(rel, db, &mut wrap)?;
+    while op.next_batch().is_some() {}
+    drop(op);
+
+    // operators are opened after their inputs, left to right
+    fn collect(
+        rel: &RelNode,
+        measurements: &[Measurement],
+        next: &mut usize,
+        samples: &mut Vec<OperatorSample>,
+    ) -> Measurement {
+        let inputs = rel_inputs(rel)
+            .iter()
+            .map(|input| collect(input, measurements, next, samples))
+            .collect::<Vec<_>>();
+        let (rows, time) = measurements[*next];
+        *next += 1;
+        let input_time = inputs.iter().map(|(_, time)| *time).sum();
+        samples.push(OperatorSample {
+            operator: memo_operator(rel),
+            output_rows: rows,
+            input_rows: inputs.iter().map(|(rows, _)| *rows).collect(),
+            time: time.saturating_sub(input_time),
+        });
+        (rows, time)
+    }
+    let mut samples = vec![];
+    collect(rel, &measurements.borrow(), &mut 0, &mut samples);
+    Some(samples)
+}
+
+/// The calibrated coefficients, in the order of `cost_terms`.
+const CALIBRATED: [&str; 4] = ["cpu_tuple", "hash_build", "hash_probe", "sort"];
+
+impl CostModel {
+    fn coefficients(&self) -> [(&'static str, f64); 6] {
+        [
+            ("cpu_tuple", self.cpu_tuple),
+            ("hash_build", self.hash_build),
+            ("hash_probe", self.hash_probe),
+            ("sort", self.sort),
+            ("index_probe", self.index_probe),
+            ("index_tuple", self.index_tuple),
+        ]
+    }
+
+    fn coefficient_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "cpu_tuple" => Some(&mut self.cpu_tuple),
+            "hash_build" => Some(&mut self.hash_build),
+            "hash_probe" => Some(&mut self.hash_probe),
+            "sort" => Some(&mut self.sort),
+            "index_probe" => Some(&mut self.index_probe),
+            "index_tuple" => Some(&mut self.index_tuple),
+            _ => None,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.coefficients()
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}\n"))
+            .collect()
+    }
+
+    /// Parse `name = value` lines. Empty lines and lines starting with `#` are skipped, and
+    /// coefficients that are not in the text keep their default values.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut model = Self::default();
+        let mut offset = 0;
+        for line in text.split('\n') {
+            let line_start = offset;
+            offset += line.len() + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let line_start = line_start + line.len() - line.trim_start().len();
+            let Some((name, value)) = trimmed.split_once('=') else {
+                return Err(ParseError::at(text, line_start, "expected `name = value`"));
+            };
+            let value_start = line_start + name.len() + 1 + value.len() - value.trim_start().len();
+            let name = name.trim();
+            let Some(coefficient) = model.coefficient_mut(name) else {
+                let message = format!("unknown coefficient `{name}`");
+                return Err(ParseError::at(text, line_start, message));
+            };
+            let value = value.trim();
+            *coefficient = value.parse().map_err(|_| {
+                ParseError::at(
+                    text,
+                    value_start,
+                    format!("expected a number, found `{value}`"),
+                )
+            })?;
+        }
+        Ok(model)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CostModelError> {
+        let text = std::fs::read_to_string(path).map_err(CostModelError::Io)?;
+        Self::parse(&text).map_err(CostModelError::Parse)
+    }
+
+    /// The terms of the operator cost that each calibrated coefficient multiplies. The cost is
+    /// linear in the coefficients, so a term is the cost with that coefficient set to 1 and all
+    /// others to 0.
+    pub fn cost_terms(sample: &OperatorSample) -> [f64; 4] {
+        let output = sample.output_rows as f64;
+        let inputs = sample
+            .input_rows
+            .iter()
+            .map(|rows| *rows as f64)
+            .collect::<Vec<_>>();
+        CALIBRATED.map(|name| {
+            let mut unit = CostModel {
+                cpu_tuple: 0.0,
+                hash_build: 0.0,
+                hash_probe: 0.0,
+                sort: 0.0,
+                index_probe: 0.0,
+                index_tuple: 0.0,
+            };
+            *unit.coefficient_mut(name).unwrap() = 1.0;
+            unit.operator_cost(&sample.operator, output, &inputs)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum CostModelError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for CostModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostModelError::Io(error) => write!(f, "cannot read the cost model: {error}"),
+            CostModelError::Parse(error) => write!(f, "invalid cost model: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CostModelError {}
+
+/// Solve `a x = b` by Gaussian elimination with partial pivoting. Returns `None` if `a` is
+/// singular.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 * a[col].iter().map(|v| v.abs()).fold(1.0, f64::max) {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            let (pivot_rows, rows) = a.split_at_mut(row);
+            for (value, pivot) in rows[0][col..].iter_mut().zip(&pivot_rows[col][col..]) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).map(|k| a[row][k] * x[k]).sum::<f64>();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Fit the calibrated coefficients to the samples by least squares, with times in nanoseconds.
+/// Coefficients that no sample depends on keep their default values, negative fits are clamped to
+/// 0, and the result is scaled so that `cpu_tuple` is 1. Returns the default model if the samples
+/// don't determine the coefficients.
+pub fn fit_cost_model(samples: &[OperatorSample]) -> CostModel {
+    let rows = samples
+        .iter()
+        .map(|sample| (CostModel::cost_terms(sample), sample.time.as_nanos() as f64))
+        .collect::<Vec<_>>();
+    let active = (0..CALIBRATED.len())
+        .filter(|idx| rows.iter().any(|(terms, _)| terms[*idx] != 0.0))
+        .collect::<Vec<_>>();
+    // the normal equations: (AᵀA) x = Aᵀt
+    let ata = active
+        .iter()
+        .map(|i| {
+            active
+                .iter()
+                .map(|j| rows.iter().map(|(terms, _)| terms[*i] * terms[*j]).sum())
+                .collect()
+        })
+        .collect();
+    let atb = active
+        .iter()
+        .map(|i| rows.iter().map(|(terms, time)| terms[*i] * time).sum())
+        .collect();
+    let mut model = CostModel::default();
+    let Some(fit) = solve(ata, atb) else {
+        return model;
+    };
+    let fitted = |name| {
+        let idx = CALIBRATED.iter().position(|n| *n == name).unwrap();
+        let pos = active.iter().position(|a| *a == idx)?;
+        Some(fit[pos].max(0.0))
+    };
+    let Some(cpu_tuple) = fitted("cpu_tuple").filter(|cpu| *cpu > 0.0) else {
+        return model;
+    };
+    for name in CALIBRATED {
+        if let Some(value) = fitted(name) {
+            *model.coefficient_mut(name).unwrap() = value / cpu_tuple;
+        }
+    }
+    model
+}
+
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    pub samples: Vec<OperatorSample>,
+    pub cost_model: CostModel,
+}
+
+/// Run each plan of the workload `runs` times, and fit the cost model to all samples. Plans the
+/// vectorized executor doesn't support are skipped.
+pub fn calibrate(workload: &[Arc<RelNode>], db: &ColumnarDatabase, runs: usize) -> Calibration {
+    let samples = workload
+        .iter()
+        .flat_map(|rel| (0..runs).filter_map(|_| profile_plan(rel, db)))
+        .flatten()
+        .collect::<Vec<_>>();
+    let cost_model = fit_cost_model(&samples);
+    Calibration {
+        samples,
+        cost_model,
+    }
+}
+
+impl Optimizer<'_> {
+    /// Use the cost model saved in a file, e.g. by a calibration.
+    pub fn load_cost_model(&mut self, path: &Path) -> Result<(), CostModelError> {
+        self.cost_model = CostModel::load(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::abc_catalog;
+
+    fn catalog_and_data() -> (Catalog, ColumnarDatabase) {
+        let mut catalog = abc_catalog();
+        let (a, b) = (TableId(0), TableId(1));
+        catalog.set_row_count(&a, 2000.0);
+        catalog.set_row_count(&b, 500.0);
+        let mut db = Database::from_catalog(&catalog);
+        db.insert(
+            &a,
+            (0..2000).map(|id| vec![Some(id), Some(id % 7)]).collect(),
+        );
+        db.insert(
+            &b,
+            (0..500)
+                .map(|id| vec![Some(id), Some(id * 3), Some(id % 2)])
+                .collect(),
+        );
+        let db = ColumnarDatabase::load(&db, &catalog);
+        (catalog, db)
+    }
+
+    fn workload(catalog: &Catalog) -> Vec<Arc<RelNode>> {
+        let join = join(
+            scan(TableId(0)),
+            scan(TableId(1)),
+            eq_pred(column_ref_pred(0), column_ref_pred(3)),
+        );
+        let asc = |column| SortKey {
+            column,
+            descending: false,
+        };
+        let count = AggCall {
+            func: AggFunc::Count,
+            column: None,
+        };
+        [
+            filter(scan(TableId(0)), eq_pred(column_ref_pred(1), const_pred(3))),
+            join.clone(),
+            sort(aggregate(join, vec![1], vec![count]), vec![asc(1)]),
+            sort(scan(TableId(1)), vec![asc(1), asc(0)]),
+        ]
+        .into_iter()
+        .map(|rel| heuristic_plan(&Arc::new(rel), catalog))
+        .collect()
+    }
+
+    #[test]
+    fn test_fit_cost_model() {
+        let (catalog, db) = catalog_and_data();
+        let samples = workload(&catalog)
+            .iter()
+            .flat_map(|rel| profile_plan(rel, &db).unwrap())
+            .collect::<Vec<_>>();
+        // every row of b finds its row of a
+        let join = samples
+            .iter()
+            .find(|sample| matches!(sample.operator, MemoRelNode::HashJoin(_)))
+            .unwrap();
+        assert_eq!(
+            (join.input_rows.as_slice(), join.output_rows),
+            (&[2000, 500][..], 500)
+        );
+
+        // times that follow a known model exactly
+        let truth = [2.0, 6.0, 3.0, 1.0];
+        let synthetic = samples
+            .into_iter()
+            .map(|sample| {
+                let terms = CostModel::cost_terms(&sample);
+                let nanos = terms.iter().zip(truth).map(|(t, c)| t * c).sum::<f64>();
+                OperatorSample {
+                    time: Duration::from_nanos(nanos.round() as u64),
+                    ..sample
+                }
+            })
+            .collect::<Vec<_>>();
+        let model = fit_cost_model(&synthetic);
+        for (name, expected) in CALIBRATED.iter().zip([1.0, 3.0, 1.5, 0.5]) {
+            let value = model
+                .coefficients()
+                .iter()
+                .find(|(n, _)| n == name)
+                .unwrap()
+                .1;
+            assert!((value - expected).abs() < 1e-3, "{name}: {value}");
+        }
+        assert_eq!(model.index_probe, CostModel::default().index_probe);
+    }
+
+    #[test]
+    fn test_calibrate_and_load() {
+        let (catalog, db) = catalog_and_data();
+        let calibration = calibrate(&workload(&catalog), &db, 3);
+        let model = calibration.cost_model;
+        assert_eq!(model.cpu_tuple, 1.0);
+        assert!(model
+            .coefficients()
+            .iter()
+            .all(|(_, value)| value.is_finite() && *value >= 0.0));
+        // the fit used the measured times instead of falling back to the defaults
+        assert!(!calibration.samples.is_empty());
+        assert_ne!(
+            model.coefficients(),
+            CostModel::default().coefficients(),
+            "{}",
+            model.to_text()
+        );
+
+        let path = std::env::temp_dir().join(format!("cost_model_{}.txt", std::process::id()));
+        model.save(&path).unwrap();
+        let mut optimizer = Optimizer::new(&catalog);
+        optimizer.load_cost_model(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(optimizer.cost_model.to_text(), model.to_text());
+
+        let parsed = CostModel::parse("# calibrated\nhash_build = 4\n").unwrap();
+        assert_eq!(parsed.hash_build, 4.0);
+        assert_eq!(parsed.sort, CostModel::default().sort);
+        let error = |text| CostModel::parse(text).unwrap_err().to_string();
+        assert_eq!(error("sort = fast"), "1:8: expected a number, found `fast`");
+        assert_eq!(error("\n  disk = 1"), "2:3: unknown coefficient `disk`");
+    }
+}
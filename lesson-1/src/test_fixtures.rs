@@ -0,0 +1,103 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::*;
+
+// Tables, statistics and data used by the tests of several lessons.
+
+/// Tables `0..` with `columns` columns each and the given row counts.
+pub(crate) fn stats(
+    columns: usize,
+    rows: impl IntoIterator<Item = f64>,
+) -> HashMap<TableId, TableInfo> {
+    rows.into_iter()
+        .enumerate()
+        .map(|(idx, rows)| (TableId(idx), TableInfo { columns, rows }))
+        .collect()
+}
+
+/// Join `t0 ... tn` of two columns each left-deep, with `edges` listing the tables joined on their
+/// first column.
+pub(crate) fn join_tables(n: usize, edges: &[(usize, usize)]) -> Arc<RelNode> {
+    let mut node = Arc::new(scan(TableId(0)));
+    for idx in 1..n {
+        let preds = edges
+            .iter()
+            .filter(|(_, right)| *right == idx)
+            .map(|(left, right)| {
+                Arc::new(eq_pred(
+                    column_ref_pred(left * 2),
+                    column_ref_pred(right * 2),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let cond = fold_and(preds).unwrap();
+        node = Arc::new(join(node, scan(TableId(idx)), cond));
+    }
+    node
+}
+
+fn int(name: &str) -> ColumnDef {
+    ColumnDef::new(name, DataType::Int)
+}
+
+/// `customers(id, region)` and `orders(id, customer_id, amount)`, both keyed by `id`.
+pub(crate) fn customers_orders() -> (Catalog, TableId, TableId) {
+    let mut catalog = Catalog::new();
+    let customers = catalog
+        .add_table("customers", vec![int("id"), int("region")], 100.0)
+        .unwrap();
+    let orders = catalog
+        .add_table(
+            "orders",
+            vec![int("id"), int("customer_id").not_null(), int("amount")],
+            10000.0,
+        )
+        .unwrap();
+    catalog.add_primary_key(&customers, &["id"]).unwrap();
+    catalog.add_primary_key(&orders, &["id"]).unwrap();
+    (catalog, customers, orders)
+}
+
+/// `a(id, x)` keyed by `id`, `b(id, a_id, flag)` referencing `a`, and `c(y)`.
+pub(crate) fn abc_catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+    let a = catalog
+        .add_table("a", vec![int("id"), int("x")], 1200.0)
+        .unwrap();
+    let b = catalog
+        .add_table(
+            "b",
+            vec![
+                int("id"),
+                int("a_id"),
+                ColumnDef::new("flag", DataType::Bool),
+            ],
+            300.0,
+        )
+        .unwrap();
+    catalog.add_table("c", vec![int("y")], 30.0).unwrap();
+    catalog.add_primary_key(&a, &["id"]).unwrap();
+    catalog.add_foreign_key(&b, &["a_id"], &a, &["id"]).unwrap();
+    catalog
+}
+
+/// `abc_catalog` with as many random rows as its statistics say, some of them NULL.
+pub(crate) fn abc_catalog_and_data() -> (Catalog, Database) {
+    let catalog = abc_catalog();
+    let mut rng = Rng::new(1);
+    let mut value = |max: usize| match rng.gen_range(20) {
+        0 => None,
+        _ => Some(rng.gen_range(max) as i64),
+    };
+    let mut db = Database::from_catalog(&catalog);
+    let [a, b, c] = [0, 1, 2].map(TableId);
+    // more rows than a batch
+    let rows = (0..1200).map(|id| vec![Some(id), value(50)]).collect();
+    db.insert(&a, rows);
+    let rows = (0..300)
+        .map(|id| vec![Some(id), value(50), value(2)])
+        .collect();
+    db.insert(&b, rows);
+    db.insert(&c, (0..30).map(|_| vec![value(50)]).collect());
+    (catalog, db)
+}